@@ -0,0 +1,26 @@
+use std::env;
+use std::process::Command;
+
+fn main() {
+    // Plugins must be built by the same compiler, since they hand us Rust
+    // trait objects; record its version for the check in `plugin.rs`.
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_default();
+
+    println!("cargo:rustc-env=VRL_TEST_RUSTC_VERSION={}", version.trim());
+    println!("cargo:rerun-if-env-changed=RUSTC");
+
+    // `--all-features` enables every allocator feature, so pick the one
+    // `alloc.rs` installs here: any other choice wins over the default.
+    let allocator = ["system", "mimalloc", "jemalloc"]
+        .into_iter()
+        .find(|name| env::var_os(format!("CARGO_FEATURE_ALLOC_{}", name.to_uppercase())).is_some())
+        .unwrap_or("system");
+    println!(r#"cargo:rustc-check-cfg=cfg(allocator, values("system", "mimalloc", "jemalloc"))"#);
+    println!(r#"cargo:rustc-cfg=allocator="{allocator}""#);
+}
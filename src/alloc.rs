@@ -0,0 +1,162 @@
+//! The global allocator, counting the allocations of each thread so
+//! benchmarks can report how many an event costs, along with the memory
+//! the allocator holds as a whole.
+//!
+//! The allocator counting is wrapped around is jemalloc, mimalloc or the
+//! system's, as selected by the `alloc-*` features; `build.rs` sets the
+//! `allocator` cfg to the one that wins. Only jemalloc reports its memory.
+//!
+//! The binary installs [`CountingAllocator`]; crates using the library
+//! install it themselves to get allocation counts in bench reports.
+
+use anyhow::{anyhow, Result};
+use std::alloc::{GlobalAlloc, Layout};
+use std::cell::Cell;
+#[cfg(allocator = "jemalloc")]
+use tikv_jemalloc_ctl::{epoch, stats};
+
+#[cfg(allocator = "mimalloc")]
+use mimalloc::MiMalloc as Inner;
+#[cfg(allocator = "system")]
+use std::alloc::System as Inner;
+#[cfg(allocator = "jemalloc")]
+use tikv_jemallocator::Jemalloc as Inner;
+
+#[cfg(test)]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+thread_local! {
+    static ALLOCATED: Cell<Allocations> = const { Cell::new(Allocations { count: 0, bytes: 0 }) };
+}
+
+/// Allocations made by a thread: reallocations count as one allocation of
+/// the new size, and frees aren't subtracted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Allocations {
+    pub(crate) count: u64,
+    pub(crate) bytes: u64,
+}
+
+impl Allocations {
+    /// The allocations the current thread made so far.
+    pub(crate) fn current() -> Self {
+        ALLOCATED.try_with(Cell::get).unwrap_or_default()
+    }
+
+    /// The allocations made since `earlier`, taken on the same thread.
+    pub(crate) fn since(self, earlier: Self) -> Self {
+        Self {
+            count: self.count - earlier.count,
+            bytes: self.bytes - earlier.bytes,
+        }
+    }
+}
+
+impl std::ops::AddAssign for Allocations {
+    fn add_assign(&mut self, other: Self) {
+        self.count += other.count;
+        self.bytes += other.bytes;
+    }
+}
+
+/// The bytes allocated by the whole process and the bytes of physical memory
+/// jemalloc holds for them, as of the last [`MemoryStats::read`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct MemoryStats {
+    pub(crate) allocated: u64,
+    pub(crate) resident: u64,
+}
+
+impl MemoryStats {
+    /// Refreshes the statistics of jemalloc, which it only updates on demand,
+    /// and reads them.
+    #[cfg(allocator = "jemalloc")]
+    pub(crate) fn read() -> Result<Self> {
+        let failed = |err| anyhow!("failed to read jemalloc stats: {err}");
+        epoch::advance().map_err(failed)?;
+        Ok(Self {
+            allocated: stats::allocated::read().map_err(failed)? as u64,
+            resident: stats::resident::read().map_err(failed)? as u64,
+        })
+    }
+
+    #[cfg(not(allocator = "jemalloc"))]
+    pub(crate) fn read() -> Result<Self> {
+        Err(anyhow!("memory stats are only reported by jemalloc"))
+    }
+
+    /// How much memory grew since `earlier`, negative when it shrank.
+    pub(crate) fn since(self, earlier: Self) -> (i64, i64) {
+        (
+            self.allocated as i64 - earlier.allocated as i64,
+            self.resident as i64 - earlier.resident as i64,
+        )
+    }
+}
+
+fn record(size: usize) {
+    // a thread being torn down has no counter left; its last allocations
+    // don't matter
+    let _ = ALLOCATED.try_with(|allocated| {
+        let Allocations { count, bytes } = allocated.get();
+        allocated.set(Allocations {
+            count: count + 1,
+            bytes: bytes + size as u64,
+        });
+    });
+}
+
+/// The global allocator selected by the `alloc-*` features, counting the
+/// allocations of each thread.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CountingAllocator;
+
+// SAFETY: every call is forwarded to the inner allocator as is; counting
+// doesn't allocate.
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        Inner.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        Inner.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(new_size);
+        Inner.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        Inner.dealloc(ptr, layout)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn counts_allocations_of_the_thread() {
+        let before = Allocations::current();
+        let bytes = std::hint::black_box(vec![0u8; 100]);
+        let mut grown = bytes.clone();
+        grown.extend_from_slice(&bytes);
+        let allocations = Allocations::current().since(before);
+
+        assert_eq!(allocations.count, 3);
+        assert!(allocations.bytes >= 300);
+    }
+
+    #[test]
+    #[cfg(allocator = "jemalloc")]
+    fn reads_memory_stats() {
+        let stats = MemoryStats::read().unwrap();
+
+        assert!(stats.allocated > 0);
+        assert!(stats.resident >= stats.allocated);
+    }
+}
@@ -0,0 +1,70 @@
+//! Scratch memory for what custom functions only need while an event is
+//! resolved, such as the matches `split` collects before cutting segments.
+//!
+//! With the `arena` feature, scratch allocations come from a bump arena of
+//! the resolving thread that the pipeline resets before each event, and the
+//! REPL before each line, so they cost a pointer bump rather than a trip
+//! through jemalloc and are never freed one by one. Without it, they come
+//! from the global allocator. VRL values always come from the global
+//! allocator, as they outlive the event in the output or in the state of
+//! functions.
+
+#[cfg(feature = "arena")]
+use bumpalo::Bump;
+#[cfg(feature = "arena")]
+use std::cell::RefCell;
+
+/// The allocator of scratch memory.
+#[cfg(feature = "arena")]
+pub(crate) type Scratch<'a> = &'a Bump;
+#[cfg(not(feature = "arena"))]
+pub(crate) type Scratch<'a> = &'a allocator_api2::alloc::Global;
+
+/// A vector in scratch memory.
+pub(crate) type ScratchVec<'a, T> = allocator_api2::vec::Vec<T, Scratch<'a>>;
+
+#[cfg(feature = "arena")]
+thread_local! {
+    static ARENA: RefCell<Bump> = RefCell::new(Bump::new());
+}
+
+/// Runs `f` with the scratch allocator of the thread; what it allocates must
+/// not outlive the event being resolved.
+pub(crate) fn scratch<R>(f: impl FnOnce(Scratch<'_>) -> R) -> R {
+    #[cfg(feature = "arena")]
+    return ARENA.with_borrow(|arena| f(arena));
+    #[cfg(not(feature = "arena"))]
+    f(&allocator_api2::alloc::Global)
+}
+
+/// Collects `items` into scratch memory.
+pub(crate) fn collect<'a, T>(
+    items: impl IntoIterator<Item = T>,
+    scratch: Scratch<'a>,
+) -> ScratchVec<'a, T> {
+    let mut collected = ScratchVec::new_in(scratch);
+    collected.extend(items);
+    collected
+}
+
+/// Frees the scratch memory of the thread at once, keeping its largest chunk
+/// for the next event. Called by the pipeline before each event and by the
+/// REPL before each line, outside of any function call.
+pub(crate) fn reset() {
+    #[cfg(feature = "arena")]
+    ARENA.with_borrow_mut(Bump::reset);
+}
+
+#[cfg(all(test, feature = "arena"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reuses_memory_once_reset() {
+        let first = scratch(|scratch| collect(0..100u64, scratch).as_ptr() as usize);
+        reset();
+        let second = scratch(|scratch| collect(0..100u64, scratch).as_ptr() as usize);
+
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,982 @@
+//! Benchmarks a pipeline by resolving it repeatedly against a corpus of
+//! events or looping over input files for a while, or the compilation of a
+//! corpus of programs.
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use clap::ValueEnum;
+use hdrhistogram::Histogram;
+use log::warn;
+use serde_json::json;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use vrl::compiler::Function;
+use vrl::value::Value;
+
+use crate::alloc::{Allocations, MemoryStats};
+use crate::input::{Decoding, Input};
+use crate::output::OutputFormat;
+use crate::pipeline::Pipeline;
+use crate::program::compile_source;
+use crate::state::RunState;
+
+/// The number of events memory growth is reported per.
+const MEMORY_EVENTS: usize = 1000;
+
+/// The longest resolve latency recorded, in nanoseconds; longer ones are
+/// recorded as this.
+const MAX_LATENCY: u64 = 60 * 1_000_000_000;
+
+/// A histogram of nanosecond latencies from 1ns to [`MAX_LATENCY`], precise
+/// to 3 significant digits.
+fn latency_histogram() -> Histogram<u64> {
+    Histogram::new_with_bounds(1, MAX_LATENCY, 3).expect("histogram bounds are valid")
+}
+
+/// How benchmark reports are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BenchFormat {
+    Text,
+    Json,
+}
+
+/// How long a benchmark resolves the corpus for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchOptions {
+    /// Events resolved before measuring anything.
+    pub warmup: usize,
+    /// Events resolved at least.
+    pub iterations: usize,
+    /// Time spent resolving at least.
+    pub min_time: Duration,
+}
+
+/// Durations of a repeated operation, sorted.
+#[derive(Debug)]
+struct Latencies(Vec<Duration>);
+
+impl Latencies {
+    fn new(mut latencies: Vec<Duration>) -> Self {
+        latencies.sort_unstable();
+        Self(latencies)
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn mean(&self) -> Duration {
+        match u32::try_from(self.0.len()) {
+            Ok(0) | Err(_) => Duration::ZERO,
+            Ok(count) => self.0.iter().sum::<Duration>() / count,
+        }
+    }
+
+    /// The latency `percentile` percent of the operations took at most.
+    fn percentile(&self, percentile: f64) -> Duration {
+        let rank = (self.0.len() as f64 * percentile / 100.0).ceil() as usize;
+        self.0
+            .get(rank.saturating_sub(1))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn max(&self) -> Duration {
+        self.0.last().copied().unwrap_or_default()
+    }
+}
+
+/// Per-event latencies and totals of a benchmark.
+#[derive(Debug)]
+pub struct BenchReport {
+    /// Time taken to compile every stage.
+    compile: Duration,
+    /// Number of events in the corpus.
+    corpus: usize,
+    /// How long each event took to resolve, in nanoseconds.
+    latencies: Histogram<u64>,
+    /// Allocations made while resolving, over every event.
+    allocations: Allocations,
+    /// How much the allocated and resident bytes of the process grew over
+    /// the resolve loop, unless the allocator doesn't report them.
+    memory: Option<(i64, i64)>,
+    /// Events the pipeline failed or aborted on.
+    failed: usize,
+    /// Wall-clock time of the resolve loop.
+    total: Duration,
+}
+
+/// Resolves `options.warmup` events of `corpus` through `pipeline`, then
+/// every event of `corpus` over and over until at least `options.iterations`
+/// were resolved and `options.min_time` has passed. Events are cloned outside
+/// of the measured time, and the allocations counted are those of the thread
+/// resolving them.
+pub(crate) fn bench(
+    pipeline: &mut Pipeline,
+    corpus: &[Value],
+    options: &BenchOptions,
+) -> BenchReport {
+    for event in corpus.iter().cycle().take(options.warmup) {
+        let _ = pipeline.resolve(event.clone());
+    }
+
+    let mut latencies = latency_histogram();
+    let mut allocations = Allocations::default();
+    let mut failed = 0;
+
+    let memory = MemoryStats::read();
+    let start = Instant::now();
+    while !corpus.is_empty()
+        && (latencies.len() < options.iterations as u64 || start.elapsed() < options.min_time)
+    {
+        for event in corpus {
+            let event = event.clone();
+            let allocated = Allocations::current();
+            let event_start = Instant::now();
+            let result = pipeline.resolve(event);
+            let elapsed = event_start.elapsed();
+            latencies.saturating_record(elapsed.as_nanos().try_into().unwrap_or(MAX_LATENCY));
+            allocations += Allocations::current().since(allocated);
+            failed += usize::from(result.is_err());
+        }
+    }
+    let total = start.elapsed();
+    let memory = memory
+        .and_then(|before| Ok(MemoryStats::read()?.since(before)))
+        .map_err(|err| warn!("{err:#}"))
+        .ok();
+
+    BenchReport {
+        compile: pipeline
+            .stages()
+            .iter()
+            .map(|stage| stage.compile_time)
+            .sum(),
+        corpus: corpus.len(),
+        latencies,
+        allocations,
+        memory,
+        failed,
+        total,
+    }
+}
+
+/// Runs `bench`, sampling the stacks of the process meanwhile and writing a
+/// flamegraph of them to `flamegraph` when given.
+#[cfg(feature = "flamegraph")]
+pub(crate) fn sampled<T>(flamegraph: Option<&Path>, bench: impl FnOnce() -> T) -> Result<T> {
+    let Some(path) = flamegraph else {
+        return Ok(bench());
+    };
+    let guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(997)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()
+        .context("failed to start sampling the process")?;
+    let result = bench();
+    let report = guard
+        .report()
+        .build()
+        .context("failed to collect the samples")?;
+    drop(guard);
+
+    let file =
+        fs::File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    report
+        .flamegraph(io::BufWriter::new(file))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(result)
+}
+
+#[cfg(not(feature = "flamegraph"))]
+pub(crate) fn sampled<T>(_flamegraph: Option<&Path>, bench: impl FnOnce() -> T) -> Result<T> {
+    Ok(bench())
+}
+
+impl BenchReport {
+    pub fn render(&self, format: BenchFormat) -> String {
+        match format {
+            BenchFormat::Text => self.render_text(),
+            BenchFormat::Json => self.to_json().to_string(),
+        }
+    }
+
+    fn events(&self) -> usize {
+        self.latencies.len() as usize
+    }
+
+    fn mean(&self) -> Duration {
+        Duration::from_nanos(self.latencies.mean() as u64)
+    }
+
+    /// The latency `percentile` percent of the events resolved within.
+    fn percentile(&self, percentile: f64) -> Duration {
+        Duration::from_nanos(self.latencies.value_at_percentile(percentile))
+    }
+
+    /// Allocations and allocated bytes per event.
+    fn allocations_per_event(&self) -> (f64, f64) {
+        let events = self.events().max(1) as f64;
+        (
+            self.allocations.count as f64 / events,
+            self.allocations.bytes as f64 / events,
+        )
+    }
+
+    /// How much the allocated and resident bytes grew per
+    /// [`MEMORY_EVENTS`] events.
+    fn memory_per_events(&self) -> Option<(f64, f64)> {
+        let (allocated, resident) = self.memory?;
+        let per = self.events().max(1) as f64 / MEMORY_EVENTS as f64;
+        Some((allocated as f64 / per, resident as f64 / per))
+    }
+
+    /// Events resolved per second of the resolve loop.
+    fn throughput(&self) -> f64 {
+        match self.total.as_secs_f64() {
+            0.0 => 0.0,
+            secs => self.events() as f64 / secs,
+        }
+    }
+
+    fn render_text(&self) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "compile: {:?}", self.compile);
+        let _ = writeln!(
+            out,
+            "resolve: {} events ({} passes over {} events) in {:?}",
+            self.events(),
+            self.events() / self.corpus.max(1),
+            self.corpus,
+            self.total,
+        );
+        let _ = writeln!(
+            out,
+            "latency: mean {:?}, median {:?}, p90 {:?}, p99 {:?}, p99.9 {:?}, max {:?}",
+            self.mean(),
+            self.percentile(50.0),
+            self.percentile(90.0),
+            self.percentile(99.0),
+            self.percentile(99.9),
+            Duration::from_nanos(self.latencies.max()),
+        );
+        let (count, bytes) = self.allocations_per_event();
+        let _ = writeln!(out, "allocations: {count:.1} per event, {bytes:.0} bytes");
+        if let Some((allocated, resident)) = self.memory_per_events() {
+            let _ = writeln!(
+                out,
+                "memory: allocated {allocated:+.0} bytes, resident {resident:+.0} bytes per {MEMORY_EVENTS} events"
+            );
+        }
+        if self.failed > 0 {
+            let _ = writeln!(out, "failed: {} events", self.failed);
+        }
+        let _ = write!(out, "throughput: {:.0} events/s", self.throughput());
+
+        out
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let nanos = |duration: Duration| duration.as_nanos() as u64;
+        let (allocations, allocated_bytes) = self.allocations_per_event();
+
+        json!({
+            "compile_ns": nanos(self.compile),
+            "corpus": self.corpus,
+            "events": self.events(),
+            "total_ns": nanos(self.total),
+            "latency": {
+                "mean_ns": nanos(self.mean()),
+                "median_ns": nanos(self.percentile(50.0)),
+                "p90_ns": nanos(self.percentile(90.0)),
+                "p99_ns": nanos(self.percentile(99.0)),
+                "p999_ns": nanos(self.percentile(99.9)),
+                "max_ns": self.latencies.max(),
+            },
+            "allocations": allocations,
+            "allocated_bytes": allocated_bytes,
+            "memory": self.memory_per_events().map(|(allocated, resident)| json!({
+                "events": MEMORY_EVENTS,
+                "allocated_bytes": allocated,
+                "resident_bytes": resident,
+            })),
+            "failed": self.failed,
+            "events_per_sec": self.throughput(),
+        })
+    }
+
+    /// The figures `--baseline` compares later runs with.
+    pub(crate) fn baseline(&self) -> Baseline {
+        let (allocations, allocated_bytes) = self.allocations_per_event();
+        Baseline {
+            mean: self.mean(),
+            median: self.percentile(50.0),
+            p99: self.percentile(99.0),
+            allocations,
+            allocated_bytes,
+        }
+    }
+
+    /// Writes the latency distribution in the percentile format of
+    /// HdrHistogram, with values in microseconds, for plotting tools reading
+    /// `.hgrm` files.
+    pub(crate) fn write_histogram(&self, mut out: impl io::Write) -> io::Result<()> {
+        let micros = |nanos: f64| nanos / 1000.0;
+        writeln!(
+            out,
+            "{:>12} {:>14} {:>10} {:>14}\n",
+            "Value", "Percentile", "TotalCount", "1/(1-Percentile)"
+        )?;
+        let mut total = 0;
+        for value in self.latencies.iter_quantiles(5) {
+            total += value.count_since_last_iteration();
+            let quantile = value.quantile_iterated_to();
+            let value = micros(value.value_iterated_to() as f64);
+            match quantile < 1.0 {
+                true => writeln!(
+                    out,
+                    "{value:12.3} {quantile:14.12} {total:10} {:14.2}",
+                    1.0 / (1.0 - quantile)
+                )?,
+                false => writeln!(out, "{value:12.3} {quantile:14.12} {total:10}")?,
+            }
+        }
+        writeln!(
+            out,
+            "#[Mean    = {:12.3}, StdDeviation   = {:12.3}]",
+            micros(self.latencies.mean()),
+            micros(self.latencies.stdev())
+        )?;
+        writeln!(
+            out,
+            "#[Max     = {:12.3}, Total count    = {:12}]",
+            micros(self.latencies.max() as f64),
+            self.latencies.len()
+        )?;
+        writeln!(
+            out,
+            "#[Buckets = {:12}, SubBuckets     = {:12}]",
+            self.latencies.buckets(),
+            self.latencies.distinct_values()
+        )
+    }
+
+    /// Renders `stdlib` and `custom`, benchmarks of the same program with the
+    /// stock and the overridden functions, side by side, along with how much
+    /// the custom functions change each figure.
+    pub(crate) fn render_comparison(stdlib: &Self, custom: &Self, format: BenchFormat) -> String {
+        if format == BenchFormat::Json {
+            return json!({"stdlib": stdlib.to_json(), "custom": custom.to_json()}).to_string();
+        }
+
+        let nanos = |duration: Duration| duration.as_nanos() as f64;
+        let (stdlib_count, stdlib_bytes) = stdlib.allocations_per_event();
+        let (custom_count, custom_bytes) = custom.allocations_per_event();
+        let rows = [
+            (
+                "compile",
+                format!("{:?}", stdlib.compile),
+                format!("{:?}", custom.compile),
+                nanos(stdlib.compile),
+                nanos(custom.compile),
+            ),
+            (
+                "mean",
+                format!("{:?}", stdlib.mean()),
+                format!("{:?}", custom.mean()),
+                nanos(stdlib.mean()),
+                nanos(custom.mean()),
+            ),
+            (
+                "median",
+                format!("{:?}", stdlib.percentile(50.0)),
+                format!("{:?}", custom.percentile(50.0)),
+                nanos(stdlib.percentile(50.0)),
+                nanos(custom.percentile(50.0)),
+            ),
+            (
+                "p99",
+                format!("{:?}", stdlib.percentile(99.0)),
+                format!("{:?}", custom.percentile(99.0)),
+                nanos(stdlib.percentile(99.0)),
+                nanos(custom.percentile(99.0)),
+            ),
+            (
+                "allocations",
+                format!("{stdlib_count:.1}"),
+                format!("{custom_count:.1}"),
+                stdlib_count,
+                custom_count,
+            ),
+            (
+                "allocated bytes",
+                format!("{stdlib_bytes:.0}"),
+                format!("{custom_bytes:.0}"),
+                stdlib_bytes,
+                custom_bytes,
+            ),
+            (
+                "events/s",
+                format!("{:.0}", stdlib.throughput()),
+                format!("{:.0}", custom.throughput()),
+                stdlib.throughput(),
+                custom.throughput(),
+            ),
+        ];
+
+        let mut out = format!(
+            "{:16}  {:>12}  {:>12}  {:>8}",
+            "", "stdlib", "custom", "change"
+        );
+        for (name, stdlib, custom, before, after) in rows {
+            let change = match before {
+                0.0 => "-".to_owned(),
+                before => format!("{:+.1}%", (after - before) / before * 100.0),
+            };
+            let _ = write!(out, "\n{name:16}  {stdlib:>12}  {custom:>12}  {change:>8}");
+        }
+        for (name, report) in [("stdlib", stdlib), ("custom", custom)] {
+            if report.failed > 0 {
+                let _ = write!(out, "\n{name}: failed on {} events", report.failed);
+            }
+        }
+        out
+    }
+}
+
+/// Latency and allocations of a benchmark, saved with `--save-baseline` for
+/// later runs to be checked against with `--baseline`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Baseline {
+    mean: Duration,
+    median: Duration,
+    p99: Duration,
+    /// Allocations per event.
+    allocations: f64,
+    /// Allocated bytes per event.
+    allocated_bytes: f64,
+}
+
+impl Baseline {
+    /// Where the baseline called `name` is saved in `dir`.
+    pub(crate) fn path(dir: &Path, name: &str) -> std::path::PathBuf {
+        dir.join(format!("{name}.json"))
+    }
+
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read baseline {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Self::from_json(&json))
+            .with_context(|| format!("invalid baseline {}", path.display()))
+    }
+
+    /// Saves the baseline to `path`, creating its directory when needed.
+    pub(crate) fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        fs::write(path, format!("{:#}\n", self.to_json()))
+            .with_context(|| format!("failed to write baseline {}", path.display()))
+    }
+
+    fn to_json(self) -> serde_json::Value {
+        let nanos = |duration: Duration| duration.as_nanos() as u64;
+
+        json!({
+            "mean_ns": nanos(self.mean),
+            "median_ns": nanos(self.median),
+            "p99_ns": nanos(self.p99),
+            "allocations": self.allocations,
+            "allocated_bytes": self.allocated_bytes,
+        })
+    }
+
+    fn from_json(json: &serde_json::Value) -> Result<Self> {
+        let field = |name: &str| {
+            json[name]
+                .as_f64()
+                .ok_or_else(|| anyhow!("missing number `{name}`"))
+        };
+        let nanos = |name: &str| Ok::<_, anyhow::Error>(Duration::from_nanos(field(name)? as u64));
+
+        Ok(Self {
+            mean: nanos("mean_ns")?,
+            median: nanos("median_ns")?,
+            p99: nanos("p99_ns")?,
+            allocations: field("allocations")?,
+            allocated_bytes: field("allocated_bytes")?,
+        })
+    }
+
+    /// Renders how `current` changed from the baseline, figure by figure,
+    /// and returns how many of them grew by more than `threshold` percent.
+    pub(crate) fn compare(&self, current: &Self, threshold: f64) -> (String, usize) {
+        let nanos = |duration: Duration| duration.as_nanos() as f64;
+        let rows = [
+            (
+                "mean",
+                format!("{:?}", self.mean),
+                format!("{:?}", current.mean),
+                nanos(self.mean),
+                nanos(current.mean),
+            ),
+            (
+                "median",
+                format!("{:?}", self.median),
+                format!("{:?}", current.median),
+                nanos(self.median),
+                nanos(current.median),
+            ),
+            (
+                "p99",
+                format!("{:?}", self.p99),
+                format!("{:?}", current.p99),
+                nanos(self.p99),
+                nanos(current.p99),
+            ),
+            (
+                "allocations",
+                format!("{:.1}", self.allocations),
+                format!("{:.1}", current.allocations),
+                self.allocations,
+                current.allocations,
+            ),
+            (
+                "allocated bytes",
+                format!("{:.0}", self.allocated_bytes),
+                format!("{:.0}", current.allocated_bytes),
+                self.allocated_bytes,
+                current.allocated_bytes,
+            ),
+        ];
+
+        let mut out = format!(
+            "{:16}  {:>12}  {:>12}  {:>8}",
+            "", "baseline", "current", "change"
+        );
+        let mut regressions = 0;
+        for (name, baseline, current, before, after) in rows {
+            let change = match before {
+                0.0 => 0.0,
+                before => (after - before) / before * 100.0,
+            };
+            let _ = write!(
+                out,
+                "\n{name:16}  {baseline:>12}  {current:>12}  {change:>+7.1}%"
+            );
+            // a figure growing from nothing counts too
+            if change > threshold || (before == 0.0 && after > 0.0) {
+                out.push_str("  regressed");
+                regressions += 1;
+            }
+        }
+        (out, regressions)
+    }
+}
+
+/// Events and bytes a throughput benchmark went through.
+#[derive(Debug)]
+pub(crate) struct ThroughputReport {
+    events: u64,
+    /// Events that failed to decode.
+    invalid: u64,
+    /// Events the pipeline failed or aborted on.
+    failed: u64,
+    /// Size of the input files read, prorated for the one being read when
+    /// time ran out.
+    bytes: f64,
+    /// How many times the inputs were read through.
+    passes: f64,
+    elapsed: Duration,
+}
+
+/// Reads the events of `inputs`, resolves them through `pipeline` and
+/// encodes the transformed events as compact JSON, over and over until
+/// `duration` has passed.
+pub(crate) fn bench_throughput(
+    pipeline: &mut Pipeline,
+    inputs: &[Input],
+    decoding: &Decoding,
+    duration: Duration,
+) -> Result<ThroughputReport> {
+    let sizes = inputs
+        .iter()
+        .map(|input| match input {
+            Input::File(path) => Ok(fs::metadata(path)?.len()),
+            Input::Stdin => bail!("stdin can't be read over and over"),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let total_size = sizes.iter().sum::<u64>() as f64;
+    // the number of events of each input, once read through
+    let mut counts: Vec<Option<u64>> = vec![None; inputs.len()];
+    let mut report = ThroughputReport {
+        events: 0,
+        invalid: 0,
+        failed: 0,
+        bytes: 0.0,
+        passes: 0.0,
+        elapsed: Duration::ZERO,
+    };
+
+    let start = Instant::now();
+    'passes: loop {
+        for (index, input) in inputs.iter().enumerate() {
+            let mut events = 0;
+            for event in input.open(decoding)? {
+                if start.elapsed() >= duration {
+                    if let Some(count) = counts[index] {
+                        let read = sizes[index] as f64 * events as f64 / count.max(1) as f64;
+                        report.bytes += read;
+                        report.passes += read / total_size.max(1.0);
+                    }
+                    break 'passes;
+                }
+                events += 1;
+                report.events += 1;
+
+                let Ok(event) = event else {
+                    report.invalid += 1;
+                    continue;
+                };
+                match pipeline.resolve(event) {
+                    Ok(outcome) => {
+                        OutputFormat::JsonCompact.write(io::sink(), &outcome.target.value)?
+                    }
+                    Err(_) => report.failed += 1,
+                }
+            }
+            counts[index] = Some(events);
+            report.bytes += sizes[index] as f64;
+        }
+        report.passes += 1.0;
+        if report.events == 0 {
+            bail!("the inputs hold no events");
+        }
+    }
+    report.elapsed = start.elapsed();
+    Ok(report)
+}
+
+impl ThroughputReport {
+    pub(crate) fn render(&self, format: BenchFormat) -> String {
+        let secs = self.elapsed.as_secs_f64().max(f64::EPSILON);
+        if format == BenchFormat::Json {
+            return json!({
+                "events": self.events,
+                "passes": self.passes,
+                "elapsed_ns": self.elapsed.as_nanos() as u64,
+                "events_per_sec": self.events as f64 / secs,
+                "bytes_per_sec": self.bytes / secs,
+                "invalid": self.invalid,
+                "failed": self.failed,
+            })
+            .to_string();
+        }
+
+        let mut out = format!(
+            "throughput: {} events ({:.1} passes over the inputs) in {:?}\n\
+             {:.0} events/s, {:.1} MB/s",
+            self.events,
+            self.passes,
+            self.elapsed,
+            self.events as f64 / secs,
+            self.bytes / 1e6 / secs,
+        );
+        if self.invalid > 0 {
+            let _ = write!(out, "\ninvalid: {} events", self.invalid);
+        }
+        if self.failed > 0 {
+            let _ = write!(out, "\nfailed: {} events", self.failed);
+        }
+        out
+    }
+}
+
+/// Compile latencies of each program of a corpus.
+#[derive(Debug)]
+pub(crate) struct CompileReport {
+    /// How long each `(name, source)` took to compile, or `None` when it
+    /// failed to.
+    programs: Vec<(String, Option<Latencies>)>,
+}
+
+/// Compiles each `(name, source)` of `programs` against `functions` `runs`
+/// times, each with a new [`RunState`] as a run would. A first, unmeasured
+/// compilation prints the diagnostics of the programs failing to compile,
+/// which are then skipped.
+pub(crate) fn bench_compile(
+    programs: &[(String, String)],
+    functions: &[Box<dyn Function>],
+    runs: usize,
+) -> CompileReport {
+    let programs = programs
+        .iter()
+        .map(|(name, source)| {
+            let compiles = compile_source(source, functions, &RunState::default()).is_some();
+            let latencies = compiles.then(|| {
+                let latencies = (0..runs)
+                    .map(|_| {
+                        let state = RunState::default();
+                        let start = Instant::now();
+                        let compiled = compile_source(source, functions, &state);
+                        let elapsed = start.elapsed();
+                        drop(compiled);
+                        elapsed
+                    })
+                    .collect();
+                Latencies::new(latencies)
+            });
+            (name.clone(), latencies)
+        })
+        .collect();
+    CompileReport { programs }
+}
+
+impl CompileReport {
+    /// Whether any program failed to compile.
+    pub(crate) fn failed(&self) -> bool {
+        self.programs
+            .iter()
+            .any(|(_, latencies)| latencies.is_none())
+    }
+
+    /// Renders one aligned row per program, or an array of objects in JSON.
+    pub(crate) fn render(&self, format: BenchFormat) -> String {
+        if format == BenchFormat::Json {
+            let nanos = |duration: Duration| duration.as_nanos() as u64;
+            let programs = self
+                .programs
+                .iter()
+                .map(|(name, latencies)| match latencies {
+                    Some(latencies) => json!({
+                        "program": name,
+                        "runs": latencies.len(),
+                        "mean_ns": nanos(latencies.mean()),
+                        "median_ns": nanos(latencies.percentile(50.0)),
+                        "p99_ns": nanos(latencies.percentile(99.0)),
+                        "max_ns": nanos(latencies.max()),
+                    }),
+                    None => json!({"program": name, "failed": true}),
+                });
+            return serde_json::Value::from_iter(programs).to_string();
+        }
+
+        let width = self
+            .programs
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain([7])
+            .max()
+            .unwrap_or_default();
+
+        let mut out = format!(
+            "{:width$}  {:>6}  {:>12}  {:>12}  {:>12}  {:>12}",
+            "program", "runs", "mean", "median", "p99", "max"
+        );
+        for (name, latencies) in &self.programs {
+            let Some(latencies) = latencies else {
+                let _ = write!(out, "\n{name:width$}  failed to compile");
+                continue;
+            };
+            let _ = write!(
+                out,
+                "\n{name:width$}  {:>6}  {:>12}  {:>12}  {:>12}  {:>12}",
+                latencies.len(),
+                format!("{:?}", latencies.mean()),
+                format!("{:?}", latencies.percentile(50.0)),
+                format!("{:?}", latencies.percentile(99.0)),
+                format!("{:?}", latencies.max()),
+            );
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vrl::value;
+
+    fn histogram(nanos: impl IntoIterator<Item = u64>) -> Histogram<u64> {
+        let mut histogram = latency_histogram();
+        nanos
+            .into_iter()
+            .for_each(|nanos| histogram.record(nanos).unwrap());
+        histogram
+    }
+
+    #[test]
+    fn resolves_the_corpus_repeatedly() {
+        let sources = vec![("bench".to_owned(), "assert!(.a != 2)".to_owned())];
+        let mut pipeline = Pipeline::compile(&sources, &vrl::stdlib::all(), false).unwrap();
+        let corpus = [value!({"a": 1}), value!({"a": 2}), value!({"a": 3})];
+
+        let options = BenchOptions {
+            warmup: 5,
+            iterations: 10_000,
+            min_time: Duration::ZERO,
+        };
+        let report = bench(&mut pipeline, &corpus, &options);
+
+        assert_eq!(report.events(), 10_002);
+        assert_eq!(report.failed, 3334);
+        assert!(report
+            .render(BenchFormat::Text)
+            .contains("3334 passes over 3 events"));
+
+        let json = report.to_json();
+        assert_eq!(json["events"], 10_002);
+        assert_eq!(json["failed"], 3334);
+        assert!(json["latency"]["p99_ns"].as_u64().unwrap() > 0);
+    }
+
+    #[test]
+    fn percentiles() {
+        let report = BenchReport {
+            compile: Duration::ZERO,
+            corpus: 100,
+            latencies: histogram((1..=100).map(|micros| micros * 1000)),
+            allocations: Allocations {
+                count: 250,
+                bytes: 10_000,
+            },
+            memory: Some((4096, -1000)),
+            failed: 0,
+            total: Duration::from_secs(2),
+        };
+
+        // the histogram is precise to 3 significant digits
+        assert_eq!(report.mean().as_micros(), 50);
+        assert_eq!(report.percentile(50.0).as_micros(), 50);
+        assert_eq!(report.percentile(99.0).as_micros(), 99);
+        assert_eq!(report.percentile(100.0).as_micros(), 100);
+        assert_eq!(report.throughput(), 50.0);
+        assert_eq!(report.allocations_per_event(), (2.5, 100.0));
+        assert_eq!(report.memory_per_events(), Some((40960.0, -10000.0)));
+
+        let mut hgrm = Vec::new();
+        report.write_histogram(&mut hgrm).unwrap();
+        let hgrm = String::from_utf8(hgrm).unwrap();
+        let lines = hgrm.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "       Value     Percentile TotalCount 1/(1-Percentile)"
+        );
+        assert_eq!(
+            lines[2],
+            "       1.000 0.000000000000          1           1.00"
+        );
+        assert!(lines.contains(&"     100.031 1.000000000000        100"));
+        assert!(lines.contains(&"#[Max     =      100.031, Total count    =          100]"));
+    }
+
+    #[test]
+    fn compares_reports() {
+        let report = |micros: u64, count: u64| BenchReport {
+            compile: Duration::from_millis(1),
+            corpus: 1,
+            latencies: histogram([micros * 1000; 10]),
+            allocations: Allocations {
+                count: count * 10,
+                bytes: 0,
+            },
+            memory: None,
+            failed: 0,
+            total: Duration::from_micros(micros * 10),
+        };
+
+        assert_eq!(
+            BenchReport::render_comparison(&report(4, 10), &report(3, 5), BenchFormat::Text),
+            [
+                "                        stdlib        custom    change",
+                "compile                    1ms           1ms     +0.0%",
+                "mean                   4.001µs       3.001µs    -25.0%",
+                "median                 4.001µs       3.001µs    -25.0%",
+                "p99                    4.001µs       3.001µs    -25.0%",
+                "allocations               10.0           5.0    -50.0%",
+                "allocated bytes              0             0         -",
+                "events/s                250000        333333    +33.3%",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn compiles_every_program() {
+        let programs = vec![
+            ("ok.vrl".to_owned(), ".a = 1".to_owned()),
+            ("bad.vrl".to_owned(), ".a = (".to_owned()),
+        ];
+
+        let report = bench_compile(&programs, &vrl::stdlib::all(), 5);
+
+        assert!(report.failed());
+        assert_eq!(report.programs[0].1.as_ref().unwrap().len(), 5);
+        let rendered = report.render(BenchFormat::Text);
+        assert!(rendered.starts_with("program    runs"));
+        assert!(rendered.ends_with("\nbad.vrl  failed to compile"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&report.render(BenchFormat::Json)).unwrap();
+        assert_eq!(json[0]["runs"], 5);
+        assert_eq!(json[1], json!({"program": "bad.vrl", "failed": true}));
+    }
+
+    #[test]
+    fn loops_over_the_inputs() {
+        let path =
+            std::env::temp_dir().join(format!("vrl-test-{}-bench.ndjson", std::process::id()));
+        fs::write(&path, "{\"a\": 1}\n{\"a\": 2}\nnope\n").unwrap();
+        let sources = vec![("bench".to_owned(), "assert!(.a != 2)".to_owned())];
+        let mut pipeline = Pipeline::compile(&sources, &vrl::stdlib::all(), false).unwrap();
+
+        let inputs = [Input::File(path.clone())];
+        let duration = Duration::from_millis(50);
+        let report = bench_throughput(&mut pipeline, &inputs, &Decoding::default(), duration);
+        fs::remove_file(&path).unwrap();
+
+        let report = report.unwrap();
+        assert!(report.elapsed >= duration);
+        assert!(report.passes >= 1.0);
+        assert!(report.events >= 3);
+        assert!(report.invalid.abs_diff(report.events / 3) <= 1);
+        assert!(report.failed.abs_diff(report.events / 3) <= 1);
+        assert!(report.render(BenchFormat::Text).contains("MB/s"));
+    }
+
+    #[test]
+    fn detects_regressions_from_baselines() {
+        let baseline = Baseline {
+            mean: Duration::from_micros(10),
+            median: Duration::from_micros(10),
+            p99: Duration::from_micros(20),
+            allocations: 4.0,
+            allocated_bytes: 0.0,
+        };
+        let path = std::env::temp_dir().join(format!("vrl-test-{}-baselines", std::process::id()));
+        let saved = Baseline::path(&path, "main");
+        baseline.save(&saved).unwrap();
+        assert_eq!(Baseline::load(&saved).unwrap(), baseline);
+        fs::remove_dir_all(&path).unwrap();
+
+        let current = Baseline {
+            p99: Duration::from_micros(30),
+            allocations: 4.2,
+            ..baseline
+        };
+        let (rendered, regressions) = baseline.compare(&current, 10.0);
+        assert_eq!(regressions, 1);
+        assert_eq!(
+            rendered.lines().nth(3).unwrap(),
+            "p99                       20µs          30µs    +50.0%  regressed"
+        );
+        assert_eq!(baseline.compare(&current, 60.0).1, 0);
+        assert_eq!(baseline.compare(&baseline, 0.0).1, 0);
+    }
+}
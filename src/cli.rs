@@ -0,0 +1,749 @@
+use chrono::{DateTime, Utc};
+use clap::error::ErrorKind;
+#[cfg(feature = "kafka")]
+use clap::ValueEnum;
+use clap::{ArgAction, ArgGroup, Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap_complete::Shell;
+use log::LevelFilter;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::Duration;
+#[cfg(feature = "kafka")]
+use vrl::path::OwnedValuePath;
+
+use crate::bench::BenchFormat;
+#[cfg(feature = "crypto")]
+use crate::functions::KeyStore;
+use crate::input::{InputFormat, ListenAddr};
+use crate::output::{Emit, OutputFormat, Rotation, Template};
+use crate::program::ProgramSource;
+use crate::timing::TimingFormat;
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  success
+  1  the program failed to compile
+  2  invalid command line usage
+  3  the program compiled with warnings (`compile`, or `run --deny-warnings`)
+  4  the program failed at runtime for at least one event, or a replayed
+     REPL session printed different results
+  5  a program or input could not be read or decoded
+  6  a benchmark regressed from its `--baseline`";
+
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    after_help = EXIT_CODES,
+    about = "Test harness for VRL programs and custom functions",
+    arg_required_else_help = true
+)]
+pub(crate) struct Cli {
+    /// Evaluate an expression against an empty event and print the result as JSON
+    #[arg(short, long = "eval", value_name = "EXPR")]
+    pub(crate) eval: Option<String>,
+
+    /// Log more detail: -v for info, -vv for debug, -vvv for trace
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub(crate) verbose: u8,
+
+    /// Only log errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub(crate) quiet: bool,
+
+    #[command(flatten)]
+    pub(crate) registry: RegistryArgs,
+
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+}
+
+impl Cli {
+    /// Parses the process arguments, exiting with a usage error on conflicts
+    /// clap cannot express between `--eval` and subcommands.
+    pub(crate) fn parse_args() -> Self {
+        let cli = Self::parse();
+        if cli.eval.is_some() && cli.command.is_some() {
+            Self::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--eval cannot be used with a subcommand",
+                )
+                .exit();
+        }
+        cli
+    }
+
+    /// The log level selected by `-v`/`-q`; `RUST_LOG` takes precedence when set.
+    pub(crate) fn log_level(&self) -> LevelFilter {
+        match (self.quiet, self.verbose) {
+            (true, _) => LevelFilter::Error,
+            (false, 0) => LevelFilter::Warn,
+            (false, 1) => LevelFilter::Info,
+            (false, 2) => LevelFilter::Debug,
+            (false, _) => LevelFilter::Trace,
+        }
+    }
+}
+
+/// Arguments shaping the set of functions programs are compiled against.
+#[derive(Args, Debug)]
+pub(crate) struct RegistryArgs {
+    /// Load custom functions from a shared library plugin; may be repeated
+    #[arg(long, value_name = "PATH", global = true)]
+    pub(crate) plugin: Vec<PathBuf>,
+
+    /// Register the custom implementations as `<PREFIX>_<name>` next to the
+    /// stdlib functions instead of replacing them, e.g. `custom_split`
+    #[arg(long, value_name = "PREFIX", global = true, value_parser = parse_namespace)]
+    pub(crate) namespace: Option<String>,
+
+    /// Register another name for a function, e.g. `split_str=split`; calls
+    /// through it get a deprecation warning naming the function; may be
+    /// repeated
+    #[arg(long, value_name = "ALIAS=FUNCTION", global = true, value_parser = parse_alias)]
+    pub(crate) alias: Vec<(String, String)>,
+
+    /// Make calls to a function warn that it is deprecated, e.g.
+    /// `upcase=use shout instead`; may be repeated
+    #[arg(long, value_name = "FUNCTION=NOTICE", global = true, value_parser = parse_deprecation)]
+    pub(crate) deprecate: Vec<(String, String)>,
+
+    /// Only allow calling these functions: identifiers, globs such as
+    /// `parse_*`, or groups such as `@io`
+    #[arg(long, value_name = "FUNCTION", global = true, value_delimiter = ',')]
+    pub(crate) allow: Vec<String>,
+
+    /// Disallow calling these functions, e.g. `@io` for functions doing I/O;
+    /// applied after `--allow`
+    #[arg(long, value_name = "FUNCTION", global = true, value_delimiter = ',')]
+    pub(crate) deny: Vec<String>,
+
+    /// Stop the clock of `uuid_v7`, `ulid`, `snowflake` and `rate_limit` at
+    /// an RFC 3339 timestamp, making the IDs a run generates the same every
+    /// time
+    #[arg(long, value_name = "TIMESTAMP", global = true, value_parser = parse_timestamp)]
+    pub(crate) fixed_clock: Option<DateTime<Utc>>,
+
+    /// How long values cached with `cache_set` live unless it is given a
+    /// `ttl_ms`, e.g. `10m`; they live for the whole run by default
+    #[arg(long, value_name = "DURATION", global = true, value_parser = parse_duration)]
+    pub(crate) cache_ttl: Option<Duration>,
+
+    /// How many values the cache holds before `cache_set` evicts the oldest
+    #[arg(long, value_name = "N", global = true, default_value_t = 10_000)]
+    pub(crate) cache_max_entries: usize,
+
+    /// Redis server `redis_get` reads from, e.g. `redis://127.0.0.1/`
+    #[cfg(feature = "networking")]
+    #[arg(long, value_name = "URL", global = true)]
+    pub(crate) redis_url: Option<String>,
+
+    /// Where `hmac_sign` and `verify_signature` load the keys programs name
+    /// from: a TOML file of `name = "<algorithm>:<base64 key>"` entries, or
+    /// `env:PREFIX` to read key `name` from `$PREFIX<NAME>`
+    #[cfg(feature = "crypto")]
+    #[arg(long, value_name = "PATH | env:PREFIX", global = true)]
+    pub(crate) key_store: Option<KeyStore>,
+
+    /// Name an enrichment file, e.g. `geo=GeoLite2-City.mmdb`, so programs
+    /// can refer to it as `"geo"` instead of by path; may be repeated
+    #[cfg(feature = "enrichment")]
+    #[arg(long, value_name = "NAME=PATH", global = true, value_parser = parse_table)]
+    pub(crate) enrichment_table: Vec<(String, PathBuf)>,
+}
+
+impl Default for RegistryArgs {
+    /// The arguments when none are given on the command line.
+    fn default() -> Self {
+        let command = Self::augment_args(clap::Command::new("registry"));
+        Self::from_arg_matches(&command.get_matches_from(["registry"]))
+            .expect("no registry argument is required")
+    }
+}
+
+/// VRL has no `::` in function names, so a namespace is a plain identifier.
+fn parse_namespace(namespace: &str) -> Result<String, String> {
+    let valid = namespace.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && namespace
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+    match valid {
+        true => Ok(namespace.to_owned()),
+        false => Err("must be letters, digits and underscores".to_owned()),
+    }
+}
+
+fn parse_alias(alias: &str) -> Result<(String, String), String> {
+    match alias.split_once('=') {
+        Some((alias, function)) if !function.is_empty() => {
+            let alias = parse_namespace(alias).map_err(|err| format!("alias {err}"))?;
+            Ok((alias, function.to_owned()))
+        }
+        _ => Err("expected ALIAS=FUNCTION".to_owned()),
+    }
+}
+
+fn parse_deprecation(deprecation: &str) -> Result<(String, String), String> {
+    match deprecation.split_once('=') {
+        Some((function, notice)) if !function.is_empty() && !notice.is_empty() => {
+            Ok((function.to_owned(), notice.to_owned()))
+        }
+        _ => Err("expected FUNCTION=NOTICE".to_owned()),
+    }
+}
+
+fn parse_timestamp(timestamp: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(feature = "enrichment")]
+fn parse_table(table: &str) -> Result<(String, PathBuf), String> {
+    match table.split_once('=') {
+        Some((name, path)) if !name.is_empty() && !path.is_empty() => {
+            Ok((name.to_owned(), PathBuf::from(path)))
+        }
+        _ => Err("expected NAME=PATH".to_owned()),
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum Command {
+    /// Compile a program and run it against input events
+    #[command(after_help = EXIT_CODES)]
+    Run(Box<RunArgs>),
+
+    /// Parse and type-check a program without running it
+    ///
+    /// Exits with 0 when the program compiles cleanly, 1 on compile errors and
+    /// 3 when it compiles with warnings only. Programs remembered to have
+    /// compiled cleanly before, with the same build, registry arguments,
+    /// working directory and files they read, aren't compiled again unless
+    /// `--no-cache` is given. Only that outcome is remembered, nothing
+    /// compiled: `run` and `watch` always compile.
+    #[command(after_help = EXIT_CODES)]
+    Compile(CompileArgs),
+
+    /// Compile a program once and resolve it repeatedly against input events,
+    /// reporting per-event latency and throughput
+    ///
+    /// Events are read up front and resolved over and over until at least
+    /// `--iterations` were and `--min-time` has passed, without writing the
+    /// transformed events anywhere. With `--duration`, the input files are
+    /// read over and over instead, and with `--compile-dir`, programs are
+    /// compiled over and over.
+    Bench(Box<BenchArgs>),
+
+    /// Evaluate expressions entered line by line, printing each result as JSON
+    ///
+    /// An expression with unclosed brackets continues on the next lines.
+    /// Changes to the event are kept from one expression to the next; enter
+    /// `:reset` to go back to the event the session started from,
+    /// `:load <PATH> [<N>]` to start over from the first or Nth event of a
+    /// JSON or NDJSON file, `:type <EXPR>` to show the type of an expression
+    /// without running it, `:save <PATH>` to write the session to a script
+    /// for `--replay`, or `:help` for the other commands.
+    Repl(ReplArgs),
+
+    /// List the registered functions with their parameters and examples
+    Functions(FunctionsArgs),
+
+    /// Print a shell completion script to stdout
+    Completions(CompletionsArgs),
+}
+
+/// Arguments selecting where the VRL program is read from.
+#[derive(Args, Debug)]
+#[command(group = ArgGroup::new("program_source").required(true))]
+pub(crate) struct ProgramArgs {
+    /// Path to the VRL program file, or `-` to read it from stdin
+    ///
+    /// Repeat to chain programs into a pipeline, where the output event of
+    /// each program is the input of the next.
+    #[arg(short, long, value_name = "PATH", group = "program_source")]
+    pub(crate) program: Vec<PathBuf>,
+
+    /// Read the VRL program from stdin
+    #[arg(long, group = "program_source")]
+    pub(crate) stdin: bool,
+
+    /// Inline VRL program source
+    #[arg(long, value_name = "VRL", group = "program_source")]
+    pub(crate) source: Option<String>,
+}
+
+impl ProgramArgs {
+    pub(crate) fn program_sources(&self) -> Vec<ProgramSource> {
+        if let Some(source) = &self.source {
+            return vec![ProgramSource::Inline(source.clone())];
+        }
+        if self.stdin {
+            return vec![ProgramSource::Stdin];
+        }
+
+        self.program
+            .iter()
+            .map(|path| match path.as_os_str() == "-" {
+                true => ProgramSource::Stdin,
+                false => ProgramSource::File(path.clone()),
+            })
+            .collect()
+    }
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct RunArgs {
+    #[command(flatten)]
+    pub(crate) program: ProgramArgs,
+
+    /// Input events as JSON objects; runs against an empty event when no
+    /// events or inputs are given
+    pub(crate) events: Vec<String>,
+
+    /// Read events from a file, a directory, a glob such as `logs/**/*.json`,
+    /// or `-` for stdin
+    #[arg(short, long, value_name = "PATH")]
+    pub(crate) input: Vec<PathBuf>,
+
+    /// Format of the inputs; detected from the file extension when omitted
+    #[arg(short, long, value_enum)]
+    pub(crate) format: Option<InputFormat>,
+
+    /// Avro schema (JSON) for inputs holding raw datums rather than a
+    /// container file; used as the reader schema for container files
+    #[cfg(feature = "avro")]
+    #[arg(long, value_name = "PATH")]
+    pub(crate) avro_schema: Option<PathBuf>,
+
+    /// Compiled descriptor set (`protoc --descriptor_set_out`) describing
+    /// protobuf inputs
+    #[arg(long, value_name = "PATH", requires = "proto_message")]
+    pub(crate) proto_desc: Option<PathBuf>,
+
+    /// Fully qualified message type of protobuf inputs, e.g. `pkg.Event`
+    #[arg(long, value_name = "TYPE", requires = "proto_desc")]
+    pub(crate) proto_message: Option<String>,
+
+    /// Only load these top-level columns from Parquet inputs
+    #[cfg(feature = "parquet")]
+    #[arg(long, value_name = "COLUMN", value_delimiter = ',')]
+    pub(crate) parquet_columns: Vec<String>,
+
+    /// Tail a file like `tail -f`, running the program on every appended
+    /// line and reopening the file when it's rotated
+    #[arg(long, value_name = "PATH")]
+    pub(crate) follow: Option<PathBuf>,
+
+    /// Listen on `tcp://host:port` or `udp://host:port` and run the program
+    /// on every received line or datagram
+    #[arg(long, value_name = "ADDR")]
+    pub(crate) listen: Option<ListenAddr>,
+
+    #[cfg(feature = "kafka")]
+    #[command(flatten)]
+    pub(crate) kafka: KafkaArgs,
+
+    #[command(flatten)]
+    pub(crate) output: OutputArgs,
+
+    /// Treat compile warnings as errors and exit without running
+    #[arg(long)]
+    pub(crate) deny_warnings: bool,
+
+    /// Recompile and re-run whenever the program file changes, reading the
+    /// inputs again each time, so none of them can be stdin
+    #[arg(long)]
+    pub(crate) watch: bool,
+
+    /// Resolve the events of the inputs on this many threads, sharing the
+    /// compiled program; events are still written out in order
+    #[arg(short, long, value_name = "N", default_value = "1")]
+    pub(crate) jobs: NonZeroUsize,
+
+    /// Time every function call, and print how much of the resolve time
+    /// each took to stderr after the run
+    #[arg(long)]
+    pub(crate) profile: bool,
+
+    /// Print a compile/resolve timing breakdown to stderr after the run
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text"
+    )]
+    pub(crate) timing: Option<TimingFormat>,
+}
+
+/// Where and how `run` writes transformed events.
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Output")]
+pub(crate) struct OutputArgs {
+    /// Write transformed events to this file instead of stdout
+    #[arg(short, long, value_name = "PATH")]
+    pub(crate) output: Option<PathBuf>,
+
+    /// Encoding of transformed events [default: json-compact]; when given,
+    /// per-event errors and `--timing=json` reports use it too
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub(crate) output_format: Option<OutputFormat>,
+
+    /// Write one line per event from a template such as
+    /// `'{{.host}} -> {{.message}}'` instead of encoding it
+    #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["output_format", "columns"])]
+    pub(crate) template: Option<Template>,
+
+    /// Fields written by `--output-format csv`, e.g. `host,http.status`;
+    /// nested fields are flattened into their own columns [default: the
+    /// fields of the first event]
+    #[arg(long, value_name = "FIELD", value_delimiter = ',')]
+    pub(crate) columns: Vec<String>,
+
+    /// What to write for each event
+    #[arg(long, value_enum, default_value_t = Emit::Event)]
+    pub(crate) emit: Emit,
+
+    /// Write events the program fails or aborts on, along with the error, to
+    /// this file instead of reporting them on stderr
+    #[arg(long, value_name = "PATH")]
+    pub(crate) errors: Option<PathBuf>,
+
+    /// Rotate the output file once it reaches this size, e.g. `100M`
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, requires = "output")]
+    pub(crate) rotate_size: Option<u64>,
+
+    /// Rotate the output file after it has been open this long, e.g. `1h`
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, requires = "output")]
+    pub(crate) rotate_interval: Option<Duration>,
+
+    /// Gzip output files as they are rotated out
+    #[arg(long, requires = "output")]
+    pub(crate) rotate_gzip: bool,
+
+    #[cfg(feature = "kafka")]
+    #[command(flatten)]
+    pub(crate) kafka: KafkaOutputArgs,
+
+    #[cfg(feature = "http")]
+    #[command(flatten)]
+    pub(crate) http: HttpOutputArgs,
+}
+
+impl OutputArgs {
+    pub(crate) fn rotation(&self) -> Rotation {
+        Rotation {
+            max_bytes: self.rotate_size,
+            interval: self.rotate_interval,
+            gzip: self.rotate_gzip,
+        }
+    }
+}
+
+/// Parses a byte count with an optional binary `K`, `M` or `G` suffix.
+fn parse_size(size: &str) -> Result<u64, String> {
+    let (digits, unit) = match size.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => size.split_at(index),
+        None => (size, ""),
+    };
+    let shift = match unit.to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        _ => return Err(format!("unknown size unit `{unit}`; use K, M or G")),
+    };
+    let count = digits.parse::<u64>().map_err(|err| err.to_string())?;
+
+    count
+        .checked_mul(1 << shift)
+        .ok_or_else(|| "size is too large".to_owned())
+}
+
+/// Parses a duration such as `500ms`, `30s`, `5m` or `2h`.
+fn parse_duration(duration: &str) -> Result<Duration, String> {
+    let index = duration
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| "missing unit; use ms, s, m or h".to_owned())?;
+    let (count, unit) = duration.split_at(index);
+    let count = count.parse::<u64>().map_err(|err| err.to_string())?;
+
+    match unit {
+        "ms" => Ok(Duration::from_millis(count)),
+        "s" => Ok(Duration::from_secs(count)),
+        "m" => Ok(Duration::from_secs(count * 60)),
+        "h" => Ok(Duration::from_secs(count * 60 * 60)),
+        _ => Err(format!("unknown duration unit `{unit}`; use ms, s, m or h")),
+    }
+}
+
+/// Kafka consumer settings for `run`.
+#[cfg(feature = "kafka")]
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Kafka input")]
+pub(crate) struct KafkaArgs {
+    /// Consume events from this Kafka topic after any other inputs
+    #[arg(long, value_name = "TOPIC")]
+    pub(crate) kafka_topic: Option<String>,
+
+    /// Comma-separated list of bootstrap brokers
+    #[arg(long, value_name = "BROKERS", default_value = "localhost:9092")]
+    pub(crate) kafka_brokers: String,
+
+    /// Consumer group id
+    #[arg(long, value_name = "GROUP", default_value = "vrl-test")]
+    pub(crate) kafka_group: String,
+
+    /// Where to start when the group has no committed offset
+    #[arg(long, value_enum, default_value_t = OffsetReset::Latest)]
+    pub(crate) kafka_offset_reset: OffsetReset,
+
+    /// Stop after consuming this many messages
+    #[arg(long, value_name = "N")]
+    pub(crate) kafka_max_messages: Option<usize>,
+}
+
+/// HTTP batch output settings for `run`.
+#[cfg(feature = "http")]
+#[derive(Args, Debug)]
+#[command(next_help_heading = "HTTP output")]
+pub(crate) struct HttpOutputArgs {
+    /// POST transformed events to this URL, in batches sent as JSON arrays
+    #[arg(long, value_name = "URL", conflicts_with_all = ["output", "template"])]
+    pub(crate) http_url: Option<String>,
+
+    /// Number of events sent per request
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    pub(crate) http_batch_size: usize,
+
+    /// How often a batch is retried after a server error or connection failure
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    pub(crate) http_retries: u32,
+}
+
+/// Kafka producer settings for `run`.
+#[cfg(feature = "kafka")]
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Kafka output")]
+pub(crate) struct KafkaOutputArgs {
+    /// Publish transformed events to this Kafka topic instead of stdout
+    #[arg(long, value_name = "TOPIC", conflicts_with_all = ["output", "template"])]
+    pub(crate) kafka_output_topic: Option<String>,
+
+    /// Comma-separated list of bootstrap brokers to publish to
+    #[arg(long, value_name = "BROKERS", default_value = "localhost:9092")]
+    pub(crate) kafka_output_brokers: String,
+
+    /// Event field holding the message key, e.g. `.user.id`
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_parser = parse_path,
+        requires = "kafka_output_topic"
+    )]
+    pub(crate) kafka_key_field: Option<OwnedValuePath>,
+}
+
+#[cfg(feature = "kafka")]
+fn parse_path(path: &str) -> Result<OwnedValuePath, String> {
+    vrl::path::parse_value_path(path).map_err(|err| err.to_string())
+}
+
+#[cfg(feature = "kafka")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum OffsetReset {
+    Earliest,
+    Latest,
+}
+
+#[cfg(feature = "kafka")]
+impl OffsetReset {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            OffsetReset::Earliest => "earliest",
+            OffsetReset::Latest => "latest",
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct CompileArgs {
+    #[command(flatten)]
+    pub(crate) program: ProgramArgs,
+
+    /// Compile every program, even those remembered to have compiled cleanly
+    /// before with the same functions and files
+    #[arg(long)]
+    pub(crate) no_cache: bool,
+
+    /// Where the programs that compiled cleanly are remembered [default:
+    /// $XDG_CACHE_HOME/vrl-test or ~/.cache/vrl-test]
+    #[arg(long, value_name = "DIR", conflicts_with = "no_cache")]
+    pub(crate) cache_dir: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct BenchArgs {
+    #[command(flatten)]
+    pub(crate) program: ProgramArgs,
+
+    /// Events to resolve as JSON objects; an empty event when no events or
+    /// inputs are given
+    pub(crate) events: Vec<String>,
+
+    /// Read events from a file, a directory, a glob such as `logs/**/*.json`,
+    /// or `-` for stdin
+    #[arg(short, long, value_name = "PATH")]
+    pub(crate) input: Vec<PathBuf>,
+
+    /// Format of the inputs; detected from the file extension when omitted
+    #[arg(short, long, value_enum)]
+    pub(crate) format: Option<InputFormat>,
+
+    /// Also run the program with the stock stdlib functions, and compare
+    /// their latency and allocations with the custom implementations
+    #[arg(long)]
+    pub(crate) compare_stdlib: bool,
+
+    /// Benchmark compiling every `.vrl` file in this directory instead of
+    /// running a program, reporting the latency of each
+    #[arg(
+        long,
+        value_name = "DIR",
+        group = "program_source",
+        conflicts_with_all = ["events", "input", "compare_stdlib"]
+    )]
+    pub(crate) compile_dir: Option<PathBuf>,
+
+    /// Events to resolve before measuring, going over the corpus as many
+    /// times as it takes
+    #[arg(long, value_name = "N", default_value_t = 1000, conflicts_with_all = ["compile_dir", "duration"])]
+    pub(crate) warmup: usize,
+
+    /// Events to resolve at least, going over the whole corpus as many times
+    /// as it takes
+    #[arg(long, value_name = "N", default_value_t = 10_000, conflicts_with_all = ["compile_dir", "duration"])]
+    pub(crate) iterations: usize,
+
+    /// Keep going over the corpus until this long has passed, e.g. `5s`, even
+    /// once `--iterations` events were resolved
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        conflicts_with_all = ["compile_dir", "duration"]
+    )]
+    pub(crate) min_time: Option<Duration>,
+
+    /// How the report is rendered; JSON reports are meant for archiving and
+    /// graphing results
+    #[arg(long, value_enum, default_value_t = BenchFormat::Text)]
+    pub(crate) bench_format: BenchFormat,
+
+    /// How many times `--compile-dir` compiles each program
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    pub(crate) compile_runs: usize,
+
+    /// Write the distribution of resolve latencies to this file in the
+    /// percentile format of HdrHistogram (`.hgrm`), for plotting tools
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["compile_dir", "duration"])]
+    pub(crate) histogram: Option<PathBuf>,
+
+    /// Save the latency and allocations under this name, for later runs to
+    /// compare themselves with using `--baseline`
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["compile_dir", "duration"])]
+    pub(crate) save_baseline: Option<String>,
+
+    /// Compare the latency and allocations with those saved under this name,
+    /// failing when any grew by more than `--threshold`
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["compile_dir", "duration"])]
+    pub(crate) baseline: Option<String>,
+
+    /// How much, in percent, latency or allocations may grow over the
+    /// `--baseline` before counting as a regression
+    #[arg(long, value_name = "PERCENT", default_value_t = 10.0)]
+    pub(crate) threshold: f64,
+
+    /// Directory holding the baselines
+    #[arg(long, value_name = "DIR", default_value = ".vrl-bench")]
+    pub(crate) baseline_dir: PathBuf,
+
+    /// Read, resolve and encode the events of the `--input` files over and
+    /// over for this long, e.g. `30s`, reporting events/s and MB/s end to end
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        requires = "input",
+        conflicts_with_all = ["events", "compare_stdlib"]
+    )]
+    pub(crate) duration: Option<Duration>,
+
+    /// Sample the process while the benchmark runs and write a flamegraph
+    /// (SVG) of where it spent its time to this file
+    #[cfg(feature = "flamegraph")]
+    #[arg(long, value_name = "PATH", conflicts_with = "compile_dir")]
+    pub(crate) flamegraph: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct ReplArgs {
+    /// Event the session starts from, as a JSON object [default: {}]
+    pub(crate) event: Option<String>,
+
+    /// Run a script saved with `:save` instead of reading stdin, failing
+    /// when an expression prints something other than it did when saved
+    #[arg(long, value_name = "PATH")]
+    pub(crate) replay: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct FunctionsArgs {
+    /// Only show the functions with these identifiers
+    pub(crate) names: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct CompletionsArgs {
+    /// Shell to generate completions for
+    pub(crate) shell: Shell,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn verify_cli() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn sizes_and_durations() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("4k"), Ok(4096));
+        assert_eq!(parse_size("100M"), Ok(100 << 20));
+        assert!(parse_size("1T").is_err());
+
+        assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert!(parse_duration("5").is_err());
+        assert!(parse_duration("5d").is_err());
+    }
+
+    #[test]
+    fn log_level() {
+        let level = |args: &[&str]| Cli::parse_from(args).log_level();
+
+        assert_eq!(level(&["vrl-test", "functions"]), LevelFilter::Warn);
+        assert_eq!(level(&["vrl-test", "-q", "functions"]), LevelFilter::Error);
+        assert_eq!(level(&["vrl-test", "functions", "-v"]), LevelFilter::Info);
+        assert_eq!(level(&["vrl-test", "-vv", "functions"]), LevelFilter::Debug);
+        assert_eq!(
+            level(&["vrl-test", "-vvvv", "functions"]),
+            LevelFilter::Trace
+        );
+    }
+}
@@ -0,0 +1,320 @@
+//! Plain Rust closures registered as VRL functions, for quick experiments
+//! without writing a `Function` and `FunctionExpression` pair; see
+//! [`HarnessBuilder::register_closure`](crate::HarnessBuilder::register_closure).
+
+use std::fmt;
+use std::sync::Arc;
+use vrl::prelude::*;
+
+/// A closure argument type, mapped to the parameter kind the compiler checks
+/// calls against.
+pub trait Arg: Sized {
+    fn kind() -> u16;
+
+    fn from_value(value: Value) -> Result<Self, ExpressionError>;
+}
+
+impl Arg for Value {
+    fn kind() -> u16 {
+        kind::ANY
+    }
+
+    fn from_value(value: Value) -> Result<Self, ExpressionError> {
+        Ok(value)
+    }
+}
+
+impl Arg for String {
+    fn kind() -> u16 {
+        kind::BYTES
+    }
+
+    fn from_value(value: Value) -> Result<Self, ExpressionError> {
+        Ok(value.try_bytes_utf8_lossy()?.into_owned())
+    }
+}
+
+impl Arg for i64 {
+    fn kind() -> u16 {
+        kind::INTEGER
+    }
+
+    fn from_value(value: Value) -> Result<Self, ExpressionError> {
+        Ok(value.try_integer()?)
+    }
+}
+
+impl Arg for f64 {
+    fn kind() -> u16 {
+        kind::FLOAT
+    }
+
+    fn from_value(value: Value) -> Result<Self, ExpressionError> {
+        Ok(value.try_float()?)
+    }
+}
+
+impl Arg for bool {
+    fn kind() -> u16 {
+        kind::BOOLEAN
+    }
+
+    fn from_value(value: Value) -> Result<Self, ExpressionError> {
+        Ok(value.try_boolean()?)
+    }
+}
+
+/// A closure return type; returning a `Result` makes the function fallible.
+pub trait Output {
+    fn type_def() -> TypeDef;
+
+    fn into_resolved(self) -> Resolved;
+}
+
+macro_rules! output {
+    ($($ty:ty => $kind:expr),* $(,)?) => {
+        $(impl Output for $ty {
+            fn type_def() -> TypeDef {
+                TypeDef::from($kind).infallible()
+            }
+
+            fn into_resolved(self) -> Resolved {
+                Ok(self.into())
+            }
+        })*
+    };
+}
+
+output! {
+    Value => Kind::any(),
+    String => Kind::bytes(),
+    i64 => Kind::integer(),
+    bool => Kind::boolean(),
+}
+
+impl Output for f64 {
+    fn type_def() -> TypeDef {
+        TypeDef::float().infallible()
+    }
+
+    fn into_resolved(self) -> Resolved {
+        // like the stdlib float functions, NaN becomes zero
+        Ok(Value::from_f64_or_zero(self))
+    }
+}
+
+impl<T: Output> Output for Result<T, ExpressionError> {
+    fn type_def() -> TypeDef {
+        T::type_def().fallible()
+    }
+
+    fn into_resolved(self) -> Resolved {
+        self?.into_resolved()
+    }
+}
+
+/// A closure called with the resolved arguments of a function.
+pub type Call = Arc<dyn Fn(Vec<Value>) -> Resolved + Send + Sync>;
+
+/// A closure taking `Args` that can be called with resolved arguments.
+pub trait Callable<Args>: Send + Sync + 'static {
+    fn kinds() -> Vec<u16>;
+
+    fn type_def() -> TypeDef;
+
+    fn into_call(self) -> Call;
+}
+
+macro_rules! callable {
+    ($($arg:ident),+) => {
+        impl<F, R, $($arg),+> Callable<($($arg,)+)> for F
+        where
+            F: Fn($($arg),+) -> R + Send + Sync + 'static,
+            R: Output,
+            $($arg: Arg),+
+        {
+            fn kinds() -> Vec<u16> {
+                vec![$($arg::kind()),+]
+            }
+
+            fn type_def() -> TypeDef {
+                R::type_def()
+            }
+
+            fn into_call(self) -> Call {
+                Arc::new(move |arguments| {
+                    let mut arguments = arguments.into_iter();
+                    self($($arg::from_value(arguments.next().unwrap_or(Value::Null))?),+)
+                        .into_resolved()
+                })
+            }
+        }
+    };
+}
+
+callable!(A);
+callable!(A, B);
+callable!(A, B, C);
+
+/// Keywords of the positional closure parameters, following the stdlib
+/// convention of calling the first one `value`.
+const KEYWORDS: [&str; 3] = ["value", "arg1", "arg2"];
+
+/// A VRL function calling a closure with all of its arguments, which are
+/// all required.
+#[derive(Clone)]
+pub struct ClosureFn {
+    identifier: &'static str,
+    parameters: &'static [Parameter],
+    type_def: TypeDef,
+    call: Call,
+}
+
+impl ClosureFn {
+    /// Wraps `closure` as the function `identifier`.
+    pub fn new<Args>(identifier: &'static str, closure: impl Callable<Args>) -> Self {
+        fn kinds<Args, C: Callable<Args>>(_: &C) -> Vec<u16> {
+            C::kinds()
+        }
+        fn type_def<Args, C: Callable<Args>>(_: &C) -> TypeDef {
+            C::type_def()
+        }
+
+        let parameters = kinds(&closure)
+            .into_iter()
+            .zip(KEYWORDS)
+            .map(|(kind, keyword)| Parameter {
+                keyword,
+                kind,
+                required: true,
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            identifier,
+            parameters: parameters.leak(),
+            type_def: type_def(&closure),
+            call: closure.into_call(),
+        }
+    }
+}
+
+impl fmt::Debug for ClosureFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClosureFn")
+            .field("identifier", &self.identifier)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Function for ClosureFn {
+    fn identifier(&self) -> &'static str {
+        self.identifier
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        self.parameters
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[]
+    }
+
+    fn compile(
+        &self,
+        _state: &state::TypeState,
+        _ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        let arguments = self
+            .parameters
+            .iter()
+            .map(|parameter| arguments.required(parameter.keyword))
+            .collect();
+
+        Ok(ClosureFnExpr {
+            function: self.clone(),
+            arguments,
+        }
+        .as_expr())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ClosureFnExpr {
+    function: ClosureFn,
+    arguments: Vec<Box<dyn Expression>>,
+}
+
+impl FunctionExpression for ClosureFnExpr {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        let arguments = self
+            .arguments
+            .iter()
+            .map(|argument| argument.resolve(ctx))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        (self.function.call)(arguments)
+    }
+
+    fn type_def(&self, _: &state::TypeState) -> TypeDef {
+        self.function.type_def.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::registry::Registry;
+    use std::collections::BTreeMap;
+    use vrl::compiler::runtime::Runtime;
+    use vrl::compiler::TimeZone;
+    use vrl::value;
+
+    fn eval(source: &str) -> Resolved {
+        let functions = Registry::stdlib()
+            .register_closure("double", |value: i64| value * 2)
+            .register_closure("greet", |name: String, excited: bool| match excited {
+                true => format!("hello, {name}!"),
+                false => format!("hello, {name}"),
+            })
+            .register_closure("must_be_even", |value: i64| match value % 2 {
+                0 => Ok(value),
+                _ => Err(ExpressionError::from("odd")),
+            })
+            .build()
+            .unwrap();
+        let program = vrl::compiler::compile(source, &functions).unwrap().program;
+        let mut target = crate::program::new_target(Value::Object(BTreeMap::new()));
+
+        Runtime::default()
+            .resolve(&mut target, &program, &TimeZone::default())
+            .map_err(|err| ExpressionError::from(err.to_string()))
+    }
+
+    #[test]
+    fn calls_closures() {
+        assert_eq!(eval("double(21)").unwrap(), value!(42));
+        assert_eq!(
+            eval(r#"greet("vrl", true)"#).unwrap(),
+            value!("hello, vrl!")
+        );
+        assert_eq!(eval("must_be_even!(4)").unwrap(), value!(4));
+        assert!(eval("must_be_even!(3)").is_err());
+    }
+
+    #[test]
+    fn infers_parameters() {
+        let function = ClosureFn::new("greet", |_: String, _: bool| -> Value { Value::Null });
+        let parameters = function
+            .parameters()
+            .iter()
+            .map(|parameter| (parameter.keyword, parameter.kind))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            parameters,
+            [("value", kind::BYTES), ("arg1", kind::BOOLEAN)]
+        );
+    }
+}
@@ -0,0 +1,675 @@
+//! The subcommands of the `vrl-test` binary.
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use clap::builder::PossibleValuesParser;
+use clap::CommandFactory;
+use log::debug;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Instant;
+use vrl::compiler::runtime::Runtime;
+use vrl::compiler::TimeZone;
+use vrl::prelude::*;
+use vrl::value::Value;
+
+use crate::bench::{self, Baseline, BenchFormat, BenchOptions, BenchReport};
+use crate::cli::{
+    BenchArgs, Cli, Command, CompileArgs, CompletionsArgs, FunctionsArgs, RegistryArgs, ReplArgs,
+    RunArgs,
+};
+use crate::compile_memo::CleanCompileMemo;
+use crate::describe::describe;
+use crate::error::HarnessError;
+use crate::input::{self, Decoding, Input, InputStats, Source};
+use crate::output::{self, report_error, DeadLetters, Failure};
+use crate::pipeline::{CompileFailure, Outcome, Pipeline, StageError};
+use crate::profile::{self, Profile};
+use crate::program::{compile_source, format_diagnostics, new_target, read_sources, ProgramSource};
+use crate::registry::{self, Registry};
+use crate::repl::Session;
+use crate::state::RunState;
+use crate::timing::{TimingFormat, TimingReport};
+use crate::watch::watch;
+
+/// Process exit codes, also listed in the `--help` output.
+mod exit {
+    /// The program failed to compile.
+    pub(crate) const COMPILE_ERROR: u8 = 1;
+    /// The command line was invalid; also used by clap for parse failures.
+    pub(crate) const USAGE_ERROR: u8 = 2;
+    /// The program compiled, but with warnings.
+    pub(crate) const WARNINGS: u8 = 3;
+    /// The program failed at runtime for at least one event.
+    pub(crate) const RUNTIME_ERROR: u8 = 4;
+    /// A program or input could not be read or decoded.
+    pub(crate) const IO_ERROR: u8 = 5;
+    /// A benchmark got slower or allocated more than its baseline.
+    pub(crate) const REGRESSION: u8 = 6;
+}
+
+/// The exit code of a command that failed with `err`.
+fn exit_code(err: &HarnessError) -> u8 {
+    let io = |err: &anyhow::Error| err.chain().any(|cause| cause.is::<io::Error>());
+    match err {
+        HarnessError::CompileFailed(_) => exit::COMPILE_ERROR,
+        HarnessError::DeniedWarnings(_) => exit::WARNINGS,
+        HarnessError::RuntimeError(_) | HarnessError::Flush(_) | HarnessError::Panicked(_) => {
+            exit::RUNTIME_ERROR
+        }
+        HarnessError::InputError(_) | HarnessError::OutputError(_) => exit::IO_ERROR,
+        HarnessError::Registry(err) if io(err) => exit::IO_ERROR,
+        HarnessError::Registry(_) | HarnessError::Threads(_) => exit::USAGE_ERROR,
+    }
+}
+
+/// The functions programs are compiled against, as configured on the
+/// command line.
+fn functions(registry_args: &RegistryArgs) -> Result<Vec<Box<dyn Function>>> {
+    Ok(registry::functions(registry_args).map_err(HarnessError::Registry)?)
+}
+
+fn run(args: RunArgs, registry_args: &RegistryArgs) -> Result<ExitCode> {
+    let functions = match args.profile {
+        true => profile::instrument(functions(registry_args)?),
+        false => functions(registry_args)?,
+    };
+    let sources = args.program.program_sources();
+    let inputs = args
+        .input
+        .iter()
+        .map(|path| Input::expand(path))
+        .collect::<Result<Vec<_>>>()?
+        .concat();
+    let mut literal = parse_events(&args.events)?;
+    if literal.is_empty() && inputs.is_empty() && !has_stream_input(&args) {
+        literal.push(Value::Object(BTreeMap::new()));
+    }
+
+    if sources.contains(&ProgramSource::Stdin) && inputs.contains(&Input::Stdin) {
+        bail!("stdin cannot be used for both the program and the input");
+    }
+    let pool = match args.jobs.get() {
+        1 => None,
+        _ if has_stream_input(&args) => {
+            bail!("--jobs only applies to input files, stdin and events given on the command line")
+        }
+        jobs => Some(
+            ThreadPoolBuilder::new()
+                .num_threads(jobs)
+                .build()
+                .map_err(HarnessError::Threads)?,
+        ),
+    };
+
+    if args.watch {
+        // every run reads the inputs again, and stdin is only read once
+        if inputs.contains(&Input::Stdin) {
+            bail!(
+                "--watch cannot read the input events from stdin, as every re-run reads them again"
+            );
+        }
+        let paths = sources
+            .iter()
+            .map(|source| match source {
+                ProgramSource::File(path) => Ok(path.clone()),
+                _ => Err(anyhow!("--watch requires every program to be a file")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let names = sources.iter().map(ToString::to_string).collect::<Vec<_>>();
+        watch(&paths, |contents| {
+            eprintln!("--- compiling {} ---", names.join(", "));
+            let sources = names
+                .iter()
+                .cloned()
+                .zip(contents.iter().cloned())
+                .collect::<Vec<_>>();
+            let events = match open_events(literal.clone(), &inputs, &args) {
+                Ok(events) => events,
+                Err(err) => return eprintln!("Error: {err:?}"),
+            };
+            run_pipeline(&sources, &functions, events, &args, pool.as_ref());
+        });
+    }
+
+    let sources = read_sources(&sources)?;
+    let events = open_events(literal, &inputs, &args).map_err(HarnessError::InputError)?;
+    Ok(run_pipeline(
+        &sources,
+        &functions,
+        events,
+        &args,
+        pool.as_ref(),
+    ))
+}
+
+/// Whether events arrive from a source other than files, stdin or the command line.
+fn has_stream_input(args: &RunArgs) -> bool {
+    #[cfg(feature = "kafka")]
+    if args.kafka.kafka_topic.is_some() {
+        return true;
+    }
+
+    args.follow.is_some() || args.listen.is_some()
+}
+
+/// Opens the command line events, input files and any streaming sources in order.
+fn open_events(literal: Vec<Value>, inputs: &[Input], args: &RunArgs) -> Result<Vec<Source>> {
+    let decoding = Decoding {
+        format: args.format,
+        #[cfg(feature = "avro")]
+        avro_schema: args
+            .avro_schema
+            .as_deref()
+            .map(input::avro::read_schema)
+            .transpose()?,
+        protobuf: match (&args.proto_desc, &args.proto_message) {
+            (Some(path), Some(message)) => Some(input::protobuf::read_descriptor(path, message)?),
+            _ => None,
+        },
+        #[cfg(feature = "parquet")]
+        parquet_columns: args.parquet_columns.clone(),
+    };
+    let mut events = input::open_all(literal, inputs, &decoding)?;
+
+    #[cfg(feature = "kafka")]
+    if let Some(topic) = &args.kafka.kafka_topic {
+        events.push(input::kafka(&args.kafka, topic, args.format)?);
+    }
+    if let Some(path) = &args.follow {
+        events.push(input::follow(path, args.format)?);
+    }
+    if let Some(addr) = &args.listen {
+        events.push(input::listen(addr, args.format)?);
+    }
+
+    Ok(events)
+}
+
+/// Parses the JSON events given on the command line.
+fn parse_events(events: &[String]) -> Result<Vec<Value>> {
+    events
+        .iter()
+        .map(|event| {
+            serde_json::from_str::<serde_json::Value>(event)
+                .map(Value::from)
+                .with_context(|| format!("invalid input event: {event}"))
+        })
+        .collect()
+}
+
+/// How many events of an input are resolved at once unless it is a stream;
+/// the transformed events of a batch are written out once all of them were
+/// resolved.
+const BATCH_SIZE: usize = 4096;
+
+/// Compiles `sources` into a pipeline and runs each event through it, printing
+/// the transformed events. Per-input stats are reported when events come from
+/// more than one input.
+///
+/// Each input that isn't a stream, such as stdin piped from `tail -f`, is
+/// read in batches, whose events are resolved on the `pool` of `--jobs`
+/// threads when there's one.
+fn run_pipeline(
+    sources: &[(String, String)],
+    functions: &[Box<dyn Function>],
+    inputs: Vec<Source>,
+    args: &RunArgs,
+    pool: Option<&ThreadPool>,
+) -> ExitCode {
+    let start = Instant::now();
+    let mut timing = TimingReport::default();
+
+    let mut pipeline = match Pipeline::compile(sources, functions, args.deny_warnings) {
+        Ok(pipeline) => pipeline,
+        Err(CompileFailure::Errors) => return ExitCode::from(exit::COMPILE_ERROR),
+        Err(CompileFailure::DeniedWarnings) => return ExitCode::from(exit::WARNINGS),
+    };
+    let mut output = match output::open(&args.output) {
+        Ok(output) => output,
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            return ExitCode::from(exit::IO_ERROR);
+        }
+    };
+
+    let mut dead_letters = match DeadLetters::open(&args.output) {
+        Ok(dead_letters) => dead_letters,
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            return ExitCode::from(exit::IO_ERROR);
+        }
+    };
+
+    let error_format = args.output.output_format;
+    // the events of a batch are only kept to send those that fail to the
+    // dead letters
+    // writes out a transformed event, or sends a failed one to the dead letters
+    let mut deliver =
+        |result: Result<Outcome, StageError>, original: &Value, input_stats: &mut InputStats| {
+            match result {
+                Ok(outcome) => output.send(&args.output.emit.select(outcome)),
+                Err(e) => {
+                    input_stats.failed += 1;
+                    match dead_letters.as_mut() {
+                        Some(dead_letters) => dead_letters.send(original.clone(), &e),
+                        None => {
+                            report_error(error_format, Failure::Resolve, e);
+                            Ok(())
+                        }
+                    }
+                }
+            }
+            .map_err(|e| report_error(error_format, Failure::Output, format_args!("{e:#}")))
+        };
+
+    let report_stats = inputs.len() > 1;
+    let mut stats = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let batch_size = match input.stream {
+            true => 1,
+            false => BATCH_SIZE,
+        };
+        let mut input_stats = InputStats::new(input.name);
+        let mut invalid = 0;
+        let mut events = input.events.filter_map(|event| match event {
+            Ok(event) => Some(event),
+            Err(e) => {
+                report_error(error_format, Failure::Input, format_args!("{e:#}"));
+                invalid += 1;
+                None
+            }
+        });
+
+        loop {
+            let batch = events.by_ref().take(batch_size).collect::<Vec<_>>();
+            if batch.is_empty() {
+                break;
+            }
+            input_stats.events += batch.len();
+
+            let results = match pool {
+                Some(pool) => pool.install(|| pipeline.resolve_all(&batch)),
+                None => pipeline.run_batch(&batch),
+            };
+            for ((result, elapsed), original) in results.into_iter().zip(&batch) {
+                timing.record_event(elapsed);
+                if deliver(result, original, &mut input_stats).is_err() {
+                    return ExitCode::from(exit::IO_ERROR);
+                }
+            }
+        }
+        drop(events);
+        input_stats.invalid = invalid;
+        stats.push(input_stats);
+    }
+
+    let state_flushed = pipeline
+        .flush()
+        .map_err(|e| report_error(error_format, Failure::Resolve, format_args!("{e:#}")))
+        .is_ok();
+    let flushed = output.flush().and_then(|()| match &mut dead_letters {
+        Some(dead_letters) => dead_letters.flush(),
+        None => Ok(()),
+    });
+    if let Err(e) = flushed {
+        report_error(error_format, Failure::Output, format_args!("{e:#}"));
+        return ExitCode::from(exit::IO_ERROR);
+    }
+
+    if report_stats {
+        eprintln!("{}", InputStats::render(&stats));
+    }
+    let input_failed = stats.iter().any(|stats| stats.invalid > 0);
+    let failed = !state_flushed || stats.iter().any(|stats| stats.failed > 0);
+
+    if args.profile {
+        let resolve = pipeline
+            .stages()
+            .iter()
+            .map(|stage| stage.resolve_time)
+            .sum();
+        let profile = pipeline.state().get_or_init(profile::KEY, Profile::default);
+        eprintln!("{}", profile.render(sources, resolve));
+    }
+    if let Some(format) = args.timing {
+        timing.finish(pipeline.stages(), start.elapsed());
+        let report = match (format, args.output.output_format) {
+            (TimingFormat::Json, Some(style)) => style
+                .to_string(&timing.to_json())
+                .unwrap_or_else(|_| timing.render(format)),
+            _ => timing.render(format),
+        };
+        eprintln!("{report}");
+    }
+
+    if input_failed {
+        ExitCode::from(exit::IO_ERROR)
+    } else if failed {
+        ExitCode::from(exit::RUNTIME_ERROR)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Resolves a one-off expression against an empty event and prints the result as JSON.
+fn eval(source: &str, registry_args: &RegistryArgs) -> Result<ExitCode> {
+    let functions = functions(registry_args)?;
+
+    let state = RunState::default();
+    let Some(program) = compile_source(source, &functions, &state) else {
+        return Ok(ExitCode::from(exit::COMPILE_ERROR));
+    };
+
+    let mut target_value = new_target(Value::Object(BTreeMap::new()));
+    let resolved =
+        Runtime::default().resolve(&mut target_value, &program.program, &TimeZone::default());
+    state.flush().map_err(HarnessError::Flush)?;
+    match resolved {
+        Ok(value) => {
+            let value = serde_json::to_string(&value).map_err(HarnessError::OutputError)?;
+            println!("{value}");
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(e) => {
+            eprintln!("Error resolving expression: {e}");
+            Ok(ExitCode::from(exit::RUNTIME_ERROR))
+        }
+    }
+}
+
+/// Evaluates expressions entered line by line until stdin is closed, or
+/// replays a saved session.
+fn repl(args: ReplArgs, registry_args: &RegistryArgs) -> Result<ExitCode> {
+    let (origins, functions): (Vec<_>, Vec<_>) = registry::configured(registry_args)?
+        .build_with_origins()?
+        .into_iter()
+        .unzip();
+    let event = match &args.event {
+        Some(event) => parse_events(std::slice::from_ref(event))?.remove(0),
+        None => Value::Object(BTreeMap::new()),
+    };
+
+    let matched = Session::new(&functions, &origins, event).run(args.replay.as_deref())?;
+    Ok(match matched {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::from(exit::RUNTIME_ERROR),
+    })
+}
+
+/// Compiles the program once and resolves it repeatedly against the events,
+/// printing latency and throughput.
+fn bench(args: BenchArgs, registry_args: &RegistryArgs) -> Result<ExitCode> {
+    let functions = functions(registry_args)?;
+    if let Some(dir) = &args.compile_dir {
+        return bench_compile(dir, &functions, args.compile_runs, args.bench_format);
+    }
+    let sources = args.program.program_sources();
+    let inputs = args
+        .input
+        .iter()
+        .map(|path| Input::expand(path))
+        .collect::<Result<Vec<_>>>()?
+        .concat();
+    if sources.contains(&ProgramSource::Stdin) && inputs.contains(&Input::Stdin) {
+        bail!("stdin cannot be used for both the program and the input");
+    }
+
+    let decoding = Decoding {
+        format: args.format,
+        ..Decoding::default()
+    };
+    #[cfg(feature = "flamegraph")]
+    let flamegraph = args.flamegraph.as_deref();
+    #[cfg(not(feature = "flamegraph"))]
+    let flamegraph = None;
+    if let Some(duration) = args.duration {
+        let sources = read_sources(&sources)?;
+        let Ok(mut pipeline) = Pipeline::compile(&sources, &functions, false) else {
+            return Ok(ExitCode::from(exit::COMPILE_ERROR));
+        };
+        let report = bench::sampled(flamegraph, || {
+            bench::bench_throughput(&mut pipeline, &inputs, &decoding, duration)
+        })??;
+        pipeline.flush()?;
+        println!("{}", report.render(args.bench_format));
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut corpus = parse_events(&args.events)?;
+    for source in input::open_all(Vec::new(), &inputs, &decoding)? {
+        for event in source.events {
+            corpus.push(event.with_context(|| format!("invalid event in {}", source.name))?);
+        }
+    }
+    if corpus.is_empty() {
+        corpus.push(Value::Object(BTreeMap::new()));
+    }
+
+    let options = BenchOptions {
+        warmup: args.warmup,
+        iterations: args.iterations,
+        min_time: args.min_time.unwrap_or_default(),
+    };
+    let sources = read_sources(&sources)?;
+    let Some(report) = bench::sampled(flamegraph, || {
+        bench_with(&sources, &functions, &corpus, &options)
+    })??
+    else {
+        return Ok(ExitCode::from(exit::COMPILE_ERROR));
+    };
+    if let Some(path) = &args.histogram {
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+        report
+            .write_histogram(io::BufWriter::new(file))
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    if args.compare_stdlib {
+        let stdlib = Registry::stdlib()
+            .allow(&registry_args.allow)
+            .deny(&registry_args.deny)
+            .build()?;
+        let Some(stdlib) = bench_with(&sources, &stdlib, &corpus, &options)? else {
+            eprintln!("Error: the program only compiles with the custom functions");
+            return Ok(ExitCode::from(exit::COMPILE_ERROR));
+        };
+        println!(
+            "{}",
+            BenchReport::render_comparison(&stdlib, &report, args.bench_format)
+        );
+    } else {
+        println!("{}", report.render(args.bench_format));
+    }
+
+    let mut code = ExitCode::SUCCESS;
+    if let Some(name) = &args.baseline {
+        let baseline = Baseline::load(&Baseline::path(&args.baseline_dir, name))?;
+        let (comparison, regressions) = baseline.compare(&report.baseline(), args.threshold);
+        // keep stdout a single document for JSON reports
+        match args.bench_format {
+            BenchFormat::Text => println!("\n{comparison}"),
+            BenchFormat::Json => eprintln!("{comparison}"),
+        }
+        if regressions > 0 {
+            eprintln!(
+                "Error: {regressions} figures grew by more than {}% over baseline `{name}`",
+                args.threshold
+            );
+            code = ExitCode::from(exit::REGRESSION);
+        }
+    }
+    if let Some(name) = &args.save_baseline {
+        let path = Baseline::path(&args.baseline_dir, name);
+        report.baseline().save(&path)?;
+        eprintln!("saved baseline `{name}` to {}", path.display());
+    }
+    Ok(code)
+}
+
+/// Compiles every `.vrl` file in `dir` `runs` times, printing the latency of
+/// each.
+fn bench_compile(
+    dir: &Path,
+    functions: &[Box<dyn Function>],
+    runs: usize,
+    format: BenchFormat,
+) -> Result<ExitCode> {
+    let mut paths = fs::read_dir(dir)
+        .with_context(|| format!("failed to read {}", dir.display()))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<io::Result<Vec<_>>>()
+        .with_context(|| format!("failed to read {}", dir.display()))?;
+    paths.retain(|path| path.extension().is_some_and(|extension| extension == "vrl"));
+    paths.sort();
+    if paths.is_empty() {
+        bail!("no .vrl files in {}", dir.display());
+    }
+
+    let sources = paths
+        .into_iter()
+        .map(ProgramSource::File)
+        .collect::<Vec<_>>();
+    let report = bench::bench_compile(&read_sources(&sources)?, functions, runs);
+    println!("{}", report.render(format));
+    Ok(match report.failed() {
+        true => ExitCode::from(exit::COMPILE_ERROR),
+        false => ExitCode::SUCCESS,
+    })
+}
+
+/// Benchmarks the program compiled against `functions`, or returns `None`
+/// when it fails to compile.
+fn bench_with(
+    sources: &[(String, String)],
+    functions: &[Box<dyn Function>],
+    corpus: &[Value],
+    options: &BenchOptions,
+) -> Result<Option<BenchReport>> {
+    let Ok(mut pipeline) = Pipeline::compile(sources, functions, false) else {
+        return Ok(None);
+    };
+    let report = bench::bench(&mut pipeline, corpus, options);
+    pipeline.flush()?;
+    Ok(Some(report))
+}
+
+fn check(args: CompileArgs, registry_args: &RegistryArgs) -> Result<ExitCode> {
+    let memo = match args.no_cache {
+        true => None,
+        false => CleanCompileMemo::open(args.cache_dir.as_deref(), registry_args),
+    };
+    let mut sources = read_sources(&args.program.program_sources())?;
+    if let Some(memo) = &memo {
+        sources.retain(|(name, source)| {
+            let clean = memo.contains(source);
+            if clean {
+                debug!("{name} compiled cleanly before, skipping it");
+            }
+            !clean
+        });
+    }
+    let mut code = ExitCode::SUCCESS;
+    if sources.is_empty() {
+        return Ok(code);
+    }
+
+    let functions = functions(registry_args)?;
+    for (_, source) in sources {
+        let state = RunState::default();
+        match compile_source(&source, &functions, &state) {
+            None => code = ExitCode::from(exit::COMPILE_ERROR),
+            Some(program) if !program.warnings.is_empty() => {
+                eprintln!("{}", format_diagnostics(&source, program.warnings));
+                if code == ExitCode::SUCCESS {
+                    code = ExitCode::from(exit::WARNINGS);
+                }
+            }
+            Some(_) => {
+                if let Some(memo) = &memo {
+                    memo.insert(&source, &state.inputs());
+                }
+            }
+        }
+    }
+
+    Ok(code)
+}
+
+fn list_functions(args: FunctionsArgs, registry_args: &RegistryArgs) -> Result<ExitCode> {
+    let mut functions = registry::configured(registry_args)?.build_with_origins()?;
+    functions.sort_by_key(|(_, f)| f.identifier());
+
+    for name in &args.names {
+        if !functions.iter().any(|(_, f)| f.identifier() == name) {
+            bail!("unknown function: {name}");
+        }
+    }
+
+    for (origin, function) in functions
+        .iter()
+        .filter(|(_, f)| args.names.is_empty() || args.names.iter().any(|n| n == f.identifier()))
+    {
+        println!("{}", describe(function.as_ref(), *origin));
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Prints a completion script, offering the registered function identifiers as
+/// candidates for `--eval`.
+fn completions(args: CompletionsArgs, registry_args: &RegistryArgs) -> Result<ExitCode> {
+    let functions = functions(registry_args)?;
+    let identifiers = functions.iter().map(|f| f.identifier()).collect::<Vec<_>>();
+    let mut command = Cli::command().mut_arg("eval", |arg| {
+        arg.value_parser(PossibleValuesParser::new(identifiers))
+    });
+
+    let name = command.get_name().to_owned();
+    clap_complete::generate(args.shell, &mut command, name, &mut io::stdout());
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Runs the `vrl-test` command line.
+pub fn main() -> ExitCode {
+    let cli = Cli::parse_args();
+
+    // Initialize the logger
+    env_logger::Builder::new()
+        .filter_level(cli.log_level())
+        .format_timestamp(None)
+        .parse_default_env()
+        .init();
+
+    let result = match (cli.eval, cli.command) {
+        (Some(source), _) => eval(&source, &cli.registry),
+        (None, Some(Command::Run(args))) => run(*args, &cli.registry),
+        (None, Some(Command::Compile(args))) => check(args, &cli.registry),
+        (None, Some(Command::Bench(args))) => bench(*args, &cli.registry),
+        (None, Some(Command::Repl(args))) => repl(args, &cli.registry),
+        (None, Some(Command::Functions(args))) => list_functions(args, &cli.registry),
+        (None, Some(Command::Completions(args))) => completions(args, &cli.registry),
+        (None, None) => unreachable!("clap requires a subcommand or --eval"),
+    };
+
+    result.unwrap_or_else(|err| {
+        // the message of a harness error already includes its causes
+        if let Some(err) = err.downcast_ref::<HarnessError>() {
+            eprintln!("Error: {err}");
+            return ExitCode::from(exit_code(err));
+        }
+        eprintln!("Error: {err:?}");
+        let io = err
+            .chain()
+            .any(|cause| cause.is::<io::Error>() || cause.is::<serde_json::Error>());
+        ExitCode::from(if io {
+            exit::IO_ERROR
+        } else {
+            exit::USAGE_ERROR
+        })
+    })
+}
@@ -0,0 +1,200 @@
+//! A memo of the programs `compile` found to compile cleanly, kept in the
+//! user's cache directory, so that checking the same programs over and over,
+//! e.g. from an editor or a commit hook, skips compiling those that didn't
+//! change.
+//!
+//! Compiled programs are trees of trait objects that can't be written out, so
+//! nothing compiled is stored or reused: an entry only records that the
+//! program, keyed by a hash of its source, compiled without errors or
+//! warnings against a registry, keyed by a hash of everything its functions
+//! come from, and lists the files functions read while compiling it, such as
+//! the databases of `geoip`. The entry only counts while none of those files
+//! changed. Programs with diagnostics are compiled every time so that they're
+//! printed.
+//!
+//! Only `compile` uses the memo: `run`, `watch`, `bench` and the REPL need
+//! the compiled program itself, so they always compile.
+
+use log::warn;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::cli::RegistryArgs;
+
+/// The directory of the memo under `$XDG_CACHE_HOME` or `~/.cache`.
+const DIR_NAME: &str = "vrl-test";
+
+#[derive(Debug)]
+pub(crate) struct CleanCompileMemo {
+    dir: PathBuf,
+    /// Hash of what the functions are built from, shared by every entry.
+    registry: u64,
+}
+
+impl CleanCompileMemo {
+    /// The memo in `dir`, or in the user's cache directory when `None`.
+    /// `None` when there's no cache directory, or the executable or the keys
+    /// can't be told apart from other builds or keys.
+    pub(crate) fn open(dir: Option<&Path>, registry_args: &RegistryArgs) -> Option<Self> {
+        let dir = dir.map(Path::to_owned).or_else(default_dir)?;
+        Some(Self {
+            dir,
+            registry: registry_hash(registry_args)?,
+        })
+    }
+
+    /// Whether `source` compiled cleanly before, reading files that haven't
+    /// changed since.
+    pub(crate) fn contains(&self, source: &str) -> bool {
+        let Ok(entry) = fs::read_to_string(self.path(source)) else {
+            return false;
+        };
+        entry.lines().all(|line| {
+            line.split_once(' ')
+                .is_some_and(|(modified, input)| modified == modified_nanos(Path::new(input)))
+        })
+    }
+
+    /// Records that `source` compiled cleanly, reading `inputs`. Failing to
+    /// is only logged, as the program gets compiled again next time.
+    pub(crate) fn insert(&self, source: &str, inputs: &[PathBuf]) {
+        let path = self.path(source);
+        let entry = inputs
+            .iter()
+            .map(|input| format!("{} {}\n", modified_nanos(input), input.display()))
+            .collect::<String>();
+        if let Err(err) = fs::create_dir_all(&self.dir).and_then(|()| fs::write(&path, entry)) {
+            warn!("failed to write {}: {err}", path.display());
+        }
+    }
+
+    fn path(&self, source: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        self.dir
+            .join(format!("{:016x}-{:016x}", hasher.finish(), self.registry))
+    }
+}
+
+/// When `path` was last modified, in nanoseconds since the Unix epoch, or
+/// `-` when it can't be told.
+fn modified_nanos(path: &Path) -> String {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or_else(|| "-".to_owned(), |since| since.as_nanos().to_string())
+}
+
+fn default_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join(DIR_NAME))
+}
+
+/// Hashes the executable holding the built-in functions, by modification
+/// time, along with the plugins, enrichment tables and key store file the
+/// same way, the registry arguments as a whole and the working directory
+/// relative paths are resolved against: what those files hold decides what
+/// compiles, too. A rebuild changes the hash, which also keeps it valid
+/// across versions of the standard library's hasher. Keys read from
+/// environment variables aren't hashed, so nothing is remembered with them.
+fn registry_hash(args: &RegistryArgs) -> Option<u64> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+
+    let mut hasher = DefaultHasher::new();
+    modified(&std::env::current_exe().ok()?)
+        .ok()?
+        .hash(&mut hasher);
+    for plugin in &args.plugin {
+        modified(plugin).ok().hash(&mut hasher);
+    }
+    #[cfg(feature = "enrichment")]
+    for (_, path) in &args.enrichment_table {
+        modified(path).ok().hash(&mut hasher);
+    }
+    #[cfg(feature = "crypto")]
+    match &args.key_store {
+        Some(crate::functions::KeyStore::File(path)) => modified(path).ok().hash(&mut hasher),
+        Some(crate::functions::KeyStore::Env(_)) => return None,
+        None => {}
+    }
+    format!("{args:?}").hash(&mut hasher);
+    std::env::current_dir().ok()?.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn remembers_clean_programs() {
+        let dir =
+            std::env::temp_dir().join(format!("vrl-test-{}-compile-memo", std::process::id()));
+        let memo = CleanCompileMemo {
+            dir: dir.clone(),
+            registry: 1,
+        };
+        assert!(!memo.contains(".a = 1"));
+
+        memo.insert(".a = 1", &[]);
+        assert!(memo.contains(".a = 1"));
+        assert!(!memo.contains(".a = 2"));
+        // another registry has programs of its own
+        let other = CleanCompileMemo {
+            dir: dir.clone(),
+            registry: 2,
+        };
+        assert!(!other.contains(".a = 1"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn forgets_programs_once_their_inputs_change() {
+        let dir = std::env::temp_dir().join(format!("vrl-test-{}-memo-inputs", std::process::id()));
+        let input = dir.join("table.db");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&input, "").unwrap();
+        let memo = CleanCompileMemo {
+            dir: dir.clone(),
+            registry: 1,
+        };
+
+        memo.insert(".a = 1", std::slice::from_ref(&input));
+        assert!(memo.contains(".a = 1"));
+        let file = fs::File::options().write(true).open(&input).unwrap();
+        file.set_modified(UNIX_EPOCH).unwrap();
+        assert!(!memo.contains(".a = 1"));
+        fs::remove_file(&input).unwrap();
+        assert!(!memo.contains(".a = 1"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(feature = "enrichment")]
+    #[test]
+    fn enrichment_tables_change_the_registry() {
+        let path =
+            std::env::temp_dir().join(format!("vrl-test-{}-cache-table.db", std::process::id()));
+        fs::write(&path, "").unwrap();
+        let args = RegistryArgs {
+            enrichment_table: vec![("services".to_owned(), path.clone())],
+            ..RegistryArgs::default()
+        };
+        let before = registry_hash(&args).unwrap();
+        assert_eq!(registry_hash(&args), Some(before));
+
+        let file = fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap();
+        assert_ne!(registry_hash(&args), Some(before));
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,97 @@
+use std::fmt::Write as _;
+use std::panic::{self, AssertUnwindSafe};
+use vrl::compiler::function::Example;
+use vrl::compiler::Function;
+
+/// Where a registered function's implementation comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Origin {
+    /// The stock `vrl::stdlib` implementation.
+    Stdlib,
+    /// A custom implementation replacing the stdlib function of the same name.
+    Override,
+    /// A custom function with no stdlib counterpart.
+    Custom,
+    /// A function loaded from a `--plugin` library.
+    Plugin,
+    /// A function removed by `--allow` or `--deny`; calling it fails to compile.
+    Disabled,
+    /// Another name for a registered function, deprecated in its favour.
+    Alias,
+}
+
+impl Origin {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Origin::Stdlib => "stdlib",
+            Origin::Override => "override",
+            Origin::Custom => "custom",
+            Origin::Plugin => "plugin",
+            Origin::Disabled => "disabled",
+            Origin::Alias => "alias",
+        }
+    }
+}
+
+/// Renders a function's signature, origin and examples as plain text.
+pub(crate) fn describe(function: &dyn Function, origin: Origin) -> String {
+    let mut out = String::new();
+
+    let _ = writeln!(out, "{} ({})", function.identifier(), origin.as_str());
+    for parameter in function.parameters() {
+        let _ = writeln!(
+            out,
+            "  {}: {}{}",
+            parameter.keyword,
+            parameter.kind(),
+            if parameter.required {
+                ""
+            } else {
+                " (optional)"
+            }
+        );
+    }
+    match examples(function) {
+        Some(examples) => {
+            for example in examples {
+                let _ = writeln!(out, "  example: {}", example.title);
+                let _ = match example.result {
+                    Ok(result) => writeln!(out, "    {} => {}", example.source, result),
+                    Err(error) => writeln!(out, "    {} => error: {}", example.source, error),
+                };
+            }
+        }
+        None => {
+            let _ = writeln!(out, "  examples unavailable");
+        }
+    }
+
+    out
+}
+
+/// Returns the function's examples, or `None` if building them panics.
+///
+/// A few stdlib functions (e.g. `encode_proto`) build their examples from
+/// fixture files that only exist in the vrl source tree.
+fn examples(function: &dyn Function) -> Option<&'static [Example]> {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let examples = panic::catch_unwind(AssertUnwindSafe(|| function.examples())).ok();
+    panic::set_hook(hook);
+    examples
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::functions::Split;
+
+    #[test]
+    fn describe_split() {
+        let description = describe(&Split, Origin::Override);
+
+        assert!(description.starts_with("split (override)\n"));
+        assert!(description.contains("  limit: integer (optional)\n"));
+        assert!(description.contains("    split(\"foobar\", \"b\") => [\"foo\", \"ar\"]\n"));
+    }
+}
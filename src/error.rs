@@ -0,0 +1,76 @@
+//! The errors of the [`Harness`](crate::Harness) API.
+
+use std::fmt;
+
+use crate::pipeline::{StageDiagnostics, StageError};
+
+/// Why a [`Harness`](crate::Harness) failed to compile or run programs.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum HarnessError {
+    /// The functions to compile against couldn't be set up, e.g. because a
+    /// plugin failed to load.
+    Registry(anyhow::Error),
+    /// At least one program failed to compile. Holds the diagnostics of every
+    /// failing program, including those with denied warnings.
+    CompileFailed(Vec<StageDiagnostics>),
+    /// Every program compiled, but with warnings, which were denied.
+    DeniedWarnings(Vec<StageDiagnostics>),
+    /// A program failed at runtime for an event.
+    RuntimeError(StageError),
+    /// Events couldn't be read, or one of them couldn't be decoded.
+    InputError(anyhow::Error),
+    /// A result couldn't be encoded, e.g. as JSON.
+    OutputError(serde_json::Error),
+    /// The threads to resolve events on couldn't be started.
+    Threads(rayon::ThreadPoolBuildError),
+    /// A stateful function failed to flush its state.
+    Flush(anyhow::Error),
+    /// Resolving events panicked, e.g. in a custom function, with this
+    /// message. Every event of the batch it was in fails with it.
+    Panicked(String),
+}
+
+impl fmt::Display for HarnessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Registry(err) => write!(f, "failed to set up the functions: {err:#}"),
+            Self::CompileFailed(failed) => {
+                f.write_str("the program failed to compile")?;
+                failed
+                    .iter()
+                    .try_for_each(|failed| write!(f, "\n\n{failed}"))
+            }
+            Self::DeniedWarnings(failed) => {
+                f.write_str("the program compiled with warnings")?;
+                failed
+                    .iter()
+                    .try_for_each(|failed| write!(f, "\n\n{failed}"))
+            }
+            Self::RuntimeError(err) => err.fmt(f),
+            Self::InputError(err) => write!(f, "{err:#}"),
+            Self::OutputError(err) => write!(f, "failed to encode the event: {err}"),
+            Self::Threads(err) => write!(f, "failed to start threads: {err}"),
+            Self::Flush(err) => write!(f, "failed to flush: {err:#}"),
+            Self::Panicked(message) => write!(f, "resolving events panicked: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for HarnessError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Registry(err) | Self::InputError(err) | Self::Flush(err) => Some(err.as_ref()),
+            Self::RuntimeError(err) => Some(err),
+            Self::OutputError(err) => Some(err),
+            Self::Threads(err) => Some(err),
+            Self::CompileFailed(_) | Self::DeniedWarnings(_) | Self::Panicked(_) => None,
+        }
+    }
+}
+
+impl From<StageError> for HarnessError {
+    fn from(err: StageError) -> Self {
+        Self::RuntimeError(err)
+    }
+}
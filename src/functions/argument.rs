@@ -0,0 +1,96 @@
+//! Arguments of custom function expressions that skip dynamic dispatch when
+//! they're literals.
+//!
+//! VRL hands functions their arguments as `Box<dyn Expression>`, so even a
+//! literal pattern or flag costs a virtual call into the literal expression
+//! for every event. Arguments whose value is known when compiling keep that
+//! value instead, and only the others go through the expression.
+
+use vrl::prelude::*;
+
+#[derive(Debug, Clone)]
+pub(crate) enum Argument {
+    /// The value of a literal, or any expression resolving to a constant.
+    Literal(Value),
+    Expression(Box<dyn Expression>),
+}
+
+impl Argument {
+    /// Keeps the value of `expression` when it is constant in `state`.
+    pub(crate) fn new(expression: Box<dyn Expression>, state: &TypeState) -> Self {
+        match expression.resolve_constant(state) {
+            Some(value) => Self::Literal(value),
+            None => Self::Expression(expression),
+        }
+    }
+
+    /// The value of the argument, when it's known when compiling.
+    pub(crate) fn literal(&self) -> Option<&Value> {
+        match self {
+            Self::Literal(value) => Some(value),
+            Self::Expression(_) => None,
+        }
+    }
+
+    pub(crate) fn resolve(&self, ctx: &mut Context) -> Resolved {
+        match self {
+            Self::Literal(value) => Ok(value.clone()),
+            Self::Expression(expression) => expression.resolve(ctx),
+        }
+    }
+}
+
+/// Runs `prepare` on the values of `arguments` when compiling, if they're all
+/// literals, so that what a function derives from them, such as a regex built
+/// from a pattern, is computed once rather than for every event. `None` when
+/// any of them has to wait for the event.
+pub(crate) fn fold<const N: usize, T>(
+    arguments: [&Argument; N],
+    prepare: impl FnOnce([&Value; N]) -> T,
+) -> Option<T> {
+    let literals = arguments.map(Argument::literal);
+    if literals.contains(&None) {
+        return None;
+    }
+    Some(prepare(
+        literals.map(|literal| literal.expect("checked above")),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vrl::compiler::expression::Literal;
+    use vrl::compiler::{TargetValue, TimeZone};
+    use vrl::value;
+
+    #[test]
+    fn literals_skip_the_expression() {
+        let state = TypeState::default();
+        let literal = Argument::new(Box::new(Literal::from("a")), &state);
+        assert!(matches!(&literal, Argument::Literal(value) if *value == Value::from("a")));
+
+        let mut target = TargetValue {
+            value: value!({}),
+            metadata: value!({}),
+            secrets: Default::default(),
+        };
+        let mut runtime = state::RuntimeState::default();
+        let timezone = TimeZone::default();
+        let mut ctx = Context::new(&mut target, &mut runtime, &timezone);
+        assert_eq!(literal.resolve(&mut ctx).unwrap(), Value::from("a"));
+    }
+
+    #[test]
+    fn folds_literals_only() {
+        let state = TypeState::default();
+        let a = Argument::new(Box::new(Literal::from("a")), &state);
+        let b = Argument::new(Box::new(Literal::from("b")), &state);
+        let joined =
+            |[a, b]: [&Value; 2]| format!("{}{}", a.as_str().unwrap(), b.as_str().unwrap());
+
+        assert_eq!(fold([&a, &b], joined).as_deref(), Some("ab"));
+        let query = Argument::Expression(Box::new(Literal::from("b")));
+        assert_eq!(fold([&a, &query], joined), None);
+    }
+}
@@ -0,0 +1,353 @@
+//! An in-process cache shared by the `cache_get` and `cache_set` calls of a
+//! run, for prototyping deduplication and memoized enrichment.
+
+use log::info;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use vrl::prelude::*;
+
+use crate::state::{FunctionState, RunState};
+
+/// Limits of the cache, from `--cache-ttl` and `--cache-max-entries`.
+#[derive(Clone, Copy, Debug)]
+pub struct CacheConfig {
+    /// How long entries live unless `cache_set` says otherwise; forever
+    /// without one.
+    pub ttl: Option<Duration>,
+    /// How many entries the cache holds before evicting the oldest.
+    pub max_entries: usize,
+}
+
+struct Entry {
+    value: Value,
+    expires: Option<Instant>,
+    /// When the entry was set, relative to the others.
+    sequence: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    map: HashMap<String, Entry>,
+    sequence: u64,
+    hits: u64,
+    misses: u64,
+}
+
+struct Cache {
+    config: CacheConfig,
+    entries: Mutex<Entries>,
+}
+
+impl Cache {
+    fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::default(),
+        }
+    }
+
+    fn get(&self, key: &str, now: Instant) -> Option<Value> {
+        let mut entries = self.entries.lock().expect("cache lock poisoned");
+        let value = match entries.map.get(key) {
+            Some(entry) if entry.expires.is_some_and(|expires| expires <= now) => {
+                entries.map.remove(key);
+                None
+            }
+            entry => entry.map(|entry| entry.value.clone()),
+        };
+        match value {
+            Some(_) => entries.hits += 1,
+            None => entries.misses += 1,
+        }
+        value
+    }
+
+    fn set(&self, key: String, value: Value, ttl: Option<Duration>, now: Instant) {
+        if self.config.max_entries == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().expect("cache lock poisoned");
+        if !entries.map.contains_key(&key) && entries.map.len() >= self.config.max_entries {
+            entries
+                .map
+                .retain(|_, entry| entry.expires.is_none_or(|expires| expires > now));
+            // still full without the expired entries, so the oldest makes room
+            if entries.map.len() >= self.config.max_entries {
+                let oldest = entries
+                    .map
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.sequence)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    entries.map.remove(&oldest);
+                }
+            }
+        }
+
+        entries.sequence += 1;
+        let entry = Entry {
+            value,
+            expires: ttl.or(self.config.ttl).map(|ttl| now + ttl),
+            sequence: entries.sequence,
+        };
+        entries.map.insert(key, entry);
+    }
+}
+
+impl FunctionState for Cache {
+    fn flush(&self) -> anyhow::Result<()> {
+        let entries = self.entries.lock().expect("cache lock poisoned");
+        info!(
+            "cache: {} hits, {} misses, {} entries",
+            entries.hits,
+            entries.misses,
+            entries.map.len()
+        );
+        Ok(())
+    }
+}
+
+/// The cache of the run being compiled; outside of one, e.g. in tests, each
+/// call has its own.
+fn cache(config: CacheConfig, ctx: &FunctionCompileContext) -> Arc<Cache> {
+    match ctx.get_external_context::<RunState>() {
+        Some(state) => state.get_or_init("cache", || Cache::new(config)),
+        None => Arc::new(Cache::new(config)),
+    }
+}
+
+/// Returns the value cached for `key`, or null when there is none or it
+/// expired.
+#[derive(Clone, Copy, Debug)]
+pub struct CacheGet(pub CacheConfig);
+
+impl Function for CacheGet {
+    fn identifier(&self) -> &'static str {
+        "cache_get"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[Parameter {
+            keyword: "key",
+            kind: kind::BYTES,
+            required: true,
+        }]
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[Example {
+            title: "cache miss",
+            source: r#"cache_get("user:1")"#,
+            result: Ok("null"),
+        }]
+    }
+
+    fn compile(
+        &self,
+        _state: &state::TypeState,
+        ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        Ok(CacheGetFn {
+            key: arguments.required("key"),
+            cache: cache(self.0, ctx),
+        }
+        .as_expr())
+    }
+}
+
+#[derive(Clone)]
+struct CacheGetFn {
+    key: Box<dyn Expression>,
+    cache: Arc<Cache>,
+}
+
+impl std::fmt::Debug for CacheGetFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CacheGetFn")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FunctionExpression for CacheGetFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        let key = self.key.resolve(ctx)?;
+        let key = key.try_bytes_utf8_lossy()?;
+        Ok(self.cache.get(&key, Instant::now()).unwrap_or(Value::Null))
+    }
+
+    fn type_def(&self, _: &state::TypeState) -> TypeDef {
+        TypeDef::any().infallible()
+    }
+}
+
+/// Caches `value` under `key`, for `ttl_ms` milliseconds when given, and
+/// returns it.
+#[derive(Clone, Copy, Debug)]
+pub struct CacheSet(pub CacheConfig);
+
+impl Function for CacheSet {
+    fn identifier(&self) -> &'static str {
+        "cache_set"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[
+            Parameter {
+                keyword: "key",
+                kind: kind::BYTES,
+                required: true,
+            },
+            Parameter {
+                keyword: "value",
+                kind: kind::ANY,
+                required: true,
+            },
+            Parameter {
+                keyword: "ttl_ms",
+                kind: kind::INTEGER,
+                required: false,
+            },
+        ]
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[Example {
+            title: "cache for a minute",
+            source: r#"cache_set("user:1", "alice", ttl_ms: 60000)"#,
+            result: Ok(r#""alice""#),
+        }]
+    }
+
+    fn compile(
+        &self,
+        _state: &state::TypeState,
+        ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        Ok(CacheSetFn {
+            key: arguments.required("key"),
+            value: arguments.required("value"),
+            ttl_ms: arguments.optional("ttl_ms"),
+            cache: cache(self.0, ctx),
+        }
+        .as_expr())
+    }
+}
+
+#[derive(Clone)]
+struct CacheSetFn {
+    key: Box<dyn Expression>,
+    value: Box<dyn Expression>,
+    ttl_ms: Option<Box<dyn Expression>>,
+    cache: Arc<Cache>,
+}
+
+impl std::fmt::Debug for CacheSetFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CacheSetFn")
+            .field("key", &self.key)
+            .field("value", &self.value)
+            .field("ttl_ms", &self.ttl_ms)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FunctionExpression for CacheSetFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        let key = self.key.resolve(ctx)?;
+        let key = key.try_bytes_utf8_lossy()?.into_owned();
+        let value = self.value.resolve(ctx)?;
+        let ttl = match &self.ttl_ms {
+            Some(ttl_ms) => {
+                let ttl_ms = ttl_ms.resolve(ctx)?.try_integer()?;
+                Some(Duration::from_millis(ttl_ms.max(0) as u64))
+            }
+            None => None,
+        };
+        self.cache.set(key, value.clone(), ttl, Instant::now());
+        Ok(value)
+    }
+
+    fn type_def(&self, state: &state::TypeState) -> TypeDef {
+        self.value.type_def(state).infallible()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::program::{compile_source, new_target};
+    use vrl::compiler::runtime::Runtime;
+    use vrl::compiler::TimeZone;
+    use vrl::value;
+
+    const CONFIG: CacheConfig = CacheConfig {
+        ttl: None,
+        max_entries: 2,
+    };
+
+    #[test]
+    fn expires_entries() {
+        let cache = Cache::new(CacheConfig {
+            ttl: Some(Duration::from_secs(60)),
+            ..CONFIG
+        });
+        let now = Instant::now();
+        cache.set("a".to_owned(), value!(1), None, now);
+        cache.set("b".to_owned(), value!(2), Some(Duration::from_secs(1)), now);
+
+        let later = now + Duration::from_secs(2);
+        assert_eq!(cache.get("a", later), Some(value!(1)));
+        assert_eq!(cache.get("b", later), None);
+        assert_eq!(cache.get("a", now + Duration::from_secs(60)), None);
+    }
+
+    #[test]
+    fn evicts_the_oldest_entry() {
+        let cache = Cache::new(CONFIG);
+        let now = Instant::now();
+        cache.set("a".to_owned(), value!(1), None, now);
+        cache.set("b".to_owned(), value!(2), None, now);
+        cache.set("a".to_owned(), value!(3), None, now);
+        cache.set("c".to_owned(), value!(4), None, now);
+
+        assert_eq!(cache.get("a", now), Some(value!(3)));
+        assert_eq!(cache.get("b", now), None);
+        assert_eq!(cache.get("c", now), Some(value!(4)));
+    }
+
+    #[test]
+    fn evicts_expired_entries_first() {
+        let cache = Cache::new(CONFIG);
+        let now = Instant::now();
+        cache.set("a".to_owned(), value!(1), None, now);
+        cache.set("b".to_owned(), value!(2), Some(Duration::ZERO), now);
+        cache.set("c".to_owned(), value!(3), None, now);
+
+        assert_eq!(cache.get("a", now), Some(value!(1)));
+        assert_eq!(cache.get("c", now), Some(value!(3)));
+    }
+
+    #[test]
+    fn shares_entries_across_programs() {
+        let functions: Vec<Box<dyn Function>> =
+            vec![Box::new(CacheGet(CONFIG)), Box::new(CacheSet(CONFIG))];
+        let state = RunState::default();
+        let set = compile_source(r#"cache_set("a", [1])"#, &functions, &state).unwrap();
+        let get = compile_source(r#"cache_get("a")"#, &functions, &state).unwrap();
+
+        let resolve = |program| {
+            let mut target = new_target(value!({}));
+            Runtime::default()
+                .resolve(&mut target, program, &TimeZone::default())
+                .unwrap()
+        };
+        assert_eq!(resolve(&get.program), value!(null));
+        assert_eq!(resolve(&set.program), value!([1]));
+        assert_eq!(resolve(&get.program), value!([1]));
+        assert!(state.flush().is_ok());
+    }
+}
@@ -0,0 +1,120 @@
+use log::info;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use vrl::prelude::*;
+
+use crate::state::{FunctionState, RunState};
+
+/// Counts per key, shared by every `counter` call in a run.
+#[derive(Debug, Default)]
+struct Counts(Mutex<BTreeMap<String, i64>>);
+
+impl Counts {
+    fn increment(&self, key: String) -> i64 {
+        let mut counts = self.0.lock().expect("counter lock poisoned");
+        let count = counts.entry(key).or_default();
+        *count += 1;
+        *count
+    }
+}
+
+impl FunctionState for Counts {
+    fn flush(&self) -> anyhow::Result<()> {
+        for (key, count) in self.0.lock().expect("counter lock poisoned").iter() {
+            info!("counter {key:?}: {count}");
+        }
+        Ok(())
+    }
+}
+
+/// Increments the counter for `key` and returns its new value. Counters
+/// persist across the events of a run and are logged when it ends.
+#[derive(Clone, Copy, Debug)]
+pub struct Counter;
+
+impl Function for Counter {
+    fn identifier(&self) -> &'static str {
+        "counter"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[Parameter {
+            keyword: "key",
+            kind: kind::BYTES,
+            required: false,
+        }]
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[Example {
+            title: "first call",
+            source: r#"counter("events")"#,
+            result: Ok("1"),
+        }]
+    }
+
+    fn compile(
+        &self,
+        _state: &state::TypeState,
+        ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        // outside of a run, e.g. in tests, the counts only live as long as the program
+        let counts = match ctx.get_external_context::<RunState>() {
+            Some(state) => state.get_or_init(self.identifier(), Counts::default),
+            None => Arc::default(),
+        };
+
+        Ok(CounterFn {
+            key: arguments.optional("key").unwrap_or_else(|| expr!("")),
+            counts,
+        }
+        .as_expr())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CounterFn {
+    key: Box<dyn Expression>,
+    counts: Arc<Counts>,
+}
+
+impl FunctionExpression for CounterFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        let key = self.key.resolve(ctx)?.try_bytes_utf8_lossy()?.into_owned();
+        Ok(self.counts.increment(key).into())
+    }
+
+    fn type_def(&self, _: &state::TypeState) -> TypeDef {
+        TypeDef::integer().infallible()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::program::{compile_source, new_target};
+    use vrl::compiler::runtime::Runtime;
+    use vrl::compiler::{Program, TimeZone};
+    use vrl::value;
+
+    #[test]
+    fn counts_across_programs() {
+        let functions: Vec<Box<dyn Function>> = vec![Box::new(Counter)];
+        let state = RunState::default();
+        let first = compile_source(r#"counter("a")"#, &functions, &state).unwrap();
+        let second = compile_source(r#"counter("a") + counter("b")"#, &functions, &state).unwrap();
+
+        let mut runtime = Runtime::default();
+        let mut resolve = |program: &Program| {
+            let mut target = new_target(value!({}));
+            let result = runtime.resolve(&mut target, program, &TimeZone::default());
+            runtime.clear();
+            result.unwrap()
+        };
+        assert_eq!(resolve(&first.program), value!(1));
+        assert_eq!(resolve(&first.program), value!(2));
+        assert_eq!(resolve(&second.program), value!(4));
+        assert!(state.flush().is_ok());
+    }
+}
@@ -0,0 +1,141 @@
+use vrl::diagnostic::{DiagnosticMessage, Label};
+use vrl::prelude::*;
+
+use super::keys::{compile_key, Key, KeyStore};
+
+/// Computes the HMAC of a value with a key from the key store, returning the
+/// raw bytes like the stdlib `hmac` does.
+#[derive(Debug)]
+pub struct HmacSign {
+    store: Option<KeyStore>,
+}
+
+impl HmacSign {
+    pub fn new(store: Option<KeyStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl Function for HmacSign {
+    fn identifier(&self) -> &'static str {
+        "hmac_sign"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[
+            Parameter {
+                keyword: "value",
+                kind: kind::BYTES,
+                required: true,
+            },
+            Parameter {
+                keyword: "key",
+                kind: kind::BYTES,
+                required: true,
+            },
+        ]
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[Example {
+            title: "webhook signature",
+            source: r#"encode_base16(hmac_sign("payload", "webhook"))"#,
+            result: Ok(r#""b82fcb791acec57859b989b430a826488ce2e479fdf92326bd0a2e8375a42ba4""#),
+        }]
+    }
+
+    fn compile(
+        &self,
+        state: &state::TypeState,
+        ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        let key = compile_key(self.store.as_ref(), state, ctx, &arguments)?;
+        if matches!(key, Key::Ed25519(_)) {
+            return Err(Box::new(ExpressionError::Error {
+                message: "an ed25519 public key can only verify signatures".to_owned(),
+                labels: vec![Label::primary("in this call", ctx.span())],
+                notes: vec![],
+            }) as Box<dyn DiagnosticMessage>);
+        }
+
+        Ok(HmacSignFn {
+            value: arguments.required("value"),
+            key,
+        }
+        .as_expr())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct HmacSignFn {
+    value: Box<dyn Expression>,
+    key: Key,
+}
+
+impl FunctionExpression for HmacSignFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        let value = self.value.resolve(ctx)?;
+        let signature = self
+            .key
+            .sign(&value.try_bytes()?)
+            .expect("compiled with an HMAC key");
+        Ok(Value::Bytes(signature.into()))
+    }
+
+    fn type_def(&self, _: &state::TypeState) -> TypeDef {
+        TypeDef::bytes().infallible()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::program::{compile_source, new_target};
+    use crate::state::RunState;
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use vrl::compiler::runtime::Runtime;
+    use vrl::compiler::TimeZone;
+    use vrl::value;
+
+    fn compile(source: &str, store: Option<KeyStore>) -> Option<vrl::compiler::Program> {
+        let mut functions = vrl::stdlib::all();
+        functions.push(Box::new(HmacSign::new(store)));
+        compile_source(source, &functions, &RunState::default()).map(|result| result.program)
+    }
+
+    #[test]
+    fn signs_with_stored_keys() {
+        std::env::set_var("VRL_TEST_SIGN_WEBHOOK", "hmac-sha256:c2VjcmV0");
+        let store = KeyStore::Env("VRL_TEST_SIGN_".to_owned());
+        let program = compile(
+            r#"encode_base16(hmac_sign("payload", "webhook"))"#,
+            Some(store),
+        )
+        .unwrap();
+
+        let mut target = new_target(value!({}));
+        // echo -n payload | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            Runtime::default()
+                .resolve(&mut target, &program, &TimeZone::default())
+                .unwrap(),
+            value!("b82fcb791acec57859b989b430a826488ce2e479fdf92326bd0a2e8375a42ba4")
+        );
+    }
+
+    #[test]
+    fn needs_a_signing_key() {
+        let public = ed25519_dalek::SigningKey::from_bytes(&[7; 32]).verifying_key();
+        std::env::set_var(
+            "VRL_TEST_SIGN_PUBLIC",
+            format!("ed25519:{}", STANDARD.encode(public.as_bytes())),
+        );
+        let store = KeyStore::Env("VRL_TEST_SIGN_".to_owned());
+
+        assert!(compile(r#"hmac_sign("payload", "public")"#, Some(store.clone())).is_none());
+        assert!(compile(r#"hmac_sign("payload", "missing")"#, Some(store)).is_none());
+        assert!(compile(r#"hmac_sign("payload", "webhook")"#, None).is_none());
+    }
+}
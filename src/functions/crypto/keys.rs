@@ -0,0 +1,263 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ed25519_dalek::{Signature, VerifyingKey};
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha512};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use subtle::ConstantTimeEq;
+use vrl::diagnostic::{DiagnosticMessage, Label, Note, Span};
+use vrl::prelude::*;
+
+/// Where the keys programs refer to by name are loaded from.
+///
+/// Each key is an `<algorithm>:<base64 key>` string: `hmac-sha256`,
+/// `hmac-sha512` or `ed25519`, the latter being a 32 byte public key.
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyStore {
+    /// A TOML file of `name = "<algorithm>:<base64 key>"` entries.
+    File(PathBuf),
+    /// Environment variables, key `name` being read from `<prefix><NAME>`.
+    Env(String),
+}
+
+impl FromStr for KeyStore {
+    type Err = String;
+
+    fn from_str(store: &str) -> Result<Self, String> {
+        match store.strip_prefix("env:") {
+            Some(prefix) => Ok(Self::Env(prefix.to_owned())),
+            None if store.is_empty() => Err("expected a path or env:PREFIX".to_owned()),
+            None => Ok(Self::File(PathBuf::from(store))),
+        }
+    }
+}
+
+impl KeyStore {
+    /// Returns the entry of key `name`, or `None` when there is none.
+    fn entry(&self, name: &str) -> Result<Option<String>, String> {
+        match self {
+            Self::File(path) => {
+                let keys = std::fs::read_to_string(path)
+                    .map_err(|err| err.to_string())
+                    .and_then(|keys| {
+                        toml::from_str::<BTreeMap<String, String>>(&keys)
+                            .map_err(|err| err.to_string())
+                    })
+                    .map_err(|err| format!("failed to read key store {}: {err}", path.display()))?;
+                Ok(keys.get(name).cloned())
+            }
+            Self::Env(prefix) => Ok(std::env::var(format!("{prefix}{}", name.to_uppercase())).ok()),
+        }
+    }
+
+    /// Loads key `name`.
+    pub(super) fn load(&self, name: &str) -> Result<Key, String> {
+        let entry = self
+            .entry(name)?
+            .ok_or_else(|| format!("no key `{name}` in the key store"))?;
+        entry
+            .parse()
+            .map_err(|err| format!("invalid key `{name}`: {err}"))
+    }
+}
+
+/// The HMAC digests keys can be used with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum Digest {
+    Sha256,
+    Sha512,
+}
+
+#[derive(Clone, PartialEq)]
+pub(super) enum Key {
+    Hmac(Digest, Vec<u8>),
+    Ed25519(VerifyingKey),
+}
+
+impl Key {
+    /// The HMAC of `data`, or `None` for keys that can only verify.
+    pub(super) fn sign(&self, data: &[u8]) -> Option<Vec<u8>> {
+        let Self::Hmac(digest, key) = self else {
+            return None;
+        };
+        // HMAC takes keys of any length
+        Some(match digest {
+            Digest::Sha256 => Hmac::<Sha256>::new_from_slice(key)
+                .expect("any key length")
+                .chain_update(data)
+                .finalize()
+                .into_bytes()
+                .to_vec(),
+            Digest::Sha512 => Hmac::<Sha512>::new_from_slice(key)
+                .expect("any key length")
+                .chain_update(data)
+                .finalize()
+                .into_bytes()
+                .to_vec(),
+        })
+    }
+
+    /// Whether `signature` is a valid signature of `data`, compared in
+    /// constant time for HMAC keys.
+    pub(super) fn verify(&self, data: &[u8], signature: &[u8]) -> bool {
+        match self {
+            Self::Hmac(..) => self
+                .sign(data)
+                .is_some_and(|expected| bool::from(expected.ct_eq(signature))),
+            Self::Ed25519(key) => Signature::from_slice(signature)
+                .is_ok_and(|signature| key.verify_strict(data, &signature).is_ok()),
+        }
+    }
+}
+
+// keeps key material out of debug output
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Hmac(digest, _) => f.debug_tuple("Hmac").field(digest).finish_non_exhaustive(),
+            Self::Ed25519(_) => f.debug_tuple("Ed25519").finish_non_exhaustive(),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(entry: &str) -> Result<Self, String> {
+        let (algorithm, key) = entry
+            .split_once(':')
+            .ok_or("expected <algorithm>:<base64 key>")?;
+        let key = STANDARD
+            .decode(key.trim())
+            .map_err(|err| format!("invalid base64: {err}"))?;
+        match algorithm {
+            "hmac-sha256" => Ok(Self::Hmac(Digest::Sha256, key)),
+            "hmac-sha512" => Ok(Self::Hmac(Digest::Sha512, key)),
+            "ed25519" => {
+                let key = key
+                    .try_into()
+                    .map_err(|_| "an ed25519 public key is 32 bytes".to_owned())?;
+                VerifyingKey::from_bytes(&key)
+                    .map(Self::Ed25519)
+                    .map_err(|err| err.to_string())
+            }
+            _ => Err(format!("unknown algorithm `{algorithm}`")),
+        }
+    }
+}
+
+/// Reported when the key a call names can't be loaded.
+#[derive(Debug)]
+struct InvalidKey {
+    error: String,
+    span: Span,
+}
+
+impl std::fmt::Display for InvalidKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.error)
+    }
+}
+
+impl std::error::Error for InvalidKey {}
+
+impl DiagnosticMessage for InvalidKey {
+    fn code(&self) -> usize {
+        0
+    }
+
+    fn labels(&self) -> Vec<Label> {
+        vec![Label::primary("in this call", self.span)]
+    }
+
+    fn notes(&self) -> Vec<Note> {
+        vec![Note::Hint(
+            "keys are loaded from the store given with --key-store".to_owned(),
+        )]
+    }
+}
+
+/// Loads the key named by the literal `key` argument of a call.
+pub(super) fn compile_key(
+    store: Option<&KeyStore>,
+    state: &state::TypeState,
+    ctx: &FunctionCompileContext,
+    arguments: &ArgumentList,
+) -> Result<Key, Box<dyn DiagnosticMessage>> {
+    let name = arguments
+        .required_literal("key", state)?
+        .try_bytes_utf8_lossy()
+        .expect("key is bytes")
+        .into_owned();
+    store
+        .ok_or_else(|| "no key store to load keys from".to_owned())
+        .and_then(|store| store.load(&name))
+        .map_err(|error| {
+            Box::new(InvalidKey {
+                error,
+                span: ctx.span(),
+            }) as Box<dyn DiagnosticMessage>
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_stores() {
+        assert_eq!(
+            "env:VRL_KEY_".parse(),
+            Ok(KeyStore::Env("VRL_KEY_".to_owned()))
+        );
+        assert_eq!(
+            "keys.toml".parse(),
+            Ok(KeyStore::File(PathBuf::from("keys.toml")))
+        );
+        assert!("".parse::<KeyStore>().is_err());
+    }
+
+    #[test]
+    fn loads_keys() {
+        let path = std::env::temp_dir().join(format!("vrl-test-{}-keys", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+            webhook = "hmac-sha256:c2VjcmV0"
+            bad = "hmac-md5:c2VjcmV0"
+            short = "ed25519:c2VjcmV0"
+            "#,
+        )
+        .unwrap();
+        let store = KeyStore::File(path.clone());
+
+        assert_eq!(
+            store.load("webhook"),
+            Ok(Key::Hmac(Digest::Sha256, b"secret".to_vec()))
+        );
+        assert_eq!(
+            store.load("bad").unwrap_err(),
+            "invalid key `bad`: unknown algorithm `hmac-md5`"
+        );
+        assert!(store.load("short").is_err());
+        assert_eq!(
+            store.load("missing").unwrap_err(),
+            "no key `missing` in the key store"
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn loads_keys_from_env() {
+        std::env::set_var("VRL_TEST_KEYS_WEBHOOK", "hmac-sha512:c2VjcmV0");
+        let store = KeyStore::Env("VRL_TEST_KEYS_".to_owned());
+
+        assert_eq!(
+            store.load("webhook"),
+            Ok(Key::Hmac(Digest::Sha512, b"secret".to_vec()))
+        );
+        assert!(store.load("other").is_err());
+    }
+}
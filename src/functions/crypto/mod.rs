@@ -0,0 +1,18 @@
+//! Functions signing and verifying data with keys from the `--key-store`,
+//! so programs name keys instead of embedding them.
+
+mod hmac_sign;
+mod keys;
+mod verify_signature;
+
+use crate::registry::Registry;
+
+pub use hmac_sign::HmacSign;
+pub use keys::KeyStore;
+pub use verify_signature::VerifySignature;
+
+pub(super) fn register(registry: Registry, store: Option<KeyStore>) -> Registry {
+    registry
+        .add_fn(HmacSign::new(store.clone()))
+        .add_fn(VerifySignature::new(store))
+}
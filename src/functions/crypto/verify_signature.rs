@@ -0,0 +1,149 @@
+use vrl::prelude::*;
+
+use super::keys::{compile_key, Key, KeyStore};
+
+/// Checks a raw HMAC or ed25519 signature of a value against a key from the
+/// key store.
+#[derive(Debug)]
+pub struct VerifySignature {
+    store: Option<KeyStore>,
+}
+
+impl VerifySignature {
+    pub fn new(store: Option<KeyStore>) -> Self {
+        Self { store }
+    }
+}
+
+impl Function for VerifySignature {
+    fn identifier(&self) -> &'static str {
+        "verify_signature"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[
+            Parameter {
+                keyword: "value",
+                kind: kind::BYTES,
+                required: true,
+            },
+            Parameter {
+                keyword: "signature",
+                kind: kind::BYTES,
+                required: true,
+            },
+            Parameter {
+                keyword: "key",
+                kind: kind::BYTES,
+                required: true,
+            },
+        ]
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[Example {
+            title: "webhook signature",
+            source: r#"verify_signature(.body, decode_base16!(.headers."x-signature"), "webhook")"#,
+            result: Ok("true"),
+        }]
+    }
+
+    fn compile(
+        &self,
+        state: &state::TypeState,
+        ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        Ok(VerifySignatureFn {
+            key: compile_key(self.store.as_ref(), state, ctx, &arguments)?,
+            value: arguments.required("value"),
+            signature: arguments.required("signature"),
+        }
+        .as_expr())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct VerifySignatureFn {
+    value: Box<dyn Expression>,
+    signature: Box<dyn Expression>,
+    key: Key,
+}
+
+impl FunctionExpression for VerifySignatureFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        let value = self.value.resolve(ctx)?;
+        let signature = self.signature.resolve(ctx)?;
+        Ok(self
+            .key
+            .verify(&value.try_bytes()?, &signature.try_bytes()?)
+            .into())
+    }
+
+    fn type_def(&self, _: &state::TypeState) -> TypeDef {
+        TypeDef::boolean().infallible()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::program::{compile_source, new_target};
+    use crate::state::RunState;
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use ed25519_dalek::{Signer, SigningKey};
+    use vrl::compiler::runtime::Runtime;
+    use vrl::compiler::TimeZone;
+    use vrl::value;
+
+    fn verify(key: &str, event: Value) -> Value {
+        let store = KeyStore::Env("VRL_TEST_VERIFY_".to_owned());
+        let mut functions = vrl::stdlib::all();
+        functions.push(Box::new(VerifySignature::new(Some(store))));
+        let source = format!(r#"verify_signature(string!(.value), string!(.signature), "{key}")"#);
+        let program = compile_source(&source, &functions, &RunState::default())
+            .unwrap()
+            .program;
+
+        let mut target = new_target(event);
+        Runtime::default()
+            .resolve(&mut target, &program, &TimeZone::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn verifies_hmac_signatures() {
+        std::env::set_var("VRL_TEST_VERIFY_WEBHOOK", "hmac-sha256:c2VjcmV0");
+        let signature = "hmac-sha256:c2VjcmV0"
+            .parse::<Key>()
+            .unwrap()
+            .sign(b"payload")
+            .unwrap();
+
+        let event =
+            value!({"value": "payload", "signature": (Value::Bytes(signature.clone().into()))});
+        assert_eq!(verify("webhook", event), value!(true));
+        let event =
+            value!({"value": "tampered", "signature": (Value::Bytes(signature.clone().into()))});
+        assert_eq!(verify("webhook", event), value!(false));
+        let event = value!({"value": "payload", "signature": (Value::Bytes(signature[1..].to_vec().into()))});
+        assert_eq!(verify("webhook", event), value!(false));
+    }
+
+    #[test]
+    fn verifies_ed25519_signatures() {
+        let signing = SigningKey::from_bytes(&[7; 32]);
+        let public = STANDARD.encode(signing.verifying_key().as_bytes());
+        std::env::set_var("VRL_TEST_VERIFY_PUBLIC", format!("ed25519:{public}"));
+        let signature = signing.sign(b"payload").to_bytes().to_vec();
+
+        let event =
+            value!({"value": "payload", "signature": (Value::Bytes(signature.clone().into()))});
+        assert_eq!(verify("public", event), value!(true));
+        let event = value!({"value": "tampered", "signature": (Value::Bytes(signature.into()))});
+        assert_eq!(verify("public", event), value!(false));
+        let event = value!({"value": "payload", "signature": "short"});
+        assert_eq!(verify("public", event), value!(false));
+    }
+}
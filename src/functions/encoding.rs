@@ -0,0 +1,367 @@
+//! Encodings the stdlib doesn't have: base58, base62, Z85 and Crockford's
+//! base32.
+
+use std::iter;
+use vrl::prelude::*;
+
+use crate::registry::Registry;
+
+/// The Bitcoin alphabet.
+const BASE58: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BASE62: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const Z85: &[u8] =
+    b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ.-:+=^!/*?&<>()[]{}@%$#";
+const CROCKFORD: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+pub(super) fn register(registry: Registry) -> Registry {
+    registry
+        .add_fn(EncodeBase58)
+        .add_fn(DecodeBase58)
+        .add_fn(EncodeBase62)
+        .add_fn(DecodeBase62)
+        .add_fn(EncodeZ85)
+        .add_fn(DecodeZ85)
+        .add_fn(EncodeCrockford32)
+        .add_fn(DecodeCrockford32)
+}
+
+fn digit(alphabet: &[u8], c: char) -> Option<u32> {
+    let c = u8::try_from(c).ok()?;
+    alphabet.iter().position(|&a| a == c).map(|d| d as u32)
+}
+
+fn invalid(name: &str, c: char, index: usize) -> String {
+    format!("invalid {name} character {c:?} at {index}")
+}
+
+/// Encodes `bytes` as a big-endian number in the base of `alphabet`, each
+/// leading zero byte becoming a leading zero digit.
+fn encode_radix(bytes: &[u8], alphabet: &[u8]) -> String {
+    let base = alphabet.len() as u32;
+    let zeros = bytes.iter().take_while(|&&byte| byte == 0).count();
+    // little endian, so carries push new digits
+    let mut digits: Vec<u32> = Vec::new();
+    for &byte in &bytes[zeros..] {
+        let mut carry = u32::from(byte);
+        for digit in &mut digits {
+            carry += *digit << 8;
+            *digit = carry % base;
+            carry /= base;
+        }
+        while carry > 0 {
+            digits.push(carry % base);
+            carry /= base;
+        }
+    }
+
+    iter::repeat_n(alphabet[0], zeros)
+        .chain(digits.iter().rev().map(|&digit| alphabet[digit as usize]))
+        .map(char::from)
+        .collect()
+}
+
+/// Reverses [`encode_radix`].
+fn decode_radix(text: &str, alphabet: &[u8], name: &str) -> Result<Vec<u8>, String> {
+    let base = alphabet.len() as u32;
+    let zero = char::from(alphabet[0]);
+    let zeros = text.chars().take_while(|&c| c == zero).count();
+    let mut bytes: Vec<u32> = Vec::new();
+    for (index, c) in text.char_indices().skip(zeros) {
+        let mut carry = digit(alphabet, c).ok_or_else(|| invalid(name, c, index))?;
+        for byte in &mut bytes {
+            carry += *byte * base;
+            *byte = carry & 0xff;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry & 0xff);
+            carry >>= 8;
+        }
+    }
+
+    Ok(iter::repeat_n(0, zeros)
+        .chain(bytes.iter().rev().map(|&byte| byte as u8))
+        .collect())
+}
+
+/// Encodes groups of 4 bytes as 5 characters, as ZeroMQ's Z85 does.
+fn encode_z85(bytes: &[u8]) -> Result<String, String> {
+    if !bytes.len().is_multiple_of(4) {
+        return Err(format!(
+            "Z85 encodes multiples of 4 bytes, got {}",
+            bytes.len()
+        ));
+    }
+    Ok(bytes
+        .chunks(4)
+        .flat_map(|chunk| {
+            let value = u32::from_be_bytes(chunk.try_into().expect("chunks of 4"));
+            (0..5)
+                .rev()
+                .map(move |place| Z85[(value / 85u32.pow(place) % 85) as usize] as char)
+        })
+        .collect())
+}
+
+fn decode_z85(text: &str) -> Result<Vec<u8>, String> {
+    let chars = text.chars().collect::<Vec<_>>();
+    if !chars.len().is_multiple_of(5) {
+        return Err(format!(
+            "Z85 decodes multiples of 5 characters, got {}",
+            chars.len()
+        ));
+    }
+    let mut bytes = Vec::with_capacity(chars.len() / 5 * 4);
+    for (group, chunk) in chars.chunks(5).enumerate() {
+        let mut value = 0u64;
+        for (offset, &c) in chunk.iter().enumerate() {
+            let digit = digit(Z85, c).ok_or_else(|| invalid("Z85", c, group * 5 + offset))?;
+            value = value * 85 + u64::from(digit);
+        }
+        let value =
+            u32::try_from(value).map_err(|_| format!("invalid Z85 group at {}", group * 5))?;
+        bytes.extend(value.to_be_bytes());
+    }
+    Ok(bytes)
+}
+
+/// Encodes 5 bits per character, without padding.
+fn encode_crockford32(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let (mut buffer, mut bits) = (0u16, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | u16::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            text.push(CROCKFORD[usize::from(buffer >> bits) & 0x1f] as char);
+        }
+    }
+    if bits > 0 {
+        text.push(CROCKFORD[usize::from(buffer << (5 - bits)) & 0x1f] as char);
+    }
+    text
+}
+
+/// Decodes case-insensitively, reading `I` and `L` as 1, `O` as 0 and
+/// skipping hyphens, as Crockford's spec asks for.
+fn decode_crockford32(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(text.len() * 5 / 8);
+    let (mut buffer, mut bits) = (0u16, 0);
+    for (index, c) in text.char_indices() {
+        let digit = match c.to_ascii_uppercase() {
+            '-' => continue,
+            'I' | 'L' => 1,
+            'O' => 0,
+            upper => digit(CROCKFORD, upper).ok_or_else(|| invalid("base32", c, index))?,
+        };
+        buffer = (buffer << 5) | digit as u16;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+fn utf8(text: &[u8]) -> Result<&str, ExpressionError> {
+    std::str::from_utf8(text).map_err(|err| format!("invalid UTF-8: {err}").into())
+}
+
+vrl_fn! {
+    /// Encodes bytes with the Bitcoin base58 alphabet.
+    pub struct EncodeBase58 => EncodeBase58Fn {
+        identifier: "encode_base58",
+        parameters: { value: kind::BYTES },
+        examples: [
+            Example {
+                title: "encode",
+                source: r#"encode_base58("hello world")"#,
+                result: Ok(r#""StV1DL6CwTryKyV""#),
+            },
+        ],
+        type_def: TypeDef::bytes().infallible(),
+        resolve: |value: Value| Ok(encode_radix(&value.try_bytes()?, BASE58).into()),
+    }
+}
+
+vrl_fn! {
+    /// Decodes base58 with the Bitcoin alphabet.
+    pub struct DecodeBase58 => DecodeBase58Fn {
+        identifier: "decode_base58",
+        parameters: { value: kind::BYTES },
+        examples: [
+            Example {
+                title: "decode",
+                source: r#"decode_base58!("StV1DL6CwTryKyV")"#,
+                result: Ok(r#""hello world""#),
+            },
+        ],
+        type_def: TypeDef::bytes().fallible(),
+        resolve: |value: Value| {
+            let bytes = decode_radix(utf8(&value.try_bytes()?)?, BASE58, "base58")?;
+            Ok(Value::Bytes(bytes.into()))
+        },
+    }
+}
+
+vrl_fn! {
+    /// Encodes bytes with the `0-9A-Za-z` base62 alphabet.
+    pub struct EncodeBase62 => EncodeBase62Fn {
+        identifier: "encode_base62",
+        parameters: { value: kind::BYTES },
+        examples: [
+            Example {
+                title: "encode",
+                source: r#"encode_base62("hello world")"#,
+                result: Ok(r#""AAwf93rvy4aWQVw""#),
+            },
+        ],
+        type_def: TypeDef::bytes().infallible(),
+        resolve: |value: Value| Ok(encode_radix(&value.try_bytes()?, BASE62).into()),
+    }
+}
+
+vrl_fn! {
+    /// Decodes base62 with the `0-9A-Za-z` alphabet.
+    pub struct DecodeBase62 => DecodeBase62Fn {
+        identifier: "decode_base62",
+        parameters: { value: kind::BYTES },
+        examples: [
+            Example {
+                title: "decode",
+                source: r#"decode_base62!("AAwf93rvy4aWQVw")"#,
+                result: Ok(r#""hello world""#),
+            },
+        ],
+        type_def: TypeDef::bytes().fallible(),
+        resolve: |value: Value| {
+            let bytes = decode_radix(utf8(&value.try_bytes()?)?, BASE62, "base62")?;
+            Ok(Value::Bytes(bytes.into()))
+        },
+    }
+}
+
+vrl_fn! {
+    /// Encodes bytes, a multiple of 4 of them, as Z85.
+    pub struct EncodeZ85 => EncodeZ85Fn {
+        identifier: "encode_z85",
+        parameters: { value: kind::BYTES },
+        examples: [
+            Example {
+                title: "encode",
+                source: r#"encode_z85!(decode_base16!("864fd26fb559f75b"))"#,
+                result: Ok(r#""HelloWorld""#),
+            },
+        ],
+        type_def: TypeDef::bytes().fallible(),
+        resolve: |value: Value| Ok(encode_z85(&value.try_bytes()?)?.into()),
+    }
+}
+
+vrl_fn! {
+    /// Decodes Z85.
+    pub struct DecodeZ85 => DecodeZ85Fn {
+        identifier: "decode_z85",
+        parameters: { value: kind::BYTES },
+        examples: [
+            Example {
+                title: "decode",
+                source: r#"encode_base16(decode_z85!("HelloWorld"))"#,
+                result: Ok(r#""864fd26fb559f75b""#),
+            },
+        ],
+        type_def: TypeDef::bytes().fallible(),
+        resolve: |value: Value| {
+            let bytes = decode_z85(utf8(&value.try_bytes()?)?)?;
+            Ok(Value::Bytes(bytes.into()))
+        },
+    }
+}
+
+vrl_fn! {
+    /// Encodes bytes as Crockford's base32, without padding.
+    pub struct EncodeCrockford32 => EncodeCrockford32Fn {
+        identifier: "encode_base32_crockford",
+        parameters: { value: kind::BYTES },
+        examples: [
+            Example {
+                title: "encode",
+                source: r#"encode_base32_crockford("foobar")"#,
+                result: Ok(r#""CSQPYRK1E8""#),
+            },
+        ],
+        type_def: TypeDef::bytes().infallible(),
+        resolve: |value: Value| Ok(encode_crockford32(&value.try_bytes()?).into()),
+    }
+}
+
+vrl_fn! {
+    /// Decodes Crockford's base32, accepting lowercase, hyphens and the
+    /// letters it reads as digits.
+    pub struct DecodeCrockford32 => DecodeCrockford32Fn {
+        identifier: "decode_base32_crockford",
+        parameters: { value: kind::BYTES },
+        examples: [
+            Example {
+                title: "decode",
+                source: r#"decode_base32_crockford!("csqp-yrk1-e8")"#,
+                result: Ok(r#""foobar""#),
+            },
+        ],
+        type_def: TypeDef::bytes().fallible(),
+        resolve: |value: Value| {
+            let bytes = decode_crockford32(utf8(&value.try_bytes()?)?)?;
+            Ok(Value::Bytes(bytes.into()))
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trips_radix_encodings() {
+        assert_eq!(encode_radix(b"hello world", BASE58), "StV1DL6CwTryKyV");
+        assert_eq!(encode_radix(b"\0\0hi", BASE58), "118wr");
+        assert_eq!(encode_radix(b"hello world", BASE62), "AAwf93rvy4aWQVw");
+        assert_eq!(encode_radix(b"\0hi", BASE62), "06x7");
+        assert_eq!(encode_radix(b"", BASE58), "");
+
+        assert_eq!(decode_radix("118wr", BASE58, "base58").unwrap(), b"\0\0hi");
+        assert_eq!(
+            decode_radix("AAwf93rvy4aWQVw", BASE62, "base62").unwrap(),
+            b"hello world"
+        );
+        assert_eq!(
+            decode_radix("0OI", BASE58, "base58").unwrap_err(),
+            "invalid base58 character '0' at 0"
+        );
+    }
+
+    #[test]
+    fn round_trips_z85() {
+        let bytes = [0x86, 0x4f, 0xd2, 0x6f, 0xb5, 0x59, 0xf7, 0x5b];
+        assert_eq!(encode_z85(&bytes).unwrap(), "HelloWorld");
+        assert_eq!(decode_z85("HelloWorld").unwrap(), bytes);
+        assert!(encode_z85(b"abc").is_err());
+        assert!(decode_z85("Hello!").is_err());
+        // 85^5 - 1 doesn't fit in 4 bytes
+        assert!(decode_z85("#####").is_err());
+    }
+
+    #[test]
+    fn round_trips_crockford32() {
+        assert_eq!(encode_crockford32(b"foobar"), "CSQPYRK1E8");
+        assert_eq!(encode_crockford32(b"f"), "CR");
+        assert_eq!(decode_crockford32("csqp-yrk1-e8").unwrap(), b"foobar");
+        assert_eq!(decode_crockford32("CR").unwrap(), b"f");
+        assert_eq!(
+            decode_crockford32("1").unwrap(),
+            decode_crockford32("L").unwrap()
+        );
+        assert!(decode_crockford32("U").is_err());
+    }
+}
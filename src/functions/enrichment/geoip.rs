@@ -0,0 +1,330 @@
+use maxminddb::{geoip2, MaxMindDbError, Reader};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use vrl::diagnostic::{DiagnosticMessage, Label};
+use vrl::prelude::*;
+
+use super::{resolve, EnrichmentTables};
+use crate::state::{FunctionState, RunState};
+
+type Database = Arc<Reader<Vec<u8>>>;
+
+/// The databases opened in a run, by path.
+#[derive(Default)]
+struct Databases(Mutex<HashMap<PathBuf, Database>>);
+
+impl Databases {
+    fn open(&self, path: &Path) -> Result<Database, MaxMindDbError> {
+        let mut databases = self.0.lock().expect("geoip lock poisoned");
+        if let Some(database) = databases.get(path) {
+            return Ok(database.clone());
+        }
+        let database = Arc::new(Reader::open_readfile(path)?);
+        databases.insert(path.to_owned(), database.clone());
+        Ok(database)
+    }
+}
+
+impl FunctionState for Databases {}
+
+/// Looks `ip` up in a City, Country or ASN database, returning the fields it
+/// has a value for, or null when the address isn't in the database.
+fn geoip(database: &Reader<Vec<u8>>, ip: Value) -> Resolved {
+    let ip = ip.try_bytes_utf8_lossy()?;
+    let ip: IpAddr = ip
+        .parse()
+        .map_err(|err| format!("invalid IP address {ip}: {err}"))?;
+    let result = database
+        .lookup(ip)
+        .map_err(|err| format!("lookup of {ip} failed: {err}"))?;
+    let decode_error = |err: MaxMindDbError| format!("invalid record for {ip}: {err}");
+    let (Some(city), Some(asn)) = (
+        result.decode::<geoip2::City>().map_err(decode_error)?,
+        result.decode::<geoip2::Asn>().map_err(decode_error)?,
+    ) else {
+        return Ok(Value::Null);
+    };
+
+    let region = city.subdivisions.first();
+    let fields = [
+        ("continent_code", city.continent.code.map(Value::from)),
+        ("country_code", city.country.iso_code.map(Value::from)),
+        ("country_name", city.country.names.english.map(Value::from)),
+        (
+            "region_code",
+            region.and_then(|r| r.iso_code).map(Value::from),
+        ),
+        (
+            "region_name",
+            region.and_then(|r| r.names.english).map(Value::from),
+        ),
+        ("city_name", city.city.names.english.map(Value::from)),
+        ("postal_code", city.postal.code.map(Value::from)),
+        (
+            "latitude",
+            city.location.latitude.map(Value::from_f64_or_zero),
+        ),
+        (
+            "longitude",
+            city.location.longitude.map(Value::from_f64_or_zero),
+        ),
+        ("timezone", city.location.time_zone.map(Value::from)),
+        ("asn", asn.autonomous_system_number.map(Value::from)),
+        (
+            "as_organization",
+            asn.autonomous_system_organization.map(Value::from),
+        ),
+    ];
+
+    Ok(Value::Object(
+        fields
+            .into_iter()
+            .filter_map(|(field, value)| Some((field.into(), value?)))
+            .collect(),
+    ))
+}
+
+/// Looks up the location and network of an IP address in a MaxMind
+/// database.
+#[derive(Debug)]
+pub struct Geoip {
+    tables: EnrichmentTables,
+}
+
+impl Geoip {
+    pub fn new(tables: EnrichmentTables) -> Self {
+        Self { tables }
+    }
+}
+
+impl Function for Geoip {
+    fn identifier(&self) -> &'static str {
+        "geoip"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[
+            Parameter {
+                keyword: "value",
+                kind: kind::BYTES,
+                required: true,
+            },
+            Parameter {
+                keyword: "database",
+                kind: kind::BYTES,
+                required: true,
+            },
+        ]
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[Example {
+            title: "country of an address",
+            source: r#"geoip!("89.160.20.128", "GeoLite2-City.mmdb").country_code"#,
+            result: Ok(r#""SE""#),
+        }]
+    }
+
+    fn compile(
+        &self,
+        state: &state::TypeState,
+        ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        let table = arguments
+            .required_literal("database", state)?
+            .try_bytes_utf8_lossy()
+            .expect("database is bytes")
+            .into_owned();
+        let path = resolve(&self.tables, &table);
+        let database = match ctx.get_external_context::<RunState>() {
+            Some(state) => {
+                state.record_input(&path);
+                state.get_or_init("geoip", Databases::default).open(&path)
+            }
+            None => Reader::open_readfile(&path).map(Arc::new),
+        }
+        .map_err(|err| {
+            Box::new(ExpressionError::Error {
+                message: format!("failed to open GeoIP database {}: {err}", path.display()),
+                labels: vec![Label::primary("in this call", ctx.span())],
+                notes: vec![],
+            }) as Box<dyn DiagnosticMessage>
+        })?;
+
+        Ok(GeoipFn {
+            value: arguments.required("value"),
+            database,
+        }
+        .as_expr())
+    }
+}
+
+#[derive(Clone)]
+struct GeoipFn {
+    value: Box<dyn Expression>,
+    database: Database,
+}
+
+impl std::fmt::Debug for GeoipFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GeoipFn")
+            .field("value", &self.value)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FunctionExpression for GeoipFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        geoip(&self.database, self.value.resolve(ctx)?)
+    }
+
+    fn type_def(&self, _: &state::TypeState) -> TypeDef {
+        TypeDef::object(Collection::any()).or_null().fallible()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+    use vrl::value;
+
+    /// Encodes MaxMind DB data fields; only the types the tests need.
+    enum Data {
+        Str(&'static str),
+        U16(u16),
+        U32(u32),
+        U64(u64),
+        Double(f64),
+        Array(Vec<Data>),
+        Map(Vec<(&'static str, Data)>),
+    }
+
+    impl Data {
+        fn encode(&self, out: &mut Vec<u8>) {
+            match self {
+                Data::Str(s) => {
+                    out.push(0x40 | s.len() as u8);
+                    out.extend(s.as_bytes());
+                }
+                Data::U16(n) => {
+                    out.push(0xa0 | 2);
+                    out.extend(n.to_be_bytes());
+                }
+                Data::U32(n) => {
+                    out.push(0xc0 | 4);
+                    out.extend(n.to_be_bytes());
+                }
+                Data::U64(n) => {
+                    // extended types follow the size with their type minus 7
+                    out.extend([8, 9 - 7]);
+                    out.extend(n.to_be_bytes());
+                }
+                Data::Double(n) => {
+                    out.push(0x60 | 8);
+                    out.extend(n.to_be_bytes());
+                }
+                Data::Array(items) => {
+                    out.extend([items.len() as u8, 11 - 7]);
+                    for item in items {
+                        item.encode(out);
+                    }
+                }
+                Data::Map(entries) => {
+                    out.push(0xe0 | entries.len() as u8);
+                    for (key, value) in entries {
+                        Data::Str(key).encode(out);
+                        value.encode(out);
+                    }
+                }
+            }
+        }
+    }
+
+    /// An IPv4 database mapping every address to `record`.
+    fn database(record: Data) -> Vec<u8> {
+        // a single node whose records both point at the first data record
+        let pointer = 1 + 16;
+        let mut buf = [[0, 0, pointer], [0, 0, pointer]].concat();
+        buf.extend([0; 16]);
+        record.encode(&mut buf);
+        buf.extend(b"\xab\xcd\xefMaxMind.com");
+        Data::Map(vec![
+            ("node_count", Data::U32(1)),
+            ("record_size", Data::U16(24)),
+            ("ip_version", Data::U16(4)),
+            ("database_type", Data::Str("Test-City")),
+            ("binary_format_major_version", Data::U16(2)),
+            ("binary_format_minor_version", Data::U16(0)),
+            ("build_epoch", Data::U64(0)),
+            ("languages", Data::Array(vec![Data::Str("en")])),
+            ("description", Data::Map(vec![])),
+        ])
+        .encode(&mut buf);
+        buf
+    }
+
+    fn names(name: &'static str) -> Data {
+        Data::Map(vec![("names", Data::Map(vec![("en", Data::Str(name))]))])
+    }
+
+    #[test]
+    fn looks_up_fields() {
+        let database = database(Data::Map(vec![
+            ("city", names("Stockholm")),
+            (
+                "country",
+                Data::Map(vec![
+                    ("iso_code", Data::Str("SE")),
+                    ("names", Data::Map(vec![("en", Data::Str("Sweden"))])),
+                ]),
+            ),
+            (
+                "location",
+                Data::Map(vec![("latitude", Data::Double(59.5))]),
+            ),
+            ("autonomous_system_number", Data::U32(29518)),
+        ]));
+        let database = Reader::from_source(database).unwrap();
+
+        assert_eq!(
+            geoip(&database, value!("89.160.20.128")).unwrap(),
+            value!({
+                "country_code": "SE",
+                "country_name": "Sweden",
+                "city_name": "Stockholm",
+                "latitude": 59.5,
+                "asn": 29518,
+            })
+        );
+        assert!(geoip(&database, value!("nope")).is_err());
+    }
+
+    #[test]
+    fn opens_each_database_once() {
+        let path = std::env::temp_dir().join(format!("vrl-test-{}-geoip", std::process::id()));
+        std::fs::write(&path, database(names("Stockholm"))).unwrap();
+        let tables = Arc::new(BTreeMap::from([("geo".to_owned(), path.clone())]));
+        let functions: Vec<Box<dyn Function>> = vec![Box::new(Geoip::new(tables))];
+        let state = RunState::default();
+        let compile = |source| crate::program::compile_source(source, &functions, &state);
+
+        assert!(compile(r#"geoip!("1.2.3.4", "geo")"#).is_some());
+        let by_path = format!(r#"geoip!("1.2.3.4", "{}")"#, path.display());
+        assert!(compile(&by_path).is_some());
+        assert_eq!(
+            state
+                .get_or_init("geoip", Databases::default)
+                .0
+                .lock()
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(compile(r#"geoip!("1.2.3.4", "/nonexistent.mmdb")"#).is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+}
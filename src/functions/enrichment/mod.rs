@@ -0,0 +1,32 @@
+//! Functions looking up reference data in local files, each opened once per
+//! run. Files are named by path, or by a name given with `--enrichment-table`.
+
+mod geoip;
+mod sqlite_lookup;
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::registry::Registry;
+
+pub use geoip::Geoip;
+pub use sqlite_lookup::SqliteLookup;
+
+/// Enrichment files by the name given on the command line.
+pub type EnrichmentTables = Arc<BTreeMap<String, PathBuf>>;
+
+pub(super) fn register(registry: Registry, tables: &[(String, PathBuf)]) -> Registry {
+    let tables: EnrichmentTables = Arc::new(tables.iter().cloned().collect());
+    registry
+        .add_fn(Geoip::new(tables.clone()))
+        .add_fn(SqliteLookup::new(tables))
+}
+
+/// The file a program refers to as `table`.
+fn resolve(tables: &EnrichmentTables, table: &str) -> PathBuf {
+    tables
+        .get(table)
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from(table))
+}
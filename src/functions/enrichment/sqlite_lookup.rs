@@ -0,0 +1,302 @@
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{Connection, OpenFlags};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use vrl::diagnostic::{DiagnosticMessage, Label};
+use vrl::prelude::*;
+
+use super::{resolve, EnrichmentTables};
+use crate::state::{FunctionState, RunState};
+
+type Database = Arc<Mutex<Connection>>;
+
+/// The databases opened in a run, by path.
+#[derive(Default)]
+struct Databases(Mutex<HashMap<PathBuf, Database>>);
+
+impl Databases {
+    fn open(&self, path: &Path) -> rusqlite::Result<Database> {
+        let mut databases = self.0.lock().expect("sqlite lock poisoned");
+        if let Some(database) = databases.get(path) {
+            return Ok(database.clone());
+        }
+        let database = Arc::new(Mutex::new(open(path)?));
+        databases.insert(path.to_owned(), database.clone());
+        Ok(database)
+    }
+}
+
+impl FunctionState for Databases {}
+
+fn open(path: &Path) -> rusqlite::Result<Connection> {
+    Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+}
+
+/// Checks that `query` is a single statement that only reads.
+fn check_query(database: &Connection, query: &str) -> Result<(), String> {
+    let statement = database.prepare(query).map_err(|err| err.to_string())?;
+    match statement.readonly() {
+        true => Ok(()),
+        false => Err("only queries that read are allowed".to_owned()),
+    }
+}
+
+fn to_sql(value: Value) -> Result<SqlValue, ExpressionError> {
+    Ok(match value {
+        Value::Null => SqlValue::Null,
+        Value::Boolean(value) => SqlValue::Integer(value.into()),
+        Value::Integer(value) => SqlValue::Integer(value),
+        Value::Float(value) => SqlValue::Real(value.into_inner()),
+        Value::Bytes(value) => SqlValue::Text(String::from_utf8_lossy(&value).into_owned()),
+        value => {
+            return Err(ValueError::Expected {
+                got: value.kind(),
+                expected: Kind::null()
+                    | Kind::boolean()
+                    | Kind::integer()
+                    | Kind::float()
+                    | Kind::bytes(),
+            }
+            .into())
+        }
+    })
+}
+
+fn from_sql(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(value) => value.into(),
+        ValueRef::Real(value) => Value::from_f64_or_zero(value),
+        ValueRef::Text(value) | ValueRef::Blob(value) => Value::Bytes(value.to_vec().into()),
+    }
+}
+
+/// Runs `query` with `params` bound to its `?` placeholders, returning the
+/// first row as a map from column names to values, or null without rows.
+fn sqlite_lookup(database: &Connection, query: &str, params: Value) -> Resolved {
+    let params = params
+        .try_array()?
+        .into_iter()
+        .map(to_sql)
+        .collect::<Result<Vec<_>, _>>()?;
+    let error = |err: rusqlite::Error| format!("query failed: {err}");
+
+    let mut statement = database.prepare_cached(query).map_err(error)?;
+    let columns = statement
+        .column_names()
+        .into_iter()
+        .map(KeyString::from)
+        .collect::<Vec<_>>();
+    let mut rows = statement
+        .query(rusqlite::params_from_iter(params))
+        .map_err(error)?;
+    let Some(row) = rows.next().map_err(error)? else {
+        return Ok(Value::Null);
+    };
+
+    let row = columns
+        .into_iter()
+        .enumerate()
+        .map(|(index, column)| Ok((column, from_sql(row.get_ref(index)?))))
+        .collect::<rusqlite::Result<_>>()
+        .map_err(error)?;
+    Ok(Value::Object(row))
+}
+
+/// Looks up reference data in a read-only SQLite database.
+#[derive(Debug)]
+pub struct SqliteLookup {
+    tables: EnrichmentTables,
+}
+
+impl SqliteLookup {
+    pub fn new(tables: EnrichmentTables) -> Self {
+        Self { tables }
+    }
+}
+
+impl Function for SqliteLookup {
+    fn identifier(&self) -> &'static str {
+        "sqlite_lookup"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[
+            Parameter {
+                keyword: "database",
+                kind: kind::BYTES,
+                required: true,
+            },
+            Parameter {
+                keyword: "query",
+                kind: kind::BYTES,
+                required: true,
+            },
+            Parameter {
+                keyword: "params",
+                kind: kind::ARRAY,
+                required: false,
+            },
+        ]
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[Example {
+            title: "service owner",
+            source: r#"sqlite_lookup!("services.db", "SELECT owner FROM services WHERE name = ?", ["api"])"#,
+            result: Ok(r#"{"owner": "platform"}"#),
+        }]
+    }
+
+    fn compile(
+        &self,
+        state: &state::TypeState,
+        ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        let literal = |keyword| -> Result<String, Box<dyn DiagnosticMessage>> {
+            Ok(arguments
+                .required_literal(keyword, state)?
+                .try_bytes_utf8_lossy()
+                .expect("argument is bytes")
+                .into_owned())
+        };
+        let path = resolve(&self.tables, &literal("database")?);
+        let query = literal("query")?;
+        let invalid = |message: String| {
+            Box::new(ExpressionError::Error {
+                message,
+                labels: vec![Label::primary("in this call", ctx.span())],
+                notes: vec![],
+            }) as Box<dyn DiagnosticMessage>
+        };
+
+        let database = match ctx.get_external_context::<RunState>() {
+            Some(state) => {
+                state.record_input(&path);
+                state
+                    .get_or_init("sqlite_lookup", Databases::default)
+                    .open(&path)
+            }
+            None => open(&path).map(|database| Arc::new(Mutex::new(database))),
+        }
+        .map_err(|err| {
+            invalid(format!(
+                "failed to open SQLite database {}: {err}",
+                path.display()
+            ))
+        })?;
+        check_query(&database.lock().expect("sqlite lock poisoned"), &query)
+            .map_err(|err| invalid(format!("invalid query: {err}")))?;
+
+        Ok(SqliteLookupFn {
+            query,
+            params: arguments.optional("params").unwrap_or_else(|| expr!([])),
+            database,
+        }
+        .as_expr())
+    }
+}
+
+#[derive(Clone)]
+struct SqliteLookupFn {
+    query: String,
+    params: Box<dyn Expression>,
+    database: Database,
+}
+
+impl std::fmt::Debug for SqliteLookupFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SqliteLookupFn")
+            .field("query", &self.query)
+            .field("params", &self.params)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FunctionExpression for SqliteLookupFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        let params = self.params.resolve(ctx)?;
+        let database = self.database.lock().expect("sqlite lock poisoned");
+        sqlite_lookup(&database, &self.query, params)
+    }
+
+    fn type_def(&self, _: &state::TypeState) -> TypeDef {
+        TypeDef::object(Collection::any()).or_null().fallible()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::program::{compile_source, new_target};
+    use std::collections::BTreeMap;
+    use vrl::compiler::runtime::Runtime;
+    use vrl::compiler::TimeZone;
+    use vrl::value;
+
+    fn database(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("vrl-test-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE services (name TEXT, owner TEXT, tier INTEGER);
+                 INSERT INTO services VALUES ('api', 'platform', 1), ('web', NULL, 2);",
+            )
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn returns_first_row() {
+        let path = database("sqlite-rows");
+        let database = open(&path).unwrap();
+        let query = "SELECT name, owner, tier FROM services WHERE tier >= ? ORDER BY tier";
+
+        assert_eq!(
+            sqlite_lookup(&database, query, value!([1])).unwrap(),
+            value!({"name": "api", "owner": "platform", "tier": 1})
+        );
+        assert_eq!(
+            sqlite_lookup(&database, query, value!([2])).unwrap(),
+            value!({"name": "web", "owner": null, "tier": 2})
+        );
+        assert_eq!(
+            sqlite_lookup(&database, query, value!([3])).unwrap(),
+            value!(null)
+        );
+        assert!(sqlite_lookup(&database, query, value!([{"a": 1}])).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn compiles_read_only_queries() {
+        let path = database("sqlite-compile");
+        let tables = Arc::new(BTreeMap::from([("ref".to_owned(), path.clone())]));
+        let functions: Vec<Box<dyn Function>> = vec![Box::new(SqliteLookup::new(tables))];
+        let state = RunState::default();
+        let compile = |source| compile_source(source, &functions, &state);
+
+        let program = compile(
+            r#"sqlite_lookup!("ref", "SELECT owner FROM services WHERE name = ?", ["api"])"#,
+        )
+        .unwrap()
+        .program;
+        let mut target = new_target(value!({}));
+        assert_eq!(
+            Runtime::default()
+                .resolve(&mut target, &program, &TimeZone::default())
+                .unwrap(),
+            value!({"owner": "platform"})
+        );
+
+        assert!(compile(r#"sqlite_lookup!("ref", "DELETE FROM services")"#).is_none());
+        assert!(compile(r#"sqlite_lookup!("ref", "SELECT nope FROM services")"#).is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+}
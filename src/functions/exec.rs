@@ -0,0 +1,201 @@
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use vrl::prelude::*;
+
+/// How often a running command is checked for having exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Runs `command` with `value` on its stdin (strings as-is, anything else as
+/// JSON) and returns its stdout.
+///
+/// The command is killed once it runs longer than `timeout_ms` or writes more
+/// than `max_output_bytes`; either, like a non-zero exit status, is an error.
+/// It runs in a process group of its own, so that what it started is killed
+/// with it, and is killed once it exits: a process left behind holding its
+/// stdout would otherwise keep reading it from ever finishing.
+fn exec(value: Value, command: Value, timeout_ms: Value, max_output_bytes: Value) -> Resolved {
+    let mut argv = match command {
+        Value::Bytes(command) => vec![String::from_utf8_lossy(&command).into_owned()],
+        Value::Array(argv) => argv
+            .into_iter()
+            .map(|arg| Ok(arg.try_bytes_utf8_lossy()?.into_owned()))
+            .collect::<Result<Vec<_>, ExpressionError>>()?,
+        value => {
+            return Err(ValueError::Expected {
+                got: value.kind(),
+                expected: Kind::bytes() | Kind::array(Collection::any()),
+            }
+            .into())
+        }
+    }
+    .into_iter();
+    let program = argv.next().ok_or("command must not be empty")?;
+    let timeout = Duration::from_millis(timeout_ms.try_integer()?.max(0) as u64);
+    let max_output = max_output_bytes.try_integer()?.max(0) as u64;
+    let input = match value {
+        Value::Bytes(bytes) => bytes.to_vec(),
+        value => serde_json::to_vec(&value).map_err(|err| err.to_string())?,
+    };
+
+    let mut command = Command::new(&program);
+    command
+        .args(argv)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let mut child = command
+        .spawn()
+        .map_err(|err| format!("failed to run {program}: {err}"))?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = thread::spawn(move || stdin.write_all(&input));
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let overflowed = Arc::new(AtomicBool::new(false));
+    let reader = thread::spawn({
+        let overflowed = overflowed.clone();
+        move || {
+            // one byte past the limit tells an overlong output from one that fits
+            let mut output = Vec::new();
+            (&mut stdout)
+                .take(max_output + 1)
+                .read_to_end(&mut output)?;
+            overflowed.store(output.len() as u64 > max_output, Ordering::Relaxed);
+            Ok::<_, std::io::Error>(output)
+        }
+    });
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let errors = thread::spawn(move || {
+        let mut errors = String::new();
+        let _ = (&mut stderr).take(4096).read_to_string(&mut errors);
+        errors
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|err| err.to_string())? {
+            kill(&mut child);
+            break Some(status);
+        }
+        if overflowed.load(Ordering::Relaxed) || Instant::now() >= deadline {
+            kill(&mut child);
+            let _ = child.wait();
+            break None;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let _ = writer.join();
+    let output = reader
+        .join()
+        .expect("reader thread panicked")
+        .map_err(|err| format!("failed to read output of {program}: {err}"))?;
+    let errors = errors.join().unwrap_or_default();
+
+    match status {
+        _ if overflowed.load(Ordering::Relaxed) => {
+            Err(format!("{program} wrote more than {max_output} bytes").into())
+        }
+        None => Err(format!("{program} timed out after {timeout:?}").into()),
+        Some(status) if !status.success() => {
+            Err(format!("{program} failed with {status}: {}", errors.trim()).into())
+        }
+        Some(_) => Ok(Value::Bytes(output.into())),
+    }
+}
+
+/// Kills the process group of `child`, or just `child` where there are none.
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    // SAFETY: kill has no memory safety requirements; a negative pid names
+    // the process group the child leads
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    #[cfg(not(unix))]
+    let _ = child.kill();
+}
+
+vrl_fn! {
+    /// Pipes a value through an external command.
+    pub struct Exec => ExecFn {
+        identifier: "exec",
+        parameters: {
+            value: kind::ANY,
+            command: kind::BYTES | kind::ARRAY,
+            timeout_ms: kind::INTEGER => 5_000,
+            max_output_bytes: kind::INTEGER => 1_048_576,
+        },
+        examples: [
+            Example {
+                title: "uppercase with tr",
+                source: r#"exec!("hello", ["tr", "a-z", "A-Z"])"#,
+                result: Ok(r#""HELLO""#),
+            },
+        ],
+        type_def: TypeDef::bytes().fallible(),
+        resolve: exec,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vrl::value;
+
+    test_function![
+        exec => Exec;
+
+        pipes_string {
+            args: func_args![value: "hello", command: value!(["tr", "a-z", "A-Z"])],
+            want: Ok(value!("HELLO")),
+            tdef: TypeDef::bytes().fallible(),
+        }
+
+        pipes_json {
+            args: func_args![value: value!({"a": 1}), command: "cat"],
+            want: Ok(value!(r#"{"a":1}"#)),
+            tdef: TypeDef::bytes().fallible(),
+        }
+
+        non_zero_exit {
+            args: func_args![value: "", command: value!(["sh", "-c", "echo oops >&2; exit 3"])],
+            want: Err("sh failed with exit status: 3: oops"),
+            tdef: TypeDef::bytes().fallible(),
+        }
+
+        timeout {
+            args: func_args![value: "", command: value!(["sleep", "5"]), timeout_ms: 50],
+            want: Err("sleep timed out after 50ms"),
+            tdef: TypeDef::bytes().fallible(),
+        }
+
+        background_children {
+            args: func_args![value: "", command: value!(["sh", "-c", "sleep 5 & echo done"])],
+            want: Ok(value!("done\n")),
+            tdef: TypeDef::bytes().fallible(),
+        }
+
+        output_limit {
+            args: func_args![value: "", command: value!(["yes"]), max_output_bytes: 10],
+            want: Err("yes wrote more than 10 bytes"),
+            tdef: TypeDef::bytes().fallible(),
+        }
+    ];
+
+    #[test]
+    fn timeout_kills_children() {
+        let start = Instant::now();
+        let command = value!(["sh", "-c", "sleep 5; echo"]);
+        let err = exec("".into(), command, 50.into(), 1_024.into()).unwrap_err();
+
+        assert_eq!(err.to_string(), "sh timed out after 50ms");
+        // not waiting for sleep, which holds on to stdout
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,366 @@
+//! Time-ordered ID generation: UUIDv7, ULID and snowflake IDs.
+//!
+//! IDs generated within a run are strictly increasing, even when the clock
+//! stands still or goes back: the generator never goes back in time and
+//! counts up within a millisecond. With a fixed clock the randomness is
+//! seeded from it, so a run generates the same IDs every time.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use vrl::prelude::*;
+
+use crate::state::{FunctionState, RunState};
+
+/// Where the ID functions get the current time from.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Milliseconds since the Unix epoch.
+    fn now_millis(&self) -> u64;
+
+    /// Seeds the randomness of the IDs, for clocks that make them
+    /// reproducible.
+    fn seed(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// The system clock.
+#[derive(Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis() as u64)
+    }
+}
+
+/// A clock standing still at the given time.
+#[derive(Debug)]
+pub struct FixedClock(pub u64);
+
+impl Clock for FixedClock {
+    fn now_millis(&self) -> u64 {
+        self.0
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.0)
+    }
+}
+
+/// The Twitter snowflake epoch, 2010-11-04T01:42:54.657Z.
+const SNOWFLAKE_EPOCH: u64 = 1_288_834_974_657;
+
+/// The last ID a function generated, shared by its calls in a run.
+struct Generator {
+    clock: Arc<dyn Clock>,
+    state: Mutex<GeneratorState>,
+}
+
+struct GeneratorState {
+    rng: StdRng,
+    millis: u64,
+    /// What is counted up within a millisecond: the 12 bit counter of a
+    /// UUID, the 80 random bits of a ULID or the 12 bit snowflake sequence.
+    counter: u128,
+}
+
+impl FunctionState for Generator {}
+
+impl GeneratorState {
+    /// Returns the millisecond and counter of the next ID. A new millisecond
+    /// starts the counter at `start`; within one it is incremented, moving on
+    /// to the next millisecond once it exceeds `max`.
+    fn next(&mut self, now: u64, start: impl Fn(&mut StdRng) -> u128, max: u128) -> (u64, u128) {
+        if now > self.millis {
+            self.millis = now;
+            self.counter = start(&mut self.rng);
+        } else if self.counter < max {
+            self.counter += 1;
+        } else {
+            self.millis += 1;
+            self.counter = start(&mut self.rng);
+        }
+        (self.millis, self.counter)
+    }
+}
+
+impl Generator {
+    fn new(clock: Arc<dyn Clock>) -> Self {
+        let rng = match clock.seed() {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self {
+            clock,
+            state: Mutex::new(GeneratorState {
+                rng,
+                millis: 0,
+                counter: 0,
+            }),
+        }
+    }
+
+    fn lock(&self) -> (u64, MutexGuard<'_, GeneratorState>) {
+        let state = self.state.lock().expect("id generator lock poisoned");
+        (self.clock.now_millis(), state)
+    }
+
+    fn uuid_v7(&self) -> String {
+        let (now, mut state) = self.lock();
+        // the top bit starts out clear, leaving room to count up
+        let (millis, counter) = state.next(now, |rng| rng.gen_range(0..0x800), 0xfff);
+        let mut bytes = [0; 10];
+        bytes[..2].copy_from_slice(&(counter as u16).to_be_bytes());
+        state.rng.fill(&mut bytes[2..]);
+        uuid::Builder::from_unix_timestamp_millis(millis, &bytes)
+            .into_uuid()
+            .to_string()
+    }
+
+    /// A UUIDv7 of the given time, as the stdlib function generates it: the
+    /// IDs of a time given explicitly aren't ordered within its millisecond.
+    fn uuid_v7_at(&self, millis: u64) -> String {
+        let mut bytes = [0; 10];
+        self.lock().1.rng.fill(&mut bytes);
+        uuid::Builder::from_unix_timestamp_millis(millis, &bytes)
+            .into_uuid()
+            .to_string()
+    }
+
+    fn ulid(&self) -> String {
+        const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+        let (now, mut state) = self.lock();
+        // the top bit starts out clear, so counting up can't overflow
+        let (millis, random) = state.next(now, |rng| rng.gen::<u128>() >> 49, (1 << 80) - 1);
+        let id = (u128::from(millis) << 80) | random;
+        (0..26)
+            .rev()
+            .map(|digit| CROCKFORD[(id >> (digit * 5)) as usize & 0x1f] as char)
+            .collect()
+    }
+
+    fn snowflake(&self, node: i64) -> i64 {
+        let (now, mut state) = self.lock();
+        let (millis, sequence) = state.next(now, |_| 0, 0xfff);
+        let since_epoch = millis.saturating_sub(SNOWFLAKE_EPOCH) as i64;
+        (since_epoch << 22) | ((node & 0x3ff) << 12) | sequence as i64
+    }
+}
+
+/// Which kind of ID an [`IdFunction`] generates.
+#[derive(Clone, Copy, Debug)]
+pub enum IdKind {
+    UuidV7,
+    Ulid,
+    Snowflake,
+}
+
+/// Generates time-ordered IDs of one kind; every call of the function in a
+/// run shares one generator.
+#[derive(Debug)]
+pub struct IdFunction {
+    kind: IdKind,
+    clock: Arc<dyn Clock>,
+}
+
+impl IdFunction {
+    pub fn new(kind: IdKind, clock: Arc<dyn Clock>) -> Self {
+        Self { kind, clock }
+    }
+}
+
+impl Function for IdFunction {
+    fn identifier(&self) -> &'static str {
+        match self.kind {
+            IdKind::UuidV7 => "uuid_v7",
+            IdKind::Ulid => "ulid",
+            IdKind::Snowflake => "snowflake",
+        }
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        match self.kind {
+            IdKind::UuidV7 => &[Parameter {
+                keyword: "timestamp",
+                kind: kind::TIMESTAMP,
+                required: false,
+            }],
+            IdKind::Ulid => &[],
+            IdKind::Snowflake => &[Parameter {
+                keyword: "node",
+                kind: kind::INTEGER,
+                required: false,
+            }],
+        }
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        match self.kind {
+            IdKind::UuidV7 => &[
+                Example {
+                    title: "new ID",
+                    source: "uuid_v7()",
+                    result: Ok(r#""01928853-1800-7a2b-9c3d-1e2f3a4b5c6d""#),
+                },
+                Example {
+                    title: "ID of a given time",
+                    source: "uuid_v7(t'2024-10-14T00:00:00Z')",
+                    result: Ok(r#""01928853-1800-7e4f-8a1b-2c3d4e5f6a7b""#),
+                },
+            ],
+            IdKind::Ulid => &[Example {
+                title: "new ID",
+                source: "ulid()",
+                result: Ok(r#""01JA456600F8SXAGTM1HH1RQ8E""#),
+            }],
+            IdKind::Snowflake => &[Example {
+                title: "new ID",
+                source: "snowflake(1)",
+                result: Ok("1845615501112250368"),
+            }],
+        }
+    }
+
+    fn compile(
+        &self,
+        _state: &state::TypeState,
+        ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        let new = || Generator::new(self.clock.clone());
+        let generator = match ctx.get_external_context::<RunState>() {
+            Some(state) => state.get_or_init(self.identifier(), new),
+            None => Arc::new(new()),
+        };
+
+        Ok(IdFn {
+            kind: self.kind,
+            node: arguments.optional("node").unwrap_or_else(|| expr!(0)),
+            timestamp: arguments.optional("timestamp"),
+            generator,
+        }
+        .as_expr())
+    }
+}
+
+#[derive(Clone)]
+struct IdFn {
+    kind: IdKind,
+    node: Box<dyn Expression>,
+    /// The time of a UUIDv7 instead of the clock's, as with the stdlib
+    /// function.
+    timestamp: Option<Box<dyn Expression>>,
+    generator: Arc<Generator>,
+}
+
+impl fmt::Debug for IdFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdFn")
+            .field("kind", &self.kind)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FunctionExpression for IdFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        Ok(match self.kind {
+            IdKind::UuidV7 => match &self.timestamp {
+                Some(timestamp) => {
+                    let timestamp = timestamp.resolve(ctx)?.try_timestamp()?;
+                    let millis = timestamp.timestamp_millis().max(0) as u64;
+                    self.generator.uuid_v7_at(millis).into()
+                }
+                None => self.generator.uuid_v7().into(),
+            },
+            IdKind::Ulid => self.generator.ulid().into(),
+            IdKind::Snowflake => {
+                let node = self.node.resolve(ctx)?.try_integer()?;
+                self.generator.snowflake(node).into()
+            }
+        })
+    }
+
+    fn type_def(&self, _: &state::TypeState) -> TypeDef {
+        match self.kind {
+            IdKind::UuidV7 | IdKind::Ulid => TypeDef::bytes().infallible(),
+            IdKind::Snowflake => TypeDef::integer().infallible(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// 2024-10-14T00:00:00Z
+    const MILLIS: u64 = 1_728_864_000_000;
+
+    fn generator() -> Generator {
+        Generator::new(Arc::new(FixedClock(MILLIS)))
+    }
+
+    #[test]
+    fn uuids_increase() {
+        let generator = generator();
+        let uuids = (0..3).map(|_| generator.uuid_v7()).collect::<Vec<_>>();
+
+        assert!(uuids.windows(2).all(|pair| pair[0] < pair[1]));
+        let uuid = uuid::Uuid::parse_str(&uuids[0]).unwrap();
+        assert_eq!(uuid.get_version_num(), 7);
+        let (secs, _) = uuid.get_timestamp().unwrap().to_unix();
+        assert_eq!(secs, MILLIS / 1000);
+    }
+
+    #[test]
+    fn uuids_of_a_given_time() {
+        let source = "uuid_v7(t'2024-10-14T00:00:00Z')";
+        let functions = crate::registry::functions(&Default::default()).unwrap();
+        let program = vrl::compiler::compile(source, &functions).unwrap().program;
+        let mut target = crate::program::new_target(vrl::value!({}));
+        let uuid = vrl::compiler::runtime::Runtime::default()
+            .resolve(&mut target, &program, &TimeZone::default())
+            .unwrap();
+
+        let uuid = uuid::Uuid::parse_str(&uuid.try_bytes_utf8_lossy().unwrap()).unwrap();
+        let (secs, _) = uuid.get_timestamp().unwrap().to_unix();
+        assert_eq!(secs, MILLIS / 1000);
+        // the stdlib signature still compiles
+        assert!(vrl::compiler::compile("uuid_v7(now())", &functions).is_ok());
+    }
+
+    #[test]
+    fn ulids_increase() {
+        let generator = generator();
+        let ulids = (0..3).map(|_| generator.ulid()).collect::<Vec<_>>();
+
+        assert!(ulids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(ulids[0].len(), 26);
+        // the first 10 characters encode the time
+        assert_eq!(&ulids[0][..10], "01JA456600");
+    }
+
+    #[test]
+    fn snowflakes_count_up() {
+        let generator = generator();
+        let first = generator.snowflake(1);
+        assert_eq!(first >> 22, (MILLIS - SNOWFLAKE_EPOCH) as i64);
+        assert_eq!((first >> 12) & 0x3ff, 1);
+        assert_eq!(generator.snowflake(1), first + 1);
+    }
+
+    #[test]
+    fn fixed_clock_is_reproducible() {
+        assert_eq!(generator().uuid_v7(), generator().uuid_v7());
+        assert_eq!(generator().ulid(), generator().ulid());
+        assert_ne!(
+            Generator::new(Arc::new(SystemClock)).ulid(),
+            Generator::new(Arc::new(SystemClock)).ulid()
+        );
+    }
+}
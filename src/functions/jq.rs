@@ -0,0 +1,184 @@
+//! Runs jq filters against values, for pipelines migrating from jq.
+
+use jaq_core::load::{Arena, File, Loader};
+use jaq_core::{Compiler, Ctx, Filter, Native, RcIter};
+use jaq_json::Val;
+use vrl::diagnostic::{DiagnosticMessage, Label};
+use vrl::prelude::*;
+
+type JqFilter = Filter<Native<Val>>;
+
+/// Parses and compiles `code` against the jq standard library.
+fn compile(code: &str) -> Result<JqFilter, String> {
+    let loader = Loader::new(jaq_std::defs().chain(jaq_json::defs()));
+    let arena = Arena::default();
+    let modules = loader
+        .load(&arena, File { code, path: () })
+        .map_err(|errors| {
+            let mut expected = errors.into_iter().flat_map(|(_, error)| match error {
+                jaq_core::load::Error::Io(errors) => errors
+                    .into_iter()
+                    .map(|(path, error)| format!("{error} loading {path}"))
+                    .collect(),
+                jaq_core::load::Error::Lex(errors) => errors
+                    .into_iter()
+                    .map(|(expect, at)| format!("expected {} at `{at}`", expect.as_str()))
+                    .collect(),
+                jaq_core::load::Error::Parse(errors) => errors
+                    .into_iter()
+                    .map(|(expect, at)| format!("expected {} at `{at}`", expect.as_str()))
+                    .collect::<Vec<_>>(),
+            });
+            expected
+                .next()
+                .unwrap_or_else(|| "invalid filter".to_owned())
+        })?;
+
+    Compiler::default()
+        .with_funs(jaq_std::funs().chain(jaq_json::funs()))
+        .compile(modules)
+        .map_err(|errors| {
+            let undefined = errors.into_iter().flat_map(|(_, errors)| errors).next();
+            match undefined {
+                Some((name, undefined)) => format!("undefined {} `{name}`", undefined.as_str()),
+                None => "invalid filter".to_owned(),
+            }
+        })
+}
+
+/// Runs `filter` against `value`, returning its outputs as an array.
+fn jq(filter: &JqFilter, value: Value) -> Resolved {
+    let json: serde_json::Value = value
+        .try_into()
+        .map_err(|err| format!("value can't be passed to jq: {err}"))?;
+    let inputs = RcIter::new(core::iter::empty());
+    let outputs = filter
+        .run((Ctx::new([], &inputs), Val::from(json)))
+        .map(|output| {
+            output
+                .map(|output| Value::from(serde_json::Value::from(output)))
+                .map_err(|err| format!("jq filter failed: {err}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(outputs.into())
+}
+
+/// Evaluates a jq filter.
+#[derive(Clone, Copy, Debug)]
+pub struct Jq;
+
+impl Function for Jq {
+    fn identifier(&self) -> &'static str {
+        "jq"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[
+            Parameter {
+                keyword: "value",
+                kind: kind::ANY,
+                required: true,
+            },
+            Parameter {
+                keyword: "filter",
+                kind: kind::BYTES,
+                required: true,
+            },
+        ]
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[Example {
+            title: "select items",
+            source: r#"jq!({"a": [{"x": 1}, {"x": 5}]}, ".a[] | select(.x > 3)")"#,
+            result: Ok(r#"[{"x": 5}]"#),
+        }]
+    }
+
+    fn compile(
+        &self,
+        state: &state::TypeState,
+        ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        let code = arguments
+            .required_literal("filter", state)?
+            .try_bytes_utf8_lossy()
+            .expect("filter is bytes")
+            .into_owned();
+        let filter = compile(&code).map_err(|err| {
+            Box::new(ExpressionError::Error {
+                message: format!("invalid jq filter: {err}"),
+                labels: vec![Label::primary("in this call", ctx.span())],
+                notes: vec![],
+            }) as Box<dyn DiagnosticMessage>
+        })?;
+
+        Ok(JqFn {
+            value: arguments.required("value"),
+            code,
+            filter,
+        }
+        .as_expr())
+    }
+}
+
+#[derive(Clone)]
+struct JqFn {
+    value: Box<dyn Expression>,
+    code: String,
+    filter: JqFilter,
+}
+
+impl std::fmt::Debug for JqFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JqFn")
+            .field("value", &self.value)
+            .field("code", &self.code)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FunctionExpression for JqFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        jq(&self.filter, self.value.resolve(ctx)?)
+    }
+
+    fn type_def(&self, _: &state::TypeState) -> TypeDef {
+        TypeDef::array(Collection::any()).fallible()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vrl::value;
+
+    fn run(code: &str, value: Value) -> Resolved {
+        jq(&compile(code).unwrap(), value)
+    }
+
+    #[test]
+    fn returns_every_output() {
+        let value = value!({"a": [{"x": 1}, {"x": 5, "y": "z"}, {"x": 7}]});
+
+        assert_eq!(
+            run(".a[] | select(.x > 3)", value.clone()).unwrap(),
+            value!([{"x": 5, "y": "z"}, {"x": 7}])
+        );
+        assert_eq!(run("[.a[].x] | add", value.clone()).unwrap(), value!([13]));
+        assert_eq!(run(".a[0].missing", value).unwrap(), value!([null]));
+    }
+
+    #[test]
+    fn fails_on_runtime_errors() {
+        let err = run(".a + 1", value!({"a": "b"})).unwrap_err();
+        assert!(err.to_string().starts_with("jq filter failed:"));
+    }
+
+    #[test]
+    fn rejects_invalid_filters() {
+        assert!(compile(".a[").is_err());
+        assert_eq!(compile("nope").err().unwrap(), "undefined filter `nope`");
+    }
+}
@@ -0,0 +1,132 @@
+//! Custom VRL functions.
+//!
+//! Functions are grouped by what they need: the default group is always
+//! built, while groups that talk to the network, handle key material, load
+//! enrichment data, run external commands or add encodings sit behind the
+//! `networking`, `crypto`, `enrichment`, `exec` and `encoding` cargo
+//! features, so minimal builds leave them (and their dependencies) out
+//! entirely. A group is a module with a `register` function, declared
+//! here under its feature and called from `register`.
+//!
+//! Every function is exported here, under the feature of its group, so that
+//! other programs embedding VRL can register them next to or instead of the
+//! stdlib functions:
+//!
+//! ```
+//! use vrl::compiler::Function;
+//!
+//! let mut functions = vrl::stdlib::all();
+//! functions.retain(|function| function.identifier() != "split");
+//! functions.push(Box::new(vrl_test::functions::Split));
+//! ```
+
+mod argument;
+mod cache;
+mod counter;
+#[cfg(feature = "crypto")]
+mod crypto;
+#[cfg(feature = "encoding")]
+mod encoding;
+#[cfg(feature = "enrichment")]
+mod enrichment;
+#[cfg(feature = "exec")]
+mod exec;
+mod ids;
+mod jq;
+#[cfg(feature = "networking")]
+mod networking;
+mod rate_limit;
+mod regex_cache;
+mod split;
+
+use std::sync::Arc;
+use vrl::compiler::Function;
+
+use crate::cli::RegistryArgs;
+use crate::registry::Registry;
+
+pub use cache::{CacheConfig, CacheGet, CacheSet};
+pub use counter::Counter;
+#[cfg(feature = "crypto")]
+pub use crypto::{HmacSign, KeyStore, VerifySignature};
+#[cfg(feature = "encoding")]
+pub use encoding::{
+    DecodeBase58, DecodeBase62, DecodeCrockford32, DecodeZ85, EncodeBase58, EncodeBase62,
+    EncodeCrockford32, EncodeZ85,
+};
+#[cfg(feature = "enrichment")]
+pub use enrichment::{EnrichmentTables, Geoip, SqliteLookup};
+#[cfg(feature = "exec")]
+pub use exec::Exec;
+pub use ids::{Clock, FixedClock, IdFunction, IdKind, SystemClock};
+pub use jq::Jq;
+#[cfg(feature = "networking")]
+pub use networking::{HttpGet, RedisGet, ResolveAddrs, ResolvePtr};
+pub use rate_limit::RateLimit;
+pub use split::{Split, SplitFn, SplitWhitespace};
+
+/// The cargo features of the function groups compiled into this build.
+pub(crate) const GROUPS: &[&str] = &[
+    #[cfg(feature = "networking")]
+    "networking",
+    #[cfg(feature = "crypto")]
+    "crypto",
+    #[cfg(feature = "enrichment")]
+    "enrichment",
+    #[cfg(feature = "exec")]
+    "exec",
+    #[cfg(feature = "encoding")]
+    "encoding",
+];
+
+/// Registers the custom functions of every group compiled into this build.
+///
+/// Custom implementations of stdlib functions replace them, or with a
+/// `--namespace` are registered under `<namespace>_<name>` next to them.
+pub(crate) fn register(registry: Registry, args: &RegistryArgs) -> Registry {
+    let namespace = args.namespace.as_deref();
+    let clock: Arc<dyn Clock> = match args.fixed_clock {
+        Some(time) => Arc::new(FixedClock(time.timestamp_millis().max(0) as u64)),
+        None => Arc::new(SystemClock),
+    };
+    let cache = CacheConfig {
+        ttl: args.cache_ttl,
+        max_entries: args.cache_max_entries,
+    };
+    let registry = replace(registry, namespace, Split)
+        .add_fn(SplitWhitespace)
+        .add_fn(Counter)
+        .add_fn(CacheGet(cache))
+        .add_fn(CacheSet(cache))
+        .add_fn(Jq)
+        .add_fn(RateLimit::new(clock.clone()));
+    let registry = replace(
+        registry,
+        namespace,
+        IdFunction::new(IdKind::UuidV7, clock.clone()),
+    )
+    .add_fn(IdFunction::new(IdKind::Ulid, clock.clone()))
+    .add_fn(IdFunction::new(IdKind::Snowflake, clock));
+    #[cfg(feature = "networking")]
+    let registry = networking::register(registry, args);
+    #[cfg(feature = "crypto")]
+    let registry = crypto::register(registry, args.key_store.clone());
+    #[cfg(feature = "enrichment")]
+    let registry = enrichment::register(registry, &args.enrichment_table);
+    #[cfg(feature = "exec")]
+    let registry = registry.add_fn(Exec);
+    #[cfg(feature = "encoding")]
+    let registry = encoding::register(registry);
+    registry
+}
+
+fn replace(
+    registry: Registry,
+    namespace: Option<&str>,
+    function: impl Function + 'static,
+) -> Registry {
+    match namespace {
+        Some(namespace) => registry.add_namespaced(namespace, function),
+        None => registry.override_fn(function),
+    }
+}
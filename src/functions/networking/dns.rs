@@ -0,0 +1,363 @@
+use domain::base::Name;
+use domain::resolv::stub::conf::{ResolvConf, ServerConf, Transport};
+use domain::resolv::StubResolver;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::{self, Runtime};
+use vrl::prelude::*;
+
+use crate::functions::argument::{self, Argument};
+
+/// Builds a resolver configuration querying `servers` (`ip` or `ip:port`
+/// strings), or the servers from `/etc/resolv.conf` when there are none.
+///
+/// Each server is asked once and given `timeout_ms` to answer.
+fn resolv_conf(servers: Value, timeout_ms: Value) -> Result<ResolvConf, ExpressionError> {
+    let servers = servers
+        .try_array()?
+        .into_iter()
+        .map(|server| {
+            let server = server.try_bytes_utf8_lossy()?;
+            let addr = SocketAddr::from_str(&server)
+                .or_else(|_| IpAddr::from_str(&server).map(|ip| SocketAddr::new(ip, 53)))
+                .map_err(|_| format!("invalid DNS server: {server}"))?;
+            Ok(ServerConf::new(addr, Transport::UdpTcp))
+        })
+        .collect::<Result<Vec<_>, ExpressionError>>()?;
+
+    let mut conf = if servers.is_empty() {
+        ResolvConf::default()
+    } else {
+        ResolvConf::new()
+    };
+    conf.servers.extend(servers);
+    conf.options.timeout = Duration::from_millis(timeout_ms.try_integer()?.max(0) as u64);
+    conf.options.attempts = 1;
+    conf.finalize();
+    Ok(conf)
+}
+
+/// A stub resolver with the runtime it runs on, set up once per call of a
+/// function when its servers and timeout are literals, so that the events
+/// it resolves share its runtime and connections.
+struct Resolver {
+    stub: StubResolver,
+    runtime: Runtime,
+}
+
+impl Resolver {
+    fn new(conf: ResolvConf) -> Result<Self, ExpressionError> {
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| format!("failed to start the DNS resolver: {err}"))?;
+        Ok(Self {
+            stub: StubResolver::from_conf(conf),
+            runtime,
+        })
+    }
+
+    /// Resolves a host name to its IPv6 and IPv4 addresses.
+    fn addrs(&self, value: Value) -> Resolved {
+        let host = value.try_bytes_utf8_lossy()?;
+        let name = Name::<Vec<u8>>::from_str(&host)
+            .map_err(|err| format!("invalid host name {host}: {err}"))?;
+
+        let found = self
+            .runtime
+            .block_on(self.stub.lookup_host(name))
+            .map_err(|err| format!("lookup of {host} failed: {err}"))?;
+        Ok(found
+            .iter()
+            .map(|addr| Value::from(addr.to_string()))
+            .collect::<Vec<_>>()
+            .into())
+    }
+
+    /// Resolves an IP address to the host names of its PTR records.
+    fn names(&self, value: Value) -> Resolved {
+        let addr = value.try_bytes_utf8_lossy()?;
+        let ip =
+            IpAddr::from_str(&addr).map_err(|err| format!("invalid IP address {addr}: {err}"))?;
+
+        let found = self
+            .runtime
+            .block_on(self.stub.lookup_addr(ip))
+            .map_err(|err| format!("reverse lookup of {addr} failed: {err}"))?;
+        Ok(found
+            .iter()
+            .map(|name| Value::from(name.to_string()))
+            .collect::<Vec<_>>()
+            .into())
+    }
+}
+
+impl fmt::Debug for Resolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Resolver")
+            .field("servers", &self.stub.options())
+            .finish_non_exhaustive()
+    }
+}
+
+/// What a DNS function looks up.
+#[derive(Clone, Copy, Debug)]
+enum Lookup {
+    Addrs,
+    Names,
+}
+
+const PARAMETERS: &[Parameter] = &[
+    Parameter {
+        keyword: "value",
+        kind: kind::BYTES,
+        required: true,
+    },
+    Parameter {
+        keyword: "servers",
+        kind: kind::ARRAY,
+        required: false,
+    },
+    Parameter {
+        keyword: "timeout_ms",
+        kind: kind::INTEGER,
+        required: false,
+    },
+];
+
+fn compile(lookup: Lookup, state: &state::TypeState, arguments: ArgumentList) -> Compiled {
+    let argument = |expression| Argument::new(expression, state);
+    let servers = argument(
+        arguments
+            .optional("servers")
+            .unwrap_or_else(|| expr!(Vec::<Value>::new())),
+    );
+    let timeout_ms = argument(
+        arguments
+            .optional("timeout_ms")
+            .unwrap_or_else(|| expr!(5_000)),
+    );
+    // an invalid server is left to fail when resolved, as it would if it
+    // weren't a literal
+    let resolver = argument::fold([&servers, &timeout_ms], |[servers, timeout_ms]| {
+        resolv_conf(servers.clone(), timeout_ms.clone()).and_then(Resolver::new)
+    })
+    .and_then(Result::ok)
+    .map(Arc::new);
+
+    Ok(LookupFn {
+        lookup,
+        value: arguments.required("value"),
+        servers,
+        timeout_ms,
+        resolver,
+    }
+    .as_expr())
+}
+
+/// Looks up the addresses of a host name, returning them as an array of
+/// strings, through the system's or the given DNS servers.
+#[derive(Clone, Copy, Debug)]
+pub struct ResolveAddrs;
+
+impl Function for ResolveAddrs {
+    fn identifier(&self) -> &'static str {
+        "resolve_addrs"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        PARAMETERS
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[Example {
+            title: "custom resolver",
+            source: r#"resolve_addrs!("localhost", servers: ["127.0.0.53"])"#,
+            result: Ok(r#"["127.0.0.1"]"#),
+        }]
+    }
+
+    fn compile(
+        &self,
+        state: &state::TypeState,
+        _ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        compile(Lookup::Addrs, state, arguments)
+    }
+}
+
+/// Looks up the host names of an IP address, returning every PTR record as
+/// an array, through the system's or the given DNS servers.
+#[derive(Clone, Copy, Debug)]
+pub struct ResolvePtr;
+
+impl Function for ResolvePtr {
+    fn identifier(&self) -> &'static str {
+        "resolve_ptr"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        PARAMETERS
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[Example {
+            title: "custom resolver",
+            source: r#"resolve_ptr!("127.0.0.1", servers: ["127.0.0.53"])"#,
+            result: Ok(r#"["localhost"]"#),
+        }]
+    }
+
+    fn compile(
+        &self,
+        state: &state::TypeState,
+        _ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        compile(Lookup::Names, state, arguments)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LookupFn {
+    lookup: Lookup,
+    value: Box<dyn Expression>,
+    servers: Argument,
+    timeout_ms: Argument,
+    /// The resolver set up when compiling, from literal servers and timeout.
+    resolver: Option<Arc<Resolver>>,
+}
+
+impl FunctionExpression for LookupFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        let value = self.value.resolve(ctx)?;
+        let configured;
+        let resolver = match &self.resolver {
+            Some(resolver) => resolver,
+            None => {
+                let conf = resolv_conf(self.servers.resolve(ctx)?, self.timeout_ms.resolve(ctx)?)?;
+                configured = Resolver::new(conf)?;
+                &configured
+            }
+        };
+        match self.lookup {
+            Lookup::Addrs => resolver.addrs(value),
+            Lookup::Names => resolver.names(value),
+        }
+    }
+
+    fn type_def(&self, _: &state::TypeState) -> TypeDef {
+        TypeDef::array(Collection::from_unknown(Kind::bytes())).fallible()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use domain::base::iana::{Rcode, Rtype};
+    use domain::base::{Message, MessageBuilder};
+    use domain::rdata::{Ptr, A};
+    use std::net::{Ipv4Addr, UdpSocket};
+    use std::thread;
+    use vrl::value;
+
+    /// Answers `queries` DNS queries over UDP: A queries with 192.0.2.1, PTR
+    /// queries with `host.example.`, anything else with no records. Returns
+    /// the server address.
+    fn serve(queries: usize) -> String {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap().to_string();
+
+        thread::spawn(move || {
+            let mut buf = [0; 512];
+            for _ in 0..queries {
+                let (len, peer) = socket.recv_from(&mut buf).unwrap();
+                let request = Message::from_octets(buf[..len].to_vec()).unwrap();
+                let question = request.sole_question().unwrap();
+                let mut answer = MessageBuilder::new_vec()
+                    .start_answer(&request, Rcode::NOERROR)
+                    .unwrap();
+                match question.qtype() {
+                    Rtype::A => answer
+                        .push((question.qname(), 60, A::new(Ipv4Addr::new(192, 0, 2, 1))))
+                        .unwrap(),
+                    Rtype::PTR => {
+                        let host = Name::<Vec<u8>>::from_str("host.example.").unwrap();
+                        answer.push((question.qname(), 60, Ptr::new(host))).unwrap()
+                    }
+                    _ => {}
+                }
+                socket.send_to(answer.as_slice(), peer).unwrap();
+            }
+        });
+
+        addr
+    }
+
+    /// A server that never answers.
+    fn silent() -> (UdpSocket, String) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap().to_string();
+        (socket, addr)
+    }
+
+    fn resolver(servers: Value, timeout_ms: i64) -> Resolver {
+        Resolver::new(resolv_conf(servers, timeout_ms.into()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn looks_up_addresses() {
+        let resolver = resolver(value!([(serve(2))]), 1_000);
+        let addrs = resolver.addrs("host.example".into()).unwrap();
+        assert_eq!(addrs, value!(["192.0.2.1"]));
+    }
+
+    #[test]
+    fn looks_up_names() {
+        let resolver = resolver(value!([(serve(1))]), 1_000);
+        let names = resolver.names("192.0.2.1".into()).unwrap();
+        assert_eq!(names, value!(["host.example"]));
+    }
+
+    #[test]
+    fn times_out() {
+        let (_socket, server) = silent();
+        let err = resolver(value!([server]), 50)
+            .addrs("host.example".into())
+            .unwrap_err();
+        assert!(err.to_string().starts_with("lookup of host.example failed"));
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let err = resolver(value!([]), 50)
+            .names("not an ip".into())
+            .unwrap_err();
+        assert!(err.to_string().starts_with("invalid IP address not an ip"));
+
+        let err = resolv_conf(value!(["nope"]), 50.into()).unwrap_err();
+        assert_eq!(err.to_string(), "invalid DNS server: nope");
+    }
+
+    #[test]
+    fn literal_servers_share_a_resolver() {
+        let source = format!(
+            r#"resolve_addrs!(.host, servers: ["{}"], timeout_ms: 1000)"#,
+            serve(4)
+        );
+        let functions: Vec<Box<dyn Function>> = vec![Box::new(ResolveAddrs)];
+        let program = vrl::compiler::compile(&source, &functions).unwrap().program;
+
+        let mut runtime = vrl::compiler::runtime::Runtime::default();
+        for _ in 0..2 {
+            let mut target = crate::program::new_target(value!({"host": "host.example"}));
+            let resolved = runtime.resolve(&mut target, &program, &TimeZone::default());
+            runtime.clear();
+            assert_eq!(resolved.unwrap(), value!(["192.0.2.1"]));
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use ureq::Agent;
+use vrl::prelude::*;
+use vrl::value::kind::Field;
+
+/// Sends a GET request to `url` with `headers` and returns the response as
+/// `{"status": <integer>, "body": <string>}`.
+///
+/// Any status counts as a response; failing to connect, or to get a
+/// response within `timeout_ms`, is an error.
+fn http_get(url: Value, headers: Value, timeout_ms: Value) -> Resolved {
+    let url = url.try_bytes_utf8_lossy()?.into_owned();
+    let timeout = Duration::from_millis(timeout_ms.try_integer()?.max(0) as u64);
+    let agent: Agent = Agent::config_builder()
+        .http_status_as_error(false)
+        .timeout_global(Some(timeout))
+        .build()
+        .into();
+
+    let mut request = agent.get(&url);
+    for (name, value) in headers.try_object()? {
+        request = request.header(name.as_str(), &*value.try_bytes_utf8_lossy()?);
+    }
+    let mut response = request
+        .call()
+        .map_err(|err| format!("GET {url} failed: {err}"))?;
+    let body = response
+        .body_mut()
+        .read_to_vec()
+        .map_err(|err| format!("failed to read response from {url}: {err}"))?;
+
+    Ok(Value::Object(BTreeMap::from([
+        (
+            "status".into(),
+            i64::from(response.status().as_u16()).into(),
+        ),
+        ("body".into(), Value::Bytes(body.into())),
+    ])))
+}
+
+fn response_kind() -> BTreeMap<Field, Kind> {
+    BTreeMap::from([
+        ("status".into(), Kind::integer()),
+        ("body".into(), Kind::bytes()),
+    ])
+}
+
+vrl_fn! {
+    /// Fetches a URL over HTTP.
+    pub struct HttpGet => HttpGetFn {
+        identifier: "http_get",
+        parameters: {
+            url: kind::BYTES,
+            headers: kind::OBJECT => BTreeMap::<KeyString, Value>::new(),
+            timeout_ms: kind::INTEGER => 5_000,
+        },
+        examples: [
+            Example {
+                title: "lookup service",
+                source: r#"http_get!("http://localhost:8080/users/1").status"#,
+                result: Ok("200"),
+            },
+        ],
+        type_def: TypeDef::object(response_kind()).fallible(),
+        resolve: http_get,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use vrl::value;
+
+    /// Answers one request with `status` and the value of its `x-name`
+    /// header as the body, returning the URL to send it to.
+    fn serve(status: u16) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/lookup", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut name = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("x-name:") {
+                    name = value.trim().to_owned();
+                }
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 {status} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{name}",
+                name.len()
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+        });
+
+        url
+    }
+
+    /// A URL nothing listens on.
+    fn closed() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}/", listener.local_addr().unwrap())
+    }
+
+    test_function![
+        http_get => HttpGet;
+
+        sends_headers {
+            args: func_args![url: serve(200), headers: value!({"x-name": "vrl"})],
+            want: Ok(value!({"status": 200, "body": "vrl"})),
+            tdef: TypeDef::object(response_kind()).fallible(),
+        }
+
+        error_status_is_a_response {
+            args: func_args![url: serve(404)],
+            want: Ok(value!({"status": 404, "body": ""})),
+            tdef: TypeDef::object(response_kind()).fallible(),
+        }
+    ];
+
+    #[test]
+    fn connection_refused() {
+        let url = closed();
+        let err = http_get(url.clone().into(), value!({}), 500.into()).unwrap_err();
+        assert!(err.to_string().starts_with(&format!("GET {url} failed")));
+    }
+}
@@ -0,0 +1,21 @@
+//! Functions calling out to network services, for testing programs against
+//! live lookups.
+
+mod dns;
+mod http_get;
+mod redis_get;
+
+use crate::cli::RegistryArgs;
+use crate::registry::Registry;
+
+pub use dns::{ResolveAddrs, ResolvePtr};
+pub use http_get::HttpGet;
+pub use redis_get::RedisGet;
+
+pub(super) fn register(registry: Registry, args: &RegistryArgs) -> Registry {
+    registry
+        .add_fn(ResolveAddrs)
+        .add_fn(ResolvePtr)
+        .add_fn(HttpGet)
+        .add_fn(RedisGet::new(args.redis_url.clone()))
+}
@@ -0,0 +1,260 @@
+use redis::{Client, Connection};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use vrl::diagnostic::{DiagnosticMessage, Label, Note, Span};
+use vrl::prelude::*;
+
+use crate::state::{FunctionState, RunState};
+
+/// How long connecting to the server, and each command, may take.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connections to the server, opened as calls need them and reused for the
+/// rest of the run.
+struct Pool {
+    client: Client,
+    idle: Mutex<Vec<Connection>>,
+}
+
+impl Pool {
+    fn new(url: &str) -> redis::RedisResult<Self> {
+        Ok(Self {
+            client: Client::open(url)?,
+            idle: Mutex::default(),
+        })
+    }
+
+    fn get(&self, key: &[u8]) -> redis::RedisResult<Option<Vec<u8>>> {
+        let idle = self.idle.lock().expect("redis pool lock poisoned").pop();
+        let mut connection = match idle {
+            Some(connection) => connection,
+            None => {
+                let connection = self.client.get_connection_with_timeout(TIMEOUT)?;
+                connection.set_read_timeout(Some(TIMEOUT))?;
+                connection.set_write_timeout(Some(TIMEOUT))?;
+                connection
+            }
+        };
+
+        // a connection that failed may be in any state, so it isn't reused
+        let value = redis::cmd("GET").arg(key).query(&mut connection)?;
+        self.idle
+            .lock()
+            .expect("redis pool lock poisoned")
+            .push(connection);
+        Ok(value)
+    }
+}
+
+impl FunctionState for Pool {
+    /// Closes the idle connections.
+    fn flush(&self) -> anyhow::Result<()> {
+        self.idle.lock().expect("redis pool lock poisoned").clear();
+        Ok(())
+    }
+}
+
+/// Reported when the server URL is missing or invalid.
+#[derive(Debug)]
+struct InvalidServer {
+    error: String,
+    span: Span,
+}
+
+impl std::fmt::Display for InvalidServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.error)
+    }
+}
+
+impl std::error::Error for InvalidServer {}
+
+impl DiagnosticMessage for InvalidServer {
+    fn code(&self) -> usize {
+        0
+    }
+
+    fn labels(&self) -> Vec<Label> {
+        vec![Label::primary("in this call", self.span)]
+    }
+
+    fn notes(&self) -> Vec<Note> {
+        vec![Note::Hint("set the server with --redis-url".to_owned())]
+    }
+}
+
+/// Fetches a key from the Redis server given with `--redis-url`, returning
+/// null when it doesn't exist.
+#[derive(Debug)]
+pub struct RedisGet {
+    url: Option<String>,
+}
+
+impl RedisGet {
+    pub fn new(url: Option<String>) -> Self {
+        Self { url }
+    }
+}
+
+impl Function for RedisGet {
+    fn identifier(&self) -> &'static str {
+        "redis_get"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[Parameter {
+            keyword: "key",
+            kind: kind::BYTES,
+            required: true,
+        }]
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[Example {
+            title: "cached user",
+            source: r#"redis_get!("user:1")"#,
+            result: Ok(r#""alice""#),
+        }]
+    }
+
+    fn compile(
+        &self,
+        _state: &state::TypeState,
+        ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        let invalid = |error: String| {
+            Box::new(InvalidServer {
+                error,
+                span: ctx.span(),
+            }) as Box<dyn DiagnosticMessage>
+        };
+        let url = self
+            .url
+            .as_deref()
+            .ok_or_else(|| invalid("no Redis server to read from".to_owned()))?;
+        let open = || Pool::new(url).map_err(|err| invalid(format!("invalid Redis URL: {err}")));
+        // every call in a run shares the pool, outside of one a call has its own
+        let pool = match ctx.get_external_context::<RunState>() {
+            Some(state) => {
+                let pool = open()?;
+                state.get_or_init(self.identifier(), || pool)
+            }
+            None => Arc::new(open()?),
+        };
+
+        Ok(RedisGetFn {
+            key: arguments.required("key"),
+            pool,
+        }
+        .as_expr())
+    }
+}
+
+#[derive(Clone)]
+struct RedisGetFn {
+    key: Box<dyn Expression>,
+    pool: Arc<Pool>,
+}
+
+impl std::fmt::Debug for RedisGetFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RedisGetFn")
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FunctionExpression for RedisGetFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        let key = self.key.resolve(ctx)?;
+        let key = key.try_bytes()?;
+        let value = self
+            .pool
+            .get(&key)
+            .map_err(|err| format!("redis GET failed: {err}"))?;
+
+        Ok(value.map_or(Value::Null, |value| Value::Bytes(value.into())))
+    }
+
+    fn type_def(&self, _: &state::TypeState) -> TypeDef {
+        TypeDef::bytes().or_null().fallible()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    /// Speaks just enough RESP to answer `GET greeting` with `hello` and
+    /// any other GET with nil, counting the connections it accepts.
+    fn serve() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("redis://{}/", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        thread::spawn({
+            let connections = connections.clone();
+            move || {
+                for stream in listener.incoming() {
+                    connections.fetch_add(1, Ordering::Relaxed);
+                    let mut reader = BufReader::new(stream.unwrap());
+                    while let Some(command) = read_command(&mut reader) {
+                        let reply: &[u8] = match command.as_slice() {
+                            [get, key] if get.eq_ignore_ascii_case(b"GET") => match &key[..] {
+                                b"greeting" => b"$5\r\nhello\r\n",
+                                _ => b"$-1\r\n",
+                            },
+                            _ => b"+OK\r\n",
+                        };
+                        reader.get_mut().write_all(reply).unwrap();
+                    }
+                }
+            }
+        });
+
+        (url, connections)
+    }
+
+    fn read_command(reader: &mut impl BufRead) -> Option<Vec<Vec<u8>>> {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let count = line.trim().strip_prefix('*')?.parse().ok()?;
+        (0..count)
+            .map(|_| {
+                let mut line = String::new();
+                reader.read_line(&mut line).ok()?;
+                let len = line.trim().strip_prefix('$')?.parse::<usize>().ok()?;
+                let mut arg = vec![0; len + 2];
+                reader.read_exact(&mut arg).ok()?;
+                arg.truncate(len);
+                Some(arg)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gets_keys_over_one_connection() {
+        let (url, connections) = serve();
+        let pool = Pool::new(&url).unwrap();
+
+        assert_eq!(pool.get(b"greeting").unwrap(), Some(b"hello".to_vec()));
+        assert_eq!(pool.get(b"missing").unwrap(), None);
+        assert_eq!(connections.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn needs_a_server() {
+        let functions: Vec<Box<dyn Function>> = vec![Box::new(RedisGet::new(None))];
+        let compiled = crate::program::compile_source(
+            r#"redis_get!("key")"#,
+            &functions,
+            &RunState::default(),
+        );
+        assert!(compiled.is_none());
+    }
+}
@@ -0,0 +1,198 @@
+use log::info;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use vrl::prelude::*;
+
+use super::ids::Clock;
+use crate::state::{FunctionState, RunState};
+
+struct Bucket {
+    tokens: f64,
+    millis: u64,
+}
+
+/// A token bucket per key, shared by every `rate_limit` call in a run.
+#[derive(Default)]
+struct Buckets {
+    buckets: Mutex<HashMap<String, Bucket>>,
+    limited: Mutex<HashMap<String, u64>>,
+}
+
+impl Buckets {
+    /// Takes a token from the bucket of `key`, which holds up to `limit`
+    /// tokens and refills at `limit` per `window_ms`. Returns false when it is
+    /// empty.
+    fn take(&self, key: &str, limit: i64, window_ms: i64, now: u64) -> bool {
+        let capacity = limit.max(0) as f64;
+        let mut buckets = self.buckets.lock().expect("rate limit lock poisoned");
+        let bucket = buckets.entry(key.to_owned()).or_insert(Bucket {
+            tokens: capacity,
+            millis: now,
+        });
+
+        let elapsed = now.saturating_sub(bucket.millis) as f64;
+        let refill = match window_ms {
+            ..=0 => capacity,
+            window_ms => elapsed * capacity / window_ms as f64,
+        };
+        bucket.tokens = (bucket.tokens + refill).min(capacity);
+        bucket.millis = bucket.millis.max(now);
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        } else {
+            *self
+                .limited
+                .lock()
+                .expect("rate limit lock poisoned")
+                .entry(key.to_owned())
+                .or_default() += 1;
+        }
+        allowed
+    }
+}
+
+impl FunctionState for Buckets {
+    fn flush(&self) -> anyhow::Result<()> {
+        let limited = self.limited.lock().expect("rate limit lock poisoned");
+        let mut limited = limited.iter().collect::<Vec<_>>();
+        limited.sort();
+        for (key, count) in limited {
+            info!("rate_limit {key:?}: {count} events over the limit");
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether an event keyed by `key` is within `limit` events per
+/// `window_ms`, allowing bursts of up to `limit` events.
+#[derive(Debug)]
+pub struct RateLimit {
+    clock: Arc<dyn Clock>,
+}
+
+impl RateLimit {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self { clock }
+    }
+}
+
+impl Function for RateLimit {
+    fn identifier(&self) -> &'static str {
+        "rate_limit"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[
+            Parameter {
+                keyword: "key",
+                kind: kind::BYTES,
+                required: true,
+            },
+            Parameter {
+                keyword: "limit",
+                kind: kind::INTEGER,
+                required: true,
+            },
+            Parameter {
+                keyword: "window_ms",
+                kind: kind::INTEGER,
+                required: true,
+            },
+        ]
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[Example {
+            title: "10 events per second per host",
+            source: r#"rate_limit("web-1", 10, 1000)"#,
+            result: Ok("true"),
+        }]
+    }
+
+    fn compile(
+        &self,
+        _state: &state::TypeState,
+        ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        let buckets = match ctx.get_external_context::<RunState>() {
+            Some(state) => state.get_or_init(self.identifier(), Buckets::default),
+            None => Arc::default(),
+        };
+
+        Ok(RateLimitFn {
+            key: arguments.required("key"),
+            limit: arguments.required("limit"),
+            window_ms: arguments.required("window_ms"),
+            clock: self.clock.clone(),
+            buckets,
+        }
+        .as_expr())
+    }
+}
+
+#[derive(Clone)]
+struct RateLimitFn {
+    key: Box<dyn Expression>,
+    limit: Box<dyn Expression>,
+    window_ms: Box<dyn Expression>,
+    clock: Arc<dyn Clock>,
+    buckets: Arc<Buckets>,
+}
+
+impl std::fmt::Debug for RateLimitFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimitFn")
+            .field("key", &self.key)
+            .field("limit", &self.limit)
+            .field("window_ms", &self.window_ms)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FunctionExpression for RateLimitFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        let key = self.key.resolve(ctx)?;
+        let key = key.try_bytes_utf8_lossy()?;
+        let limit = self.limit.resolve(ctx)?.try_integer()?;
+        let window_ms = self.window_ms.resolve(ctx)?.try_integer()?;
+        let now = self.clock.now_millis();
+
+        Ok(self.buckets.take(&key, limit, window_ms, now).into())
+    }
+
+    fn type_def(&self, _: &state::TypeState) -> TypeDef {
+        TypeDef::boolean().infallible()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn allows_bursts_up_to_the_limit() {
+        let buckets = Buckets::default();
+        let allowed = (0..5).filter(|_| buckets.take("a", 3, 1000, 0)).count();
+        assert_eq!(allowed, 3);
+        // other keys have their own bucket
+        assert!(buckets.take("b", 3, 1000, 0));
+        assert_eq!(buckets.limited.lock().unwrap()["a"], 2);
+    }
+
+    #[test]
+    fn refills_over_the_window() {
+        let buckets = Buckets::default();
+        assert!(buckets.take("a", 2, 1000, 0));
+        assert!(buckets.take("a", 2, 1000, 0));
+        assert!(!buckets.take("a", 2, 1000, 0));
+        assert!(!buckets.take("a", 2, 1000, 400));
+        assert!(buckets.take("a", 2, 1000, 500));
+        // the bucket never holds more than the limit
+        assert!(buckets.take("a", 2, 1000, 10_000));
+        assert!(buckets.take("a", 2, 1000, 10_000));
+        assert!(!buckets.take("a", 2, 1000, 10_000));
+    }
+}
@@ -0,0 +1,118 @@
+//! A process-wide cache of the regexes custom functions build from patterns
+//! only known once an event is resolved, e.g. taken from a field, so each is
+//! compiled once rather than for every event.
+
+use log::info;
+use regex::Regex;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use vrl::prelude::*;
+
+use crate::state::{FunctionState, RunState};
+
+/// How many regexes the cache holds before evicting the oldest.
+const MAX_ENTRIES: usize = 1024;
+
+static CACHE: LazyLock<RegexCache> = LazyLock::new(|| RegexCache::new(MAX_ENTRIES));
+
+#[derive(Default)]
+struct Entries {
+    regexes: HashMap<String, Regex>,
+    /// The patterns in the order they were compiled.
+    order: VecDeque<String>,
+}
+
+struct RegexCache {
+    max_entries: usize,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl RegexCache {
+    fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            entries: Mutex::default(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    fn get(&self, pattern: &str) -> Result<Regex, regex::Error> {
+        let mut entries = self.entries.lock().expect("regex cache lock poisoned");
+        if let Some(regex) = entries.regexes.get(pattern) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(regex.clone());
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let regex = Regex::new(pattern)?;
+        if self.max_entries > 0 {
+            if entries.order.len() >= self.max_entries {
+                if let Some(oldest) = entries.order.pop_front() {
+                    entries.regexes.remove(&oldest);
+                }
+            }
+            entries.order.push_back(pattern.to_owned());
+            entries.regexes.insert(pattern.to_owned(), regex.clone());
+        }
+        Ok(regex)
+    }
+}
+
+/// The compiled `pattern`, from the process-wide cache when it was compiled
+/// before.
+pub(crate) fn regex(pattern: &str) -> Result<Regex, ExpressionError> {
+    CACHE
+        .get(pattern)
+        .map_err(|err| format!("invalid pattern: {err}").into())
+}
+
+/// Logs the hits and misses of the cache when the run being compiled ends;
+/// called by the functions using it while they're compiled.
+pub(crate) fn report(ctx: &FunctionCompileContext) {
+    if let Some(state) = ctx.get_external_context::<RunState>() {
+        state.get_or_init("regex_cache", || Report);
+    }
+}
+
+struct Report;
+
+impl FunctionState for Report {
+    fn flush(&self) -> anyhow::Result<()> {
+        let entries = CACHE.entries.lock().expect("regex cache lock poisoned");
+        info!(
+            "regex cache: {} hits, {} misses, {} regexes",
+            CACHE.hits.load(Ordering::Relaxed),
+            CACHE.misses.load(Ordering::Relaxed),
+            entries.regexes.len()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn compiles_each_pattern_once() {
+        let cache = RegexCache::new(2);
+
+        let regex = cache.get("a+").unwrap();
+        assert!(regex.is_match("baa"));
+        cache.get("a+").unwrap();
+        cache.get("b+").unwrap();
+        assert_eq!(cache.hits.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.misses.load(Ordering::Relaxed), 2);
+
+        // the oldest regex makes room
+        cache.get("c+").unwrap();
+        cache.get("a+").unwrap();
+        assert_eq!(cache.misses.load(Ordering::Relaxed), 4);
+
+        assert!(cache.get("(").is_err());
+    }
+}
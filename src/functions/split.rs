@@ -0,0 +1,1134 @@
+use memchr::memmem;
+use std::borrow::Cow;
+use std::ops::Range;
+use vrl::prelude::*;
+
+use super::argument::{self, Argument};
+use super::regex_cache;
+use crate::arena::{self, Scratch, ScratchVec};
+
+/// Where a pattern matched, with the ranges of its capture groups when they
+/// are asked for.
+struct Delimiter<'a> {
+    range: Range<usize>,
+    groups: ScratchVec<'a, Option<Range<usize>>>,
+}
+
+impl<'a> Delimiter<'a> {
+    fn new(range: Range<usize>, scratch: Scratch<'a>) -> Self {
+        Self {
+            range,
+            groups: ScratchVec::new_in(scratch),
+        }
+    }
+}
+
+/// The first `limit - 1` matches of `pattern`, or the last ones `from_end`,
+/// in the order they appear. Matches from the start are found lazily, and
+/// those collected are kept in `scratch`.
+fn delimiters<'a>(
+    string: &'a str,
+    pattern: &'a Value,
+    limit: usize,
+    from_end: bool,
+    captures: bool,
+    scratch: Scratch<'a>,
+) -> Result<Box<dyn Iterator<Item = Delimiter<'a>> + 'a>, ExpressionError> {
+    let max = limit.saturating_sub(1);
+    Ok(match pattern {
+        Value::Regex(pattern) => {
+            let found: Box<dyn Iterator<Item = Delimiter>> = if captures {
+                Box::new(pattern.captures_iter(string).map(move |found| {
+                    let groups = found
+                        .iter()
+                        .skip(1)
+                        .map(|group| group.map(|group| group.range()));
+                    Delimiter {
+                        range: found.get(0).expect("group 0 is the match").range(),
+                        groups: arena::collect(groups, scratch),
+                    }
+                }))
+            } else {
+                Box::new(
+                    pattern
+                        .find_iter(string)
+                        .map(move |found| Delimiter::new(found.range(), scratch)),
+                )
+            };
+            if from_end {
+                let mut found = arena::collect(found, scratch);
+                found.drain(..found.len().saturating_sub(max));
+                Box::new(found.into_iter())
+            } else {
+                Box::new(found.take(max))
+            }
+        }
+        Value::Bytes(bytes) => {
+            let delimiter = move |(start, found): (usize, &str)| {
+                Delimiter::new(start..start + found.len(), scratch)
+            };
+            match String::from_utf8_lossy(bytes) {
+                Cow::Borrowed(pattern) if !pattern.is_empty() => {
+                    literal_delimiters(string, pattern, max, from_end, scratch)
+                }
+                Cow::Borrowed(pattern) if !from_end => {
+                    Box::new(string.match_indices(pattern).take(max).map(delimiter))
+                }
+                // a pattern fixed up from invalid UTF-8 doesn't outlive this call
+                pattern if !from_end => {
+                    let found = string.match_indices(pattern.as_ref()).take(max);
+                    Box::new(arena::collect(found.map(delimiter), scratch).into_iter())
+                }
+                pattern => {
+                    let found = string.rmatch_indices(pattern.as_ref()).take(max);
+                    let mut found = arena::collect(found.map(delimiter), scratch);
+                    found.reverse();
+                    Box::new(found.into_iter())
+                }
+            }
+        }
+        value => {
+            return Err(ValueError::Expected {
+                got: value.kind(),
+                expected: Kind::regex() | Kind::bytes(),
+            }
+            .into())
+        }
+    })
+}
+
+/// The first `max` matches of a non-empty literal `pattern`, or the last ones
+/// `from_end`, searched for with memchr rather than `str` matching. A match
+/// of valid UTF-8 in valid UTF-8 always falls on character boundaries.
+fn literal_delimiters<'a>(
+    string: &'a str,
+    pattern: &'a str,
+    max: usize,
+    from_end: bool,
+    scratch: Scratch<'a>,
+) -> Box<dyn Iterator<Item = Delimiter<'a>> + 'a> {
+    let (haystack, length) = (string.as_bytes(), pattern.len());
+    let delimiter = move |start: usize| Delimiter::new(start..start + length, scratch);
+    let found: Box<dyn Iterator<Item = usize>> = match (pattern.as_bytes(), from_end) {
+        (&[byte], false) => Box::new(memchr::memchr_iter(byte, haystack)),
+        (&[byte], true) => Box::new(memchr::memrchr_iter(byte, haystack)),
+        (needle, false) => Box::new(memmem::find_iter(haystack, needle)),
+        (needle, true) => Box::new(memmem::rfind_iter(haystack, needle)),
+    };
+    if from_end {
+        let mut found = arena::collect(found.take(max).map(delimiter), scratch);
+        found.reverse();
+        Box::new(found.into_iter())
+    } else {
+        Box::new(found.take(max).map(delimiter))
+    }
+}
+
+/// The segments of `string` between `delimiters`, cut as they are asked for
+/// so huge values aren't held twice. Each delimiter is kept at the end of the
+/// segment before it when `inclusive`; captured groups follow that segment,
+/// groups that didn't match as empty strings.
+struct Segments<'a, I> {
+    string: &'a str,
+    delimiters: I,
+    groups: allocator_api2::vec::IntoIter<Option<Range<usize>>, Scratch<'a>>,
+    start: usize,
+    inclusive: bool,
+    cut: bool,
+    done: bool,
+}
+
+impl<'a, I: Iterator<Item = Delimiter<'a>>> Segments<'a, I> {
+    fn new(string: &'a str, delimiters: I, inclusive: bool, scratch: Scratch<'a>) -> Self {
+        Self {
+            string,
+            delimiters,
+            groups: ScratchVec::new_in(scratch).into_iter(),
+            start: 0,
+            inclusive,
+            cut: false,
+            done: false,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Delimiter<'a>>> Iterator for Segments<'a, I> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        if let Some(group) = self.groups.next() {
+            return Some(group.map_or("", |group| &self.string[group]));
+        }
+        if self.done {
+            return None;
+        }
+        match self.delimiters.next() {
+            Some(Delimiter { range, groups }) => {
+                let end = if self.inclusive {
+                    range.end
+                } else {
+                    range.start
+                };
+                let segment = &self.string[self.start..end];
+                self.start = range.end;
+                self.groups = groups.into_iter();
+                self.cut = true;
+                Some(segment)
+            }
+            None => {
+                self.done = true;
+                // an inclusive split doesn't end in an empty segment
+                let rest = &self.string[self.start..];
+                (!(self.inclusive && self.cut && rest.is_empty())).then_some(rest)
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (delimiters, _) = self.delimiters.size_hint();
+        (delimiters + self.groups.len(), None)
+    }
+}
+
+/// The regex matching `pattern`, or any of an array of patterns with the
+/// first one listed winning where several match at the same place. Literals
+/// are escaped, with (?i) when they match regardless of case; regexes are
+/// kept as they are whatever `case_sensitive` is.
+fn alternation(pattern: &Value, case_sensitive: bool) -> Result<String, ExpressionError> {
+    Ok(match pattern {
+        Value::Bytes(bytes) if case_sensitive => regex::escape(&String::from_utf8_lossy(bytes)),
+        Value::Bytes(bytes) => format!("(?i:{})", regex::escape(&String::from_utf8_lossy(bytes))),
+        Value::Regex(regex) => format!("(?:{})", regex.as_str()),
+        Value::Array(patterns) if patterns.is_empty() => {
+            return Err("pattern array is empty".into())
+        }
+        Value::Array(patterns) => patterns
+            .iter()
+            .map(|pattern| alternation(pattern, case_sensitive))
+            .collect::<Result<Vec<_>, _>>()?
+            .join("|"),
+        value => {
+            return Err(ValueError::Expected {
+                got: value.kind(),
+                expected: Kind::regex() | Kind::bytes(),
+            }
+            .into())
+        }
+    })
+}
+
+/// What `split` splits on: the pattern as given, or prepared when compiling.
+enum Pattern<'a> {
+    Given {
+        pattern: Value,
+        case_sensitive: Value,
+    },
+    Prepared(&'a Value),
+}
+
+/// Turns a case insensitive literal or an array of patterns into a regex
+/// matching any of them.
+fn prepare(pattern: Value, case_sensitive: bool) -> Result<Value, ExpressionError> {
+    Ok(match pattern {
+        Value::Bytes(_) if case_sensitive => pattern,
+        Value::Bytes(_) | Value::Array(_) => {
+            let pattern = regex_cache::regex(&alternation(&pattern, case_sensitive)?)?;
+            Value::Regex(pattern.into())
+        }
+        pattern => pattern,
+    })
+}
+
+fn split(
+    value: Value,
+    pattern: Pattern,
+    limit: Value,
+    inclusive: Value,
+    from_end: Value,
+    captures: Value,
+    max_segments: Option<Value>,
+) -> Resolved {
+    let bytes = value.try_bytes()?;
+    let string = String::from_utf8_lossy(&bytes);
+    let limit = match limit.try_integer()? {
+        x if x < 0 => 0,
+        x => x as usize,
+    };
+    if limit == 0 {
+        return Ok(Value::Array(vec![]));
+    }
+    let max_segments = match max_segments {
+        Some(max) => Some(max.try_integer()?.max(0) as usize),
+        None => None,
+    };
+    let pattern = match pattern {
+        Pattern::Given {
+            pattern,
+            case_sensitive,
+        } => Cow::Owned(prepare(pattern, case_sensitive.try_boolean()?)?),
+        Pattern::Prepared(pattern) => Cow::Borrowed(pattern),
+    };
+    let (from_end, captures) = (from_end.try_boolean()?, captures.try_boolean()?);
+    let inclusive = inclusive.try_boolean()?;
+
+    // segments of valid UTF-8 are slices of the value's buffer rather than
+    // copies, keeping it alive for as long as any of them is
+    let segment_value = |segment: &str| match &string {
+        Cow::Borrowed(_) => Value::Bytes(bytes.slice_ref(segment.as_bytes())),
+        Cow::Owned(_) => Value::from(segment),
+    };
+    arena::scratch(|scratch| {
+        let delimiters = delimiters(&string, &pattern, limit, from_end, captures, scratch)?;
+        let mut segments = Segments::new(&string, delimiters, inclusive, scratch);
+        let mut values = Vec::with_capacity(
+            segments
+                .size_hint()
+                .0
+                .min(max_segments.unwrap_or(usize::MAX)),
+        );
+        for segment in segments.by_ref() {
+            // fail before materializing more than the guard allows
+            if max_segments.is_some_and(|max| values.len() == max) {
+                return Err(format!("split produced more than {} segments", values.len()).into());
+            }
+            values.push(segment_value(segment));
+        }
+        Ok(Value::Array(values))
+    })
+}
+
+/// Splits a string on a literal or regex pattern, or any of an array of
+/// them; unlike the stdlib function it can keep delimiters and captured
+/// groups, ignore case, apply the limit from the end and guard against huge
+/// results.
+///
+/// `case_sensitive` only applies to literals: regexes, in an array or not,
+/// match as written, so they ignore case with their own `(?i)` flag.
+#[derive(Clone, Copy, Debug)]
+pub struct Split;
+
+impl Function for Split {
+    fn identifier(&self) -> &'static str {
+        "split"
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        &[
+            Parameter {
+                keyword: "value",
+                kind: kind::BYTES,
+                required: true,
+            },
+            Parameter {
+                keyword: "pattern",
+                kind: kind::BYTES | kind::REGEX | kind::ARRAY,
+                required: true,
+            },
+            Parameter {
+                keyword: "limit",
+                kind: kind::INTEGER,
+                required: false,
+            },
+            Parameter {
+                keyword: "inclusive",
+                kind: kind::BOOLEAN,
+                required: false,
+            },
+            Parameter {
+                keyword: "from_end",
+                kind: kind::BOOLEAN,
+                required: false,
+            },
+            Parameter {
+                keyword: "case_sensitive",
+                kind: kind::BOOLEAN,
+                required: false,
+            },
+            Parameter {
+                keyword: "captures",
+                kind: kind::BOOLEAN,
+                required: false,
+            },
+            Parameter {
+                keyword: "max_segments",
+                kind: kind::INTEGER,
+                required: false,
+            },
+        ]
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        &[
+            Example {
+                title: "split string",
+                source: r#"split("foobar", "b")"#,
+                result: Ok(r#"["foo", "ar"]"#),
+            },
+            Example {
+                title: "split once",
+                source: r#"split("foobarbaz", "ba", 2)"#,
+                result: Ok(r#"["foo", "rbaz"]"#),
+            },
+            Example {
+                title: "keep delimiters",
+                source: r#"split("a\nb\n", "\n", inclusive: true)"#,
+                result: Ok(r#"["a\n", "b\n"]"#),
+            },
+            Example {
+                title: "split off the extension",
+                source: r#"split("archive.tar.gz", ".", 2, from_end: true)"#,
+                result: Ok(r#"["archive.tar", "gz"]"#),
+            },
+            Example {
+                title: "ignore case",
+                source: r#"split("fooANDbarandbaz", "and", case_sensitive: false)"#,
+                result: Ok(r#"["foo", "bar", "baz"]"#),
+            },
+            Example {
+                title: "split on any delimiter",
+                source: r#"split("a,b;c d", [",", ";", r'\s'])"#,
+                result: Ok(r#"["a", "b", "c", "d"]"#),
+            },
+            Example {
+                title: "split regex",
+                source: r#"split("barbaz", r'ba')"#,
+                result: Ok(r#"["", "r", "z"]"#),
+            },
+            Example {
+                title: "keep captured delimiters",
+                source: r#"split("1+2-3", r'([+-])', captures: true)"#,
+                result: Ok(r#"["1", "+", "2", "-", "3"]"#),
+            },
+            Example {
+                title: "guard against huge results",
+                source: r#"split!("a,b,c", ",", max_segments: 2)"#,
+                result: Err(
+                    r#"function call error for "split" at (0:37): split produced more than 2 segments"#,
+                ),
+            },
+        ]
+    }
+
+    fn compile(
+        &self,
+        state: &state::TypeState,
+        ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        regex_cache::report(ctx);
+        let argument = |expression| Argument::new(expression, state);
+        let pattern = argument(arguments.required("pattern"));
+        let case_sensitive = argument(
+            arguments
+                .optional("case_sensitive")
+                .unwrap_or_else(|| expr!(true)),
+        );
+        // an invalid pattern is left to fail when resolved, as it would if
+        // it weren't a literal
+        let prepared = argument::fold([&pattern, &case_sensitive], |[pattern, case_sensitive]| {
+            prepare(pattern.clone(), case_sensitive.clone().try_boolean()?)
+        })
+        .and_then(Result::ok);
+        Ok(SplitFn {
+            value: arguments.required("value"),
+            pattern,
+            prepared,
+            limit: argument(
+                arguments
+                    .optional("limit")
+                    .unwrap_or_else(|| expr!(999_999_999)),
+            ),
+            inclusive: argument(
+                arguments
+                    .optional("inclusive")
+                    .unwrap_or_else(|| expr!(false)),
+            ),
+            from_end: argument(
+                arguments
+                    .optional("from_end")
+                    .unwrap_or_else(|| expr!(false)),
+            ),
+            case_sensitive,
+            captures: argument(
+                arguments
+                    .optional("captures")
+                    .unwrap_or_else(|| expr!(false)),
+            ),
+            max_segments: arguments.optional("max_segments").map(argument),
+        }
+        .as_expr())
+    }
+}
+
+/// A call of [`Split`], with its literal arguments resolved when compiling.
+#[derive(Debug, Clone)]
+pub struct SplitFn {
+    /// The one argument expected to differ for every event.
+    value: Box<dyn Expression>,
+    pattern: Argument,
+    /// The pattern prepared when compiling, from a literal pattern and case
+    /// sensitivity.
+    prepared: Option<Value>,
+    limit: Argument,
+    inclusive: Argument,
+    from_end: Argument,
+    case_sensitive: Argument,
+    captures: Argument,
+    max_segments: Option<Argument>,
+}
+
+impl FunctionExpression for SplitFn {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        let max_segments = match &self.max_segments {
+            Some(max_segments) => Some(max_segments.resolve(ctx)?),
+            None => None,
+        };
+        let pattern = match &self.prepared {
+            Some(pattern) => Pattern::Prepared(pattern),
+            None => Pattern::Given {
+                pattern: self.pattern.resolve(ctx)?,
+                case_sensitive: self.case_sensitive.resolve(ctx)?,
+            },
+        };
+        split(
+            self.value.resolve(ctx)?,
+            pattern,
+            self.limit.resolve(ctx)?,
+            self.inclusive.resolve(ctx)?,
+            self.from_end.resolve(ctx)?,
+            self.captures.resolve(ctx)?,
+            max_segments,
+        )
+    }
+
+    fn type_def(&self, state: &state::TypeState) -> TypeDef {
+        // besides the guard, only an array of patterns, which may be empty or
+        // hold something other than patterns, makes splitting fail once it
+        // couldn't be prepared when compiling
+        let pattern_array = self.prepared.is_none()
+            && match &self.pattern {
+                Argument::Literal(pattern) => pattern.is_array(),
+                Argument::Expression(pattern) => pattern.type_def(state).kind().contains_array(),
+            };
+        TypeDef::array(Collection::from_unknown(Kind::bytes()))
+            .maybe_fallible(self.max_segments.is_some() || pattern_array)
+    }
+}
+
+vrl_fn! {
+    /// Splits on runs of Unicode whitespace, dropping empty segments, where
+    /// `split(value, " ")` would return an empty string for each extra space.
+    pub struct SplitWhitespace => SplitWhitespaceFn {
+        identifier: "split_whitespace",
+        parameters: {
+            value: kind::BYTES,
+        },
+        examples: [
+            Example {
+                title: "split words",
+                source: r#"split_whitespace(" foo  bar\tbaz\n")"#,
+                result: Ok(r#"["foo", "bar", "baz"]"#),
+            },
+        ],
+        type_def: TypeDef::array(Collection::from_unknown(Kind::bytes())).infallible(),
+        resolve: |value: Value| {
+            let string = value.try_bytes_utf8_lossy()?;
+            Ok(string.split_whitespace().collect::<Vec<_>>().into())
+        },
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::trivial_regex)]
+mod test {
+    use super::*;
+    use vrl::value;
+
+    test_function![
+        split => Split;
+
+        empty {
+            args: func_args![value: "",
+                             pattern: " "
+            ],
+            want: Ok(value!([""])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        single {
+            args: func_args![value: "foo",
+                             pattern: " "
+            ],
+            want: Ok(value!(["foo"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        long {
+            args: func_args![value: "This is a long string.",
+                             pattern: " "
+            ],
+            want: Ok(value!(["This", "is", "a", "long", "string."])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        regex {
+            args: func_args![value: "This is a long string",
+                             pattern: Value::Regex(regex::Regex::new(" ").unwrap().into()),
+                             limit: 2
+            ],
+            want: Ok(value!(["This", "is a long string"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        non_space {
+            args: func_args![value: "ThisaisAlongAstring.",
+                             pattern: Value::Regex(regex::Regex::new("(?i)a").unwrap().into())
+            ],
+            want: Ok(value!(["This", "is", "long", "string."])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        unicode {
+             args: func_args![value: "˙ƃuᴉɹʇs ƃuol ɐ sᴉ sᴉɥ┴",
+                              pattern: " "
+             ],
+             want: Ok(value!(["˙ƃuᴉɹʇs", "ƃuol", "ɐ", "sᴉ", "sᴉɥ┴"])),
+             tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+         }
+
+        limit {
+            args: func_args![value: "This is a long string.",
+                             pattern: " ",
+                             limit: 2
+            ],
+            want: Ok(value!(["This", "is a long string."])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        over_length_limit {
+            args: func_args![value: "This is a long string.",
+                             pattern: " ",
+                             limit: 2000
+            ],
+            want: Ok(value!(["This", "is", "a", "long", "string."])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        zero_limit {
+            args: func_args![value: "This is a long string.",
+                             pattern: " ",
+                             limit: 0
+            ],
+            want: Ok(value!([])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        inclusive {
+            args: func_args![value: "a\nb\n\nc",
+                             pattern: "\n",
+                             inclusive: true
+            ],
+            want: Ok(value!(["a\n", "b\n", "\n", "c"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        inclusive_trailing {
+            args: func_args![value: "a\nb\n",
+                             pattern: "\n",
+                             inclusive: true
+            ],
+            want: Ok(value!(["a\n", "b\n"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        inclusive_regex_limit {
+            args: func_args![value: "a, b,  c",
+                             pattern: Value::Regex(regex::Regex::new(", *").unwrap().into()),
+                             limit: 2,
+                             inclusive: true
+            ],
+            want: Ok(value!(["a, ", "b,  c"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        from_end {
+            args: func_args![value: "archive.tar.gz",
+                             pattern: ".",
+                             limit: 2,
+                             from_end: true
+            ],
+            want: Ok(value!(["archive.tar", "gz"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        multibyte_delimiter {
+            args: func_args![value: "a→→b→c",
+                             pattern: "→"
+            ],
+            want: Ok(value!(["a", "", "b", "c"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        long_delimiter_from_end {
+            args: func_args![value: "a::b:c::::d",
+                             pattern: "::",
+                             limit: 3,
+                             from_end: true
+            ],
+            want: Ok(value!(["a::b:c", "", "d"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        from_end_regex {
+            args: func_args![value: "a=1 b=2  c=3",
+                             pattern: Value::Regex(regex::Regex::new(" +").unwrap().into()),
+                             limit: 2,
+                             from_end: true
+            ],
+            want: Ok(value!(["a=1 b=2", "c=3"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        from_end_inclusive {
+            args: func_args![value: "a/b/c",
+                             pattern: "/",
+                             limit: 2,
+                             inclusive: true,
+                             from_end: true
+            ],
+            want: Ok(value!(["a/b/", "c"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        case_insensitive {
+            args: func_args![value: "SELECT a FROM b from c",
+                             pattern: " from ",
+                             case_sensitive: false
+            ],
+            want: Ok(value!(["SELECT a", "b", "c"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        case_insensitive_literal {
+            args: func_args![value: "a.B.c",
+                             pattern: ".",
+                             limit: 2,
+                             case_sensitive: false
+            ],
+            want: Ok(value!(["a", "B.c"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        case_sensitive_by_default {
+            args: func_args![value: "fooANDbar",
+                             pattern: "and"
+            ],
+            want: Ok(value!(["fooANDbar"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        any_pattern {
+            args: func_args![value: "a,b;c, d",
+                             pattern: value!([",", ";", ", "])
+            ],
+            want: Ok(value!(["a", "b", "c", " d"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        any_literal_or_regex {
+            args: func_args![value: "a.b1c22d",
+                             pattern: Value::Array(vec![
+                                 ".".into(),
+                                 Value::Regex(regex::Regex::new(r"\d+").unwrap().into()),
+                             ]),
+                             limit: 3
+            ],
+            want: Ok(value!(["a", "b", "c22d"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        any_pattern_ignoring_case {
+            args: func_args![value: "aANDbOrc",
+                             pattern: value!(["and", "or"]),
+                             case_sensitive: false
+            ],
+            want: Ok(value!(["a", "b", "c"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        any_regex_keeps_its_case {
+            args: func_args![value: "aANDbXc",
+                             pattern: Value::Array(vec![
+                                 "and".into(),
+                                 Value::Regex(regex::Regex::new("x").unwrap().into()),
+                                 Value::Regex(regex::Regex::new("(?i)c").unwrap().into()),
+                             ]),
+                             case_sensitive: false
+            ],
+            want: Ok(value!(["a", "bX", ""])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        any_pattern_captures {
+            args: func_args![value: "1+2=3",
+                             pattern: Value::Array(vec![
+                                 Value::Regex(regex::Regex::new("([+-])").unwrap().into()),
+                                 "=".into(),
+                             ]),
+                             captures: true
+            ],
+            want: Ok(value!(["1", "+", "2", "", "3"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        empty_pattern_array {
+            args: func_args![value: "a,b",
+                             pattern: value!([])
+            ],
+            want: Err("pattern array is empty"),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())).fallible(),
+        }
+
+        invalid_pattern_in_array {
+            args: func_args![value: "a,b",
+                             pattern: value!([",", 1])
+            ],
+            want: Err("expected string or regex, got integer"),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())).fallible(),
+        }
+
+        captures {
+            args: func_args![value: "a=1; b:2",
+                             pattern: Value::Regex(regex::Regex::new(r"([=:])|(; )").unwrap().into()),
+                             captures: true
+            ],
+            want: Ok(value!(["a", "=", "", "1", "", "; ", "b", ":", "", "2"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        captures_from_end {
+            args: func_args![value: "1+2-3",
+                             pattern: Value::Regex(regex::Regex::new("([+-])").unwrap().into()),
+                             limit: 2,
+                             from_end: true,
+                             captures: true
+            ],
+            want: Ok(value!(["1+2", "-", "3"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        captures_off {
+            args: func_args![value: "1+2",
+                             pattern: Value::Regex(regex::Regex::new("([+-])").unwrap().into())
+            ],
+            want: Ok(value!(["1", "2"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        max_segments {
+            args: func_args![value: "a,b,c",
+                             pattern: ",",
+                             max_segments: 3
+            ],
+            want: Ok(value!(["a", "b", "c"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())).fallible(),
+        }
+
+        over_max_segments {
+            args: func_args![value: "a,b,c",
+                             pattern: ",",
+                             max_segments: 2
+            ],
+            want: Err("split produced more than 2 segments"),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())).fallible(),
+        }
+
+        max_segments_after_limit {
+            args: func_args![value: "a,b,c",
+                             pattern: ",",
+                             limit: 2,
+                             max_segments: 2
+            ],
+            want: Ok(value!(["a", "b,c"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())).fallible(),
+        }
+
+        negative_limit {
+            args: func_args![value: "This is a long string.",
+                             pattern: " ",
+                             limit: -1
+            ],
+            want: Ok(value!([])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+    ];
+
+    test_function![
+        split_whitespace => SplitWhitespace;
+
+        runs {
+            args: func_args![value: " foo  bar\tbaz\u{3000}qux\n"],
+            want: Ok(value!(["foo", "bar", "baz", "qux"])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+
+        blank {
+            args: func_args![value: "  "],
+            want: Ok(value!([])),
+            tdef: TypeDef::array(Collection::from_unknown(Kind::bytes())),
+        }
+    ];
+
+    #[test]
+    fn limit_from_source() {
+        let functions: Vec<Box<dyn Function>> = vec![Box::new(Split)];
+        let program = crate::program::compile_source(
+            r#"split("foobarbaz", "ba", 2)"#,
+            &functions,
+            &crate::state::RunState::default(),
+        )
+        .unwrap()
+        .program;
+
+        let mut target = crate::program::new_target(value!({}));
+        let result = vrl::compiler::runtime::Runtime::default()
+            .resolve(&mut target, &program, &vrl::compiler::TimeZone::default())
+            .unwrap();
+        assert_eq!(result, value!(["foo", "rbaz"]));
+    }
+
+    /// Splits a 12 MB value into 2 million segments: the way split used to,
+    /// collecting every delimiter and segment before building the values,
+    /// streamed, and streamed with a guard tripping early. Run with
+    /// `cargo test --release bench_huge_split -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_huge_split() {
+        let value = Value::from("field,".repeat(2_000_000));
+        let time = |name: &str, split: &dyn Fn() -> Resolved| {
+            let start = std::time::Instant::now();
+            let result = split();
+            println!("{name:>10}: {:?}", start.elapsed());
+            result
+        };
+        let streamed = |max_segments: Option<Value>| {
+            split(
+                value.clone(),
+                Pattern::Given {
+                    pattern: ",".into(),
+                    case_sensitive: true.into(),
+                },
+                999_999_999.into(),
+                false.into(),
+                false.into(),
+                false.into(),
+                max_segments,
+            )
+        };
+
+        let collected = time("collected", &|| {
+            let string = value.try_bytes_utf8_lossy()?;
+            let delimiters = string
+                .match_indices(',')
+                .map(|(start, found)| start..start + found.len())
+                .collect::<Vec<_>>();
+            let mut start = 0;
+            let mut segments = Vec::new();
+            for delimiter in delimiters {
+                segments.push(&string[start..delimiter.start]);
+                start = delimiter.end;
+            }
+            segments.push(&string[start..]);
+            Ok(segments.into())
+        })
+        .unwrap();
+        assert_eq!(time("streamed", &|| streamed(None)).unwrap(), collected);
+        assert!(time("guarded", &|| streamed(Some(1_000.into()))).is_err());
+    }
+
+    /// Splits 2000 log lines of 5 KB on a frequent byte, a rare byte and a
+    /// short literal, with `str` matching as split used to and with memchr as
+    /// it does now. Run with
+    /// `cargo test --release bench_literal_split -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_literal_split() {
+        let line =
+            "ts=2024-01-01T00:00:00Z level=info msg=\"request served\" status=200 | ".repeat(70);
+        for pattern in [" ", "|", " | "] {
+            let time = |name: &str, split: &dyn Fn(&str) -> usize| {
+                let start = std::time::Instant::now();
+                let segments = (0..2000).map(|_| split(&line)).sum::<usize>();
+                println!("{pattern:?} {name:>7}: {:?}", start.elapsed());
+                segments
+            };
+            let matched = time("str", &|line| line.match_indices(pattern).count() + 1);
+            let memchr = time("memchr", &|line| {
+                arena::scratch(|scratch| {
+                    let delimiters = literal_delimiters(line, pattern, usize::MAX, false, scratch);
+                    Segments::new(line, delimiters, false, scratch).count()
+                })
+            });
+            assert_eq!(memchr, matched);
+        }
+    }
+
+    fn split_on_commas(value: Value) -> Resolved {
+        split(
+            value,
+            Pattern::Given {
+                pattern: ",".into(),
+                case_sensitive: true.into(),
+            },
+            999_999_999.into(),
+            false.into(),
+            false.into(),
+            false.into(),
+            None,
+        )
+    }
+
+    #[test]
+    fn segments_share_the_value() {
+        let value = Value::from("a,bb,,ccc");
+        let Value::Bytes(bytes) = &value else {
+            unreachable!()
+        };
+        let buffer = bytes.as_ptr_range();
+
+        let segments = split_on_commas(value.clone()).unwrap();
+        assert_eq!(segments, value!(["a", "bb", "", "ccc"]));
+        for segment in segments.as_array().unwrap() {
+            let segment = segment.as_bytes().unwrap();
+            assert!(segment.is_empty() || buffer.contains(&segment.as_ptr()));
+        }
+
+        // invalid UTF-8 is fixed up into a copy, cut as before
+        let lossy = split_on_commas(Value::Bytes(Bytes::from_static(b"a,\xff"))).unwrap();
+        assert_eq!(lossy, value!(["a", "\u{fffd}"]));
+    }
+
+    /// Splits a 12 MB value into 2 million segments, copying each segment as
+    /// split used to and slicing the value as it does now, reporting the time
+    /// and allocations of each. Run with
+    /// `cargo test --release bench_segment_copies -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_segment_copies() {
+        use crate::alloc::Allocations;
+
+        let value = Value::from("field,".repeat(2_000_000));
+        let measure = |name: &str, split: &dyn Fn() -> Resolved| {
+            let before = Allocations::current();
+            let start = std::time::Instant::now();
+            let result = split();
+            let elapsed = start.elapsed();
+            let allocations = Allocations::current().since(before);
+            println!(
+                "{name:>8}: {elapsed:?}, {} allocations, {} bytes",
+                allocations.count, allocations.bytes
+            );
+            (result, allocations)
+        };
+
+        let (copied, copies) = measure("copied", &|| {
+            let string = value.try_bytes_utf8_lossy()?;
+            Ok(string
+                .split(',')
+                .map(Value::from)
+                .collect::<Vec<_>>()
+                .into())
+        });
+        let (sliced, slices) = measure("sliced", &|| split_on_commas(value.clone()));
+        assert_eq!(sliced.unwrap(), copied.unwrap());
+        assert!(slices.count * 100 < copies.count);
+    }
+
+    /// Resolves `split("a,b,c", ",")` a million times with its literal
+    /// arguments resolved through their expressions, as split used to, and
+    /// kept as values, as it does now. Run with
+    /// `cargo test --release bench_literal_arguments -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_literal_arguments() {
+        use vrl::compiler::{TargetValue, TimeZone};
+
+        let expression = |argument: &dyn Fn(Box<dyn Expression>) -> Argument| SplitFn {
+            value: expr!("a,b,c"),
+            pattern: argument(expr!(",")),
+            prepared: None,
+            limit: argument(expr!(999_999_999)),
+            inclusive: argument(expr!(false)),
+            from_end: argument(expr!(false)),
+            case_sensitive: argument(expr!(true)),
+            captures: argument(expr!(false)),
+            max_segments: None,
+        };
+        let mut target = TargetValue {
+            value: value!({}),
+            metadata: value!({}),
+            secrets: Default::default(),
+        };
+        let mut state = state::RuntimeState::default();
+        let timezone = TimeZone::default();
+        let mut ctx = Context::new(&mut target, &mut state, &timezone);
+        let mut time = |name: &str, expression: SplitFn| {
+            let start = std::time::Instant::now();
+            for _ in 0..1_000_000 {
+                expression.resolve(&mut ctx).unwrap();
+            }
+            println!("{name:>11}: {:?}", start.elapsed());
+            expression.resolve(&mut ctx)
+        };
+
+        let dynamic = time("expressions", expression(&Argument::Expression));
+        let literal = time(
+            "literals",
+            expression(&|expression| Argument::new(expression, &TypeState::default())),
+        );
+        assert_eq!(literal, dynamic);
+    }
+
+    /// Resolves `split("a,b;c", [",", ";"])` a million times, building the
+    /// regex of the array pattern for every event, as split used to, and
+    /// once when compiling, as it does now. Run with
+    /// `cargo test --release bench_prepared_pattern -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_prepared_pattern() {
+        use vrl::compiler::{TargetValue, TimeZone};
+
+        let expression = |prepared: Option<Value>| SplitFn {
+            value: expr!("a,b;c"),
+            pattern: Argument::Literal(value!([",", ";"])),
+            prepared,
+            limit: Argument::Literal(value!(999_999_999)),
+            inclusive: Argument::Literal(value!(false)),
+            from_end: Argument::Literal(value!(false)),
+            case_sensitive: Argument::Literal(value!(true)),
+            captures: Argument::Literal(value!(false)),
+            max_segments: None,
+        };
+        let mut target = TargetValue {
+            value: value!({}),
+            metadata: value!({}),
+            secrets: Default::default(),
+        };
+        let mut state = state::RuntimeState::default();
+        let timezone = TimeZone::default();
+        let mut ctx = Context::new(&mut target, &mut state, &timezone);
+        let mut time = |name: &str, expression: SplitFn| {
+            let start = std::time::Instant::now();
+            for _ in 0..1_000_000 {
+                expression.resolve(&mut ctx).unwrap();
+            }
+            println!("{name:>9}: {:?}", start.elapsed());
+            expression.resolve(&mut ctx)
+        };
+
+        let per_event = time("per event", expression(None));
+        let prepared = prepare(value!([",", ";"]), true).unwrap();
+        let compiled = time("compiled", expression(Some(prepared)));
+        assert_eq!(compiled, per_event);
+    }
+}
@@ -0,0 +1,629 @@
+//! The library's entry point: VRL programs compiled against the functions the
+//! `vrl-test` binary registers, to run events through or benchmark.
+
+#[cfg(feature = "async")]
+use futures_channel::oneshot;
+#[cfg(feature = "async")]
+use futures_util::{stream, Stream, StreamExt};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+#[cfg(feature = "async")]
+use std::any::Any;
+use std::num::NonZeroUsize;
+#[cfg(feature = "async")]
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use vrl::compiler::{Function, TimeZone};
+use vrl::value::{Secrets, Value};
+
+use crate::bench::{self, BenchOptions, BenchReport};
+use crate::cli::RegistryArgs;
+use crate::closure_fn::Callable;
+use crate::error::HarnessError;
+use crate::input::{Decoding, Input};
+use crate::pipeline::{CompileFailure, Environment, Outcome, Pipeline, Resolved};
+use crate::registry::{self, Registry};
+
+/// The most events of a stream [`Harness::run_stream`] resolves at once.
+#[cfg(feature = "async")]
+const STREAM_BATCH: usize = 1024;
+
+/// One or more compiled programs, chained so that the event each program
+/// leaves is the input of the next, as with `vrl-test run -p a.vrl -p b.vrl`.
+///
+/// [`Harness::compile`] uses the defaults of the binary; configure the
+/// functions, the timezone or the initial target with [`Harness::builder`].
+pub struct Harness {
+    /// Shared with the batches of [`Harness::run_stream`] being resolved.
+    pipeline: Arc<Mutex<Pipeline>>,
+    pool: Option<ThreadPool>,
+}
+
+impl Harness {
+    /// Configures how programs are compiled and events resolved.
+    pub fn builder() -> HarnessBuilder {
+        HarnessBuilder::default()
+    }
+
+    /// Compiles a single program with the default configuration.
+    pub fn compile(source: &str) -> Result<Self, HarnessError> {
+        Self::builder().compile(source)
+    }
+
+    /// Compiles each `(name, source)` pair as a stage, in order, with the
+    /// default configuration.
+    pub fn compile_pipeline(sources: &[(String, String)]) -> Result<Self, HarnessError> {
+        Self::builder().compile_pipeline(sources)
+    }
+
+    /// Runs `event` through every program.
+    pub fn run(&mut self, event: Value) -> Result<Outcome, HarnessError> {
+        Ok(self.pipeline().resolve(event)?)
+    }
+
+    /// Runs a JSON event through every program and returns the event they
+    /// leave, as JSON; see [`Harness::run`] for the metadata and the value
+    /// of the last expression.
+    ///
+    /// ```
+    /// use serde_json::json;
+    ///
+    /// let mut harness = vrl_test::Harness::compile(r#".tags = split!(.tags, ",")"#).unwrap();
+    /// let event = harness.run_json(&json!({"tags": "a,b"})).unwrap();
+    /// assert_eq!(event, json!({"tags": ["a", "b"]}));
+    /// ```
+    pub fn run_json(
+        &mut self,
+        event: &serde_json::Value,
+    ) -> Result<serde_json::Value, HarnessError> {
+        let outcome = self.run(Value::from(event.clone()))?;
+        serde_json::to_value(outcome.target.value).map_err(HarnessError::OutputError)
+    }
+
+    /// Runs each of `events` through every program; see [`Harness::run`].
+    ///
+    /// With [`HarnessBuilder::threads`], the events are resolved in parallel,
+    /// still coming back in order.
+    pub fn run_batch(&mut self, events: &[Value]) -> Vec<Result<Outcome, HarnessError>> {
+        let results = match &self.pool {
+            Some(pool) => pool.install(|| self.pipeline().resolve_all(events)),
+            None => self.pipeline().run_batch(events),
+        };
+        outcomes(results)
+    }
+
+    /// Runs each of `events` through every program as the returned iterator
+    /// is advanced, so that a harness fits in the middle of an iterator
+    /// chain, e.g. over a `serde_json` deserializer:
+    ///
+    /// ```
+    /// use vrl::value;
+    /// use vrl::value::Value;
+    ///
+    /// let mut harness = vrl_test::Harness::compile(".n = length!(.tags)").unwrap();
+    /// let input = r#"{"tags": ["a", "b"]} {"tags": ["c"]}"#;
+    ///
+    /// let events = serde_json::Deserializer::from_str(input)
+    ///     .into_iter::<serde_json::Value>()
+    ///     .map(|event| Value::from(event.unwrap()));
+    /// let events = harness
+    ///     .transform(events)
+    ///     .map(|outcome| outcome.unwrap().target.value)
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(events[1], value!({"tags": ["c"], "n": 1}));
+    /// ```
+    pub fn transform<'a>(
+        &'a mut self,
+        events: impl IntoIterator<Item = Value> + 'a,
+    ) -> impl Iterator<Item = Result<Outcome, HarnessError>> + 'a {
+        events.into_iter().map(move |event| self.run(event))
+    }
+
+    /// Runs the events of `events` through every program as they arrive,
+    /// yielding the results in order, e.g. to embed the harness in an async
+    /// service reading events from a socket.
+    ///
+    /// The events that are ready when the stream is polled are resolved
+    /// together, as with [`Harness::run_batch`], but events are never waited
+    /// for once one is ready. They are resolved on the threads of
+    /// [`HarnessBuilder::threads`], or on rayon's global pool, so that polling
+    /// the stream never blocks the task; it is woken once the batch is done.
+    #[cfg(feature = "async")]
+    pub fn run_stream<'a>(
+        &'a mut self,
+        events: impl Stream<Item = Value> + 'a,
+    ) -> impl Stream<Item = Result<Outcome, HarnessError>> + 'a {
+        let (pipeline, pool) = (&self.pipeline, self.pool.as_ref());
+        let parallel = pool.is_some();
+        events
+            .ready_chunks(STREAM_BATCH)
+            .then(move |events| {
+                let (resolved, results) = oneshot::channel();
+                let pipeline = Arc::clone(pipeline);
+                let len = events.len();
+                let resolve = move || {
+                    // a panic on rayon's threads would abort the process
+                    let results = panic::catch_unwind(AssertUnwindSafe(|| {
+                        let mut pipeline = lock(&pipeline);
+                        match parallel {
+                            true => pipeline.resolve_all(&events),
+                            false => pipeline.run_batch(&events),
+                        }
+                    }));
+                    // the stream was dropped if no one is waiting anymore
+                    let _ = resolved.send(results.map_err(|panic| panic_message(&*panic)));
+                };
+                match pool {
+                    Some(pool) => pool.spawn(resolve),
+                    None => rayon::spawn(resolve),
+                }
+                async move {
+                    let results = results
+                        .await
+                        .unwrap_or_else(|_| Err("the batch was never resolved".to_owned()));
+                    match results {
+                        Ok(results) => outcomes(results),
+                        Err(message) => (0..len)
+                            .map(|_| Err(HarnessError::Panicked(message.clone())))
+                            .collect(),
+                    }
+                }
+            })
+            .flat_map(stream::iter)
+    }
+
+    /// Runs the events of the file at `path` through every program, reading
+    /// them in the format its extension names, as `vrl-test run` does.
+    ///
+    /// An event that can't be decoded fails with an
+    /// [`InputError`](HarnessError::InputError) in its place.
+    pub fn run_file(
+        &mut self,
+        path: &Path,
+    ) -> Result<Vec<Result<Outcome, HarnessError>>, HarnessError> {
+        let events = Input::File(path.to_owned())
+            .open(&Decoding::default())
+            .map_err(HarnessError::InputError)?;
+        Ok(events
+            .map(|event| self.run(event.map_err(HarnessError::InputError)?))
+            .collect())
+    }
+
+    /// Resolves the events of `corpus` over and over, as `vrl-test bench`
+    /// does, reporting their latency and allocations.
+    pub fn bench(&mut self, corpus: &[Value], options: &BenchOptions) -> BenchReport {
+        bench::bench(&mut self.pipeline(), corpus, options)
+    }
+
+    /// Flushes the state of the stateful functions, e.g. logging the counts
+    /// of `counter`, once no more events will be run.
+    pub fn flush(&self) -> Result<(), HarnessError> {
+        self.pipeline().flush().map_err(HarnessError::Flush)
+    }
+
+    fn pipeline(&self) -> MutexGuard<'_, Pipeline> {
+        lock(&self.pipeline)
+    }
+}
+
+/// Locks `pipeline`. A panic while it was held, e.g. in a custom function,
+/// leaves it as usable as it would be without the lock.
+fn lock(pipeline: &Mutex<Pipeline>) -> MutexGuard<'_, Pipeline> {
+    pipeline.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// What a panic was raised with, when it's a message.
+#[cfg(feature = "async")]
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => (*message).to_owned(),
+        (_, Some(message)) => message.clone(),
+        _ => "unknown panic".to_owned(),
+    }
+}
+
+/// The outcomes of resolved events, without how long each took.
+fn outcomes(results: Vec<Resolved>) -> Vec<Result<Outcome, HarnessError>> {
+    results.into_iter().map(|(result, _)| Ok(result?)).collect()
+}
+
+/// How a [`Harness`] compiles programs and resolves events, starting from
+/// what the `vrl-test` binary does without any options.
+#[derive(Default)]
+pub struct HarnessBuilder {
+    functions: Option<Vec<Box<dyn Function>>>,
+    /// Changes to the registry, applied in order once it's set up.
+    registry: Vec<Box<dyn FnOnce(Registry) -> Registry>>,
+    environment: Environment,
+    deny_warnings: bool,
+    threads: Option<NonZeroUsize>,
+}
+
+impl HarnessBuilder {
+    /// Compiles programs against exactly `functions` instead of the stdlib
+    /// with the custom functions swapped in.
+    pub fn functions(mut self, functions: Vec<Box<dyn Function>>) -> Self {
+        self.functions = Some(functions);
+        self
+    }
+
+    /// Registers a plain closure as the function `identifier`, next to the
+    /// others. The parameter kinds and the return type follow from the
+    /// closure's argument and return types; returning a `Result` makes the
+    /// function fallible:
+    ///
+    /// ```
+    /// use vrl::value;
+    ///
+    /// let mut harness = vrl_test::Harness::builder()
+    ///     .register_closure("double", |value: i64| value * 2)
+    ///     .compile(".b = double(int!(.a))")
+    ///     .unwrap();
+    /// let outcome = harness.run(value!({"a": 2})).unwrap();
+    /// assert_eq!(outcome.target.value, value!({"a": 2, "b": 4}));
+    /// ```
+    pub fn register_closure<Args>(
+        mut self,
+        identifier: &'static str,
+        closure: impl Callable<Args>,
+    ) -> Self {
+        let register = move |registry: Registry| registry.register_closure(identifier, closure);
+        self.registry.push(Box::new(register));
+        self
+    }
+
+    /// Registers `alias` as another name for the function `target`; calls
+    /// through it get a deprecation warning pointing at `target`.
+    pub fn alias(mut self, alias: impl Into<String>, target: impl Into<String>) -> Self {
+        let (alias, target) = (alias.into(), target.into());
+        self.registry.push(Box::new(move |registry: Registry| {
+            registry.alias(&alias, &target)
+        }));
+        self
+    }
+
+    /// Makes calls to the function `identifier` warn that it is deprecated,
+    /// with `notice`.
+    pub fn deprecate(mut self, identifier: impl Into<String>, notice: impl Into<String>) -> Self {
+        let (identifier, notice) = (identifier.into(), notice.into());
+        self.registry.push(Box::new(move |registry: Registry| {
+            registry.deprecate(&identifier, &notice)
+        }));
+        self
+    }
+
+    /// The timezone functions such as `format_timestamp` default to; the
+    /// local one unless set.
+    pub fn timezone(mut self, timezone: TimeZone) -> Self {
+        self.environment.timezone = timezone;
+        self
+    }
+
+    /// The metadata, e.g. `%source`, each event starts out with; an empty
+    /// object unless set.
+    pub fn metadata(mut self, metadata: Value) -> Self {
+        self.environment.metadata = metadata;
+        self
+    }
+
+    /// The secrets, read with `get_secret`, each event starts out with.
+    pub fn secrets(mut self, secrets: Secrets) -> Self {
+        self.environment.secrets = secrets;
+        self
+    }
+
+    /// Fails to compile a program with warnings, as `--deny-warnings` does.
+    pub fn deny_warnings(mut self, deny_warnings: bool) -> Self {
+        self.deny_warnings = deny_warnings;
+        self
+    }
+
+    /// Resolves the events of [`Harness::run_batch`] on this many threads,
+    /// as `--jobs` does; one resolves them on the calling thread.
+    pub fn threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Compiles a single program.
+    pub fn compile(self, source: &str) -> Result<Harness, HarnessError> {
+        self.compile_pipeline(&[("<inline>".to_owned(), source.to_owned())])
+    }
+
+    /// Compiles each `(name, source)` pair as a stage, in order. The
+    /// diagnostics of the stages that fail are returned in the error.
+    pub fn compile_pipeline(self, sources: &[(String, String)]) -> Result<Harness, HarnessError> {
+        let base = match self.functions {
+            Some(functions) => Registry::new(functions),
+            None => {
+                registry::configured(&RegistryArgs::default()).map_err(HarnessError::Registry)?
+            }
+        };
+        let functions = self
+            .registry
+            .into_iter()
+            .fold(base, |registry, change| change(registry))
+            .build()
+            .map_err(HarnessError::Registry)?;
+        let mut failed = Vec::new();
+        let compiled = Pipeline::compile_with(sources, &functions, self.deny_warnings, |stage| {
+            failed.push(stage)
+        });
+        let mut pipeline = match compiled {
+            Ok(pipeline) => pipeline,
+            Err(CompileFailure::Errors) => return Err(HarnessError::CompileFailed(failed)),
+            Err(CompileFailure::DeniedWarnings) => {
+                return Err(HarnessError::DeniedWarnings(failed))
+            }
+        };
+        pipeline.set_environment(self.environment);
+
+        let pool = match self.threads.map(NonZeroUsize::get) {
+            None | Some(1) => None,
+            Some(threads) => Some(
+                ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .map_err(HarnessError::Threads)?,
+            ),
+        };
+        Ok(Harness {
+            pipeline: Arc::new(Mutex::new(pipeline)),
+            pool,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vrl::value;
+
+    #[test]
+    fn runs_the_custom_functions() {
+        let mut harness =
+            Harness::compile(r#".b = split(string!(.a), ",", inclusive: true)"#).unwrap();
+
+        let outcome = harness.run(value!({"a": "x,y"})).unwrap();
+        assert_eq!(outcome.target.value, value!({"a": "x,y", "b": ["x,", "y"]}));
+
+        let results = harness.run_batch(&[value!({"a": "z"}), value!({})]);
+        assert_eq!(
+            results[0].as_ref().unwrap().target.value,
+            value!({"a": "z", "b": ["z"]})
+        );
+        assert!(matches!(
+            &results[1],
+            Err(HarnessError::RuntimeError(err)) if err.stage == "<inline>"
+        ));
+    }
+
+    #[test]
+    fn compile_errors() {
+        let sources = [
+            ("ok".to_owned(), ".a = 1".to_owned()),
+            ("bad".to_owned(), ".a = (".to_owned()),
+        ];
+        let Err(HarnessError::CompileFailed(failed)) = Harness::compile_pipeline(&sources) else {
+            panic!("expected a compile failure");
+        };
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].stage, "bad");
+        assert!(failed[0].diagnostics.has_errors());
+
+        let warns = "x = 1";
+        assert!(Harness::compile(warns).is_ok());
+        let Err(HarnessError::DeniedWarnings(failed)) =
+            Harness::builder().deny_warnings(true).compile(warns)
+        else {
+            panic!("expected denied warnings");
+        };
+        assert!(failed[0].to_string().contains("unused"));
+    }
+
+    #[test]
+    fn registers_closures() {
+        let mut harness = Harness::builder()
+            .functions(vec![])
+            .register_closure("shout", |value: String| format!("{value}!"))
+            .compile(r#"shout("hi")"#)
+            .unwrap();
+        assert_eq!(harness.run(value!({})).unwrap().result, value!("hi!"));
+
+        let taken = Harness::builder()
+            .register_closure("split", |value: String| value)
+            .compile(".");
+        assert!(matches!(taken, Err(HarnessError::Registry(_))));
+    }
+
+    #[test]
+    fn aliases_and_deprecations() {
+        let builder = || {
+            Harness::builder()
+                .alias("shout", String::from("upcase"))
+                .deprecate("downcase", "use lowercase")
+                .deny_warnings(true)
+        };
+        assert!(builder().compile(r#"upcase("a")"#).is_ok());
+        for source in [r#"shout("a")"#, r#"downcase("A")"#] {
+            let Err(HarnessError::DeniedWarnings(failed)) = builder().compile(source) else {
+                panic!("expected a deprecation warning for {source}");
+            };
+            assert!(failed[0].to_string().contains("is deprecated"));
+        }
+    }
+
+    #[test]
+    fn transforms_lazily() {
+        let mut harness = Harness::compile(".b = int!(.a) + 1").unwrap();
+        let mut seen = 0;
+        let events = (0..).map(|a| {
+            seen += 1;
+            value!({"a": a})
+        });
+
+        let results = harness.transform(events).take(2).collect::<Vec<_>>();
+        assert_eq!(
+            results[1].as_ref().unwrap().target.value,
+            value!({"a": 1, "b": 2})
+        );
+        assert_eq!(seen, 2);
+        assert!(harness
+            .transform([value!({})])
+            .all(|result| result.is_err()));
+    }
+
+    #[test]
+    fn runs_json() {
+        let mut harness = Harness::compile(
+            r#".n = int!(.n) + 1
+            .at = format_timestamp!(parse_timestamp!("2024-01-01T00:00:00Z", "%+"), "%F")
+            del(.gone)"#,
+        )
+        .unwrap();
+
+        let event = harness
+            .run_json(&serde_json::json!({"n": 1, "gone": null, "nested": {"f": 1.5}}))
+            .unwrap();
+        assert_eq!(
+            event,
+            serde_json::json!({"n": 2, "at": "2024-01-01", "nested": {"f": 1.5}})
+        );
+        assert!(matches!(
+            harness.run_json(&serde_json::json!({"n": "x"})),
+            Err(HarnessError::RuntimeError(_))
+        ));
+    }
+
+    #[test]
+    fn runs_files() {
+        let path =
+            std::env::temp_dir().join(format!("vrl-test-{}-harness.ndjson", std::process::id()));
+        std::fs::write(&path, "{\"a\": \"x\"}\nnope\n{}\n").unwrap();
+        let mut harness = Harness::compile(".b = upcase!(.a)").unwrap();
+
+        let results = harness.run_file(&path).unwrap();
+        assert_eq!(
+            results[0].as_ref().unwrap().target.value,
+            value!({"a": "x", "b": "X"})
+        );
+        assert!(matches!(results[1], Err(HarnessError::InputError(_))));
+        assert!(matches!(results[2], Err(HarnessError::RuntimeError(_))));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            harness.run_file(&path),
+            Err(HarnessError::InputError(_))
+        ));
+    }
+
+    #[test]
+    fn builder_configures_the_environment() {
+        let mut harness = Harness::builder()
+            .functions(vrl::stdlib::all())
+            .timezone(TimeZone::parse("Europe/Paris").unwrap())
+            .metadata(value!({"source": "test"}))
+            .threads(NonZeroUsize::new(2).unwrap())
+            .compile(
+                r#".source = %source
+                .hour = format_timestamp!(parse_timestamp!("2024-01-01 00:00", "%F %R"), "%H")"#,
+            )
+            .unwrap();
+
+        let results = harness.run_batch(&[value!({}), value!({"a": 1})]);
+        assert_eq!(
+            results[1].as_ref().unwrap().target.value,
+            value!({"a": 1, "source": "test", "hour": "23"})
+        );
+
+        // the custom functions aren't registered, so `inclusive` is unknown
+        let custom = r#"split("a,b", ",", inclusive: true)"#;
+        assert!(Harness::compile(custom).is_ok());
+        assert!(Harness::builder()
+            .functions(vrl::stdlib::all())
+            .compile(custom)
+            .is_err());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn runs_streams() {
+        let mut harness = Harness::compile(".b = int!(.a) * 2").unwrap();
+        // one event at a time, as they would come from a socket, then a burst
+        let trickle = stream::unfold(0, |a| async move {
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+            (a < 3).then(|| (value!({"a": a}), a + 1))
+        });
+        let burst = stream::iter((3..2000).map(|a| value!({"a": a})).chain([value!({})]));
+
+        let results = harness
+            .run_stream(trickle.chain(burst))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(results.len(), 2001);
+        for (a, result) in results[..2000].iter().enumerate() {
+            let a = a as i64;
+            assert_eq!(
+                result.as_ref().unwrap().target.value,
+                value!({"a": a, "b": (a * 2)})
+            );
+        }
+        assert!(matches!(results[2000], Err(HarnessError::RuntimeError(_))));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn streams_survive_panics() {
+        let mut harness = Harness::builder()
+            .register_closure("check", |value: i64| {
+                assert!(value >= 0, "negative value");
+                value
+            })
+            .compile(".checked = check(int!(.a))")
+            .unwrap();
+
+        let results = harness
+            .run_stream(stream::iter([value!({"a": (-1)})]))
+            .collect::<Vec<_>>()
+            .await;
+        assert!(
+            matches!(&results[..], [Err(HarnessError::Panicked(message))] if message == "negative value")
+        );
+        let outcome = harness.run(value!({"a": 1})).unwrap();
+        assert_eq!(outcome.target.value, value!({"a": 1, "checked": 1}));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn streams_without_blocking_the_task() {
+        let mut harness = Harness::builder()
+            .register_closure("nap", |ms: i64| {
+                std::thread::sleep(std::time::Duration::from_millis(ms as u64));
+                ms
+            })
+            .compile(".slept = nap(200)")
+            .unwrap();
+        let ticks = std::cell::Cell::new(0);
+        // ticks on the same task while the event is resolved
+        let ticker = async {
+            for _ in 0..10 {
+                tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                ticks.set(ticks.get() + 1);
+            }
+        };
+        let resolve = async {
+            let results = harness
+                .run_stream(stream::iter([value!({})]))
+                .collect::<Vec<_>>()
+                .await;
+            (results, ticks.get())
+        };
+
+        let ((results, ticked), ()) = tokio::join!(resolve, ticker);
+        assert_eq!(ticked, 10);
+        assert_eq!(
+            results[0].as_ref().unwrap().target.value,
+            value!({"slept": 200})
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use anyhow::{anyhow, Context as _, Result};
+use apache_avro::reader::datum::GenericDatumReader;
+use apache_avro::types::Value as AvroValue;
+use apache_avro::{Reader, Schema};
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::io::BufRead;
+use std::path::Path;
+use vrl::prelude::NotNan;
+use vrl::value::{ObjectMap, Value};
+
+use super::Events;
+
+/// Container files start with these four bytes, followed by the header.
+const MAGIC: &[u8] = b"Obj\x01";
+
+/// Parses a JSON Avro schema. Readers borrow their schema for as long as they
+/// yield events, so it is leaked and lives for the rest of the run.
+pub(crate) fn read_schema(path: &Path) -> Result<&'static Schema> {
+    let schema = fs::read_to_string(path)
+        .with_context(|| format!("failed to read Avro schema {}", path.display()))?;
+    let schema = Schema::parse_str(&schema)
+        .with_context(|| format!("invalid Avro schema in {}", path.display()))?;
+
+    Ok(Box::leak(Box::new(schema)))
+}
+
+/// Decodes an object container file using its embedded schema, resolved
+/// against `schema` if one is given. Inputs without the container header are
+/// read as back-to-back binary datums written with `schema`.
+pub(super) fn decode(
+    mut reader: impl BufRead + 'static,
+    name: String,
+    schema: Option<&'static Schema>,
+) -> Result<Events> {
+    let container = reader
+        .fill_buf()
+        .with_context(|| format!("failed to read {name}"))?
+        .starts_with(MAGIC);
+
+    if container {
+        let records = Reader::builder(reader)
+            .maybe_reader_schema(schema)
+            .build()
+            .with_context(|| format!("invalid Avro header in {name}"))?;
+
+        return Ok(Box::new(records.enumerate().map(move |(index, record)| {
+            record
+                .map_err(Into::into)
+                .and_then(into_value)
+                .with_context(|| format!("invalid Avro record {} in {name}", index + 1))
+        })));
+    }
+
+    let schema = schema.ok_or_else(|| {
+        anyhow!("{name} is not an Avro container file; pass --avro-schema to read raw datums")
+    })?;
+    let datums = GenericDatumReader::builder(schema).build()?;
+
+    let mut index = 0;
+    Ok(Box::new(std::iter::from_fn(move || {
+        match reader.fill_buf() {
+            Ok([]) => return None,
+            Ok(_) => {}
+            Err(err) => return Some(Err(err.into())),
+        }
+        index += 1;
+
+        Some(
+            datums
+                .read_value(&mut reader)
+                .map_err(Into::into)
+                .and_then(into_value)
+                .with_context(|| format!("invalid Avro datum {index} in {name}")),
+        )
+    })))
+}
+
+/// Converts a decoded Avro value, mapping timestamp logical types onto VRL
+/// timestamps and bytes onto VRL bytes.
+fn into_value(value: AvroValue) -> Result<Value> {
+    Ok(match value {
+        AvroValue::Null => Value::Null,
+        AvroValue::Boolean(boolean) => boolean.into(),
+        AvroValue::Int(int) | AvroValue::Date(int) | AvroValue::TimeMillis(int) => int.into(),
+        AvroValue::Long(long) | AvroValue::TimeMicros(long) => long.into(),
+        AvroValue::Float(float) => float64(float.into())?,
+        AvroValue::Double(double) => float64(double)?,
+        AvroValue::Bytes(bytes) | AvroValue::Fixed(_, bytes) => bytes.into(),
+        AvroValue::String(string) | AvroValue::Enum(_, string) => string.into(),
+        AvroValue::Union(_, value) => into_value(*value)?,
+        AvroValue::Array(items) => items
+            .into_iter()
+            .map(into_value)
+            .collect::<Result<Vec<_>>>()?
+            .into(),
+        AvroValue::Map(entries) => object(entries)?,
+        AvroValue::Record(fields) => object(fields)?,
+        AvroValue::TimestampMillis(millis) | AvroValue::LocalTimestampMillis(millis) => {
+            timestamp(DateTime::from_timestamp_millis(millis))?
+        }
+        AvroValue::TimestampMicros(micros) | AvroValue::LocalTimestampMicros(micros) => {
+            timestamp(DateTime::from_timestamp_micros(micros))?
+        }
+        AvroValue::TimestampNanos(nanos) | AvroValue::LocalTimestampNanos(nanos) => {
+            Value::Timestamp(DateTime::from_timestamp_nanos(nanos))
+        }
+        AvroValue::Decimal(decimal) => Vec::<u8>::try_from(decimal)?.into(),
+        AvroValue::BigDecimal(decimal) => decimal.to_string().into(),
+        AvroValue::Duration(duration) => Value::Object(ObjectMap::from([
+            ("months".into(), u32::from(duration.months()).into()),
+            ("days".into(), u32::from(duration.days()).into()),
+            ("millis".into(), u32::from(duration.millis()).into()),
+        ])),
+        AvroValue::Uuid(uuid) => uuid.to_string().into(),
+    })
+}
+
+fn object(entries: impl IntoIterator<Item = (String, AvroValue)>) -> Result<Value> {
+    entries
+        .into_iter()
+        .map(|(key, value)| Ok((key.into(), into_value(value)?)))
+        .collect::<Result<ObjectMap>>()
+        .map(Value::Object)
+}
+
+fn float64(float: f64) -> Result<Value> {
+    Ok(NotNan::new(float)
+        .map_err(|_| anyhow!("NaN is not a valid value"))?
+        .into())
+}
+
+fn timestamp(timestamp: Option<DateTime<Utc>>) -> Result<Value> {
+    timestamp
+        .map(Value::Timestamp)
+        .ok_or_else(|| anyhow!("timestamp out of range"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use apache_avro::types::Record;
+    use apache_avro::writer::datum::GenericDatumWriter;
+    use apache_avro::Writer;
+    use std::io::Cursor;
+    use vrl::value;
+
+    const SCHEMA: &str = r#"{
+        "type": "record",
+        "name": "event",
+        "fields": [
+            {"name": "message", "type": "string"},
+            {"name": "count", "type": ["null", "long"]},
+            {"name": "at", "type": {"type": "long", "logicalType": "timestamp-millis"}}
+        ]
+    }"#;
+
+    fn record<'a>(schema: &'a Schema, message: &str) -> Record<'a> {
+        let mut record = Record::new(schema).unwrap();
+        record.put("message", message);
+        record.put("count", AvroValue::Union(1, Box::new(AvroValue::Long(3))));
+        record.put("at", AvroValue::TimestampMillis(0));
+        record
+    }
+
+    fn collect(events: Events) -> Vec<Value> {
+        events.map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn container_file() {
+        let schema = Schema::parse_str(SCHEMA).unwrap();
+        let mut writer = Writer::new(&schema, Vec::new()).unwrap();
+        writer.append_value(record(&schema, "one")).unwrap();
+        writer.append_value(record(&schema, "two")).unwrap();
+        let bytes = writer.into_inner().unwrap();
+
+        let events = decode(Cursor::new(bytes), "test".to_owned(), None).unwrap();
+
+        let at = DateTime::from_timestamp(0, 0).unwrap();
+        assert_eq!(
+            collect(events),
+            vec![
+                value!({"message": "one", "count": 3, "at": (at)}),
+                value!({"message": "two", "count": 3, "at": (at)}),
+            ]
+        );
+    }
+
+    #[test]
+    fn raw_datums_need_schema() {
+        let schema: &'static Schema = Box::leak(Box::new(Schema::parse_str(SCHEMA).unwrap()));
+        let datums = GenericDatumWriter::builder(schema).build().unwrap();
+        let mut bytes = Vec::new();
+        datums
+            .write_value(&mut bytes, record(schema, "one"))
+            .unwrap();
+        datums
+            .write_value(&mut bytes, record(schema, "two"))
+            .unwrap();
+
+        let events = decode(Cursor::new(bytes.clone()), "test".to_owned(), Some(schema)).unwrap();
+        let messages = collect(events)
+            .into_iter()
+            .map(|event| event.get("message").cloned().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, vec![value!("one"), value!("two")]);
+
+        let err = decode(Cursor::new(bytes), "test".to_owned(), None)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("--avro-schema"));
+    }
+}
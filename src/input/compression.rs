@@ -0,0 +1,65 @@
+use flate2::bufread::MultiGzDecoder;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Extensions of compressed files, skipped when detecting the format.
+const EXTENSIONS: &[&str] = &["gz", "zst", "zstd"];
+
+/// Whether a file starting with `header` is compressed.
+pub(super) fn is_compressed(header: &[u8]) -> bool {
+    header.starts_with(GZIP_MAGIC) || header.starts_with(ZSTD_MAGIC)
+}
+
+/// Wraps `reader` in a decoder when it starts with a gzip or zstd header, and
+/// passes it through untouched otherwise.
+pub(super) fn decompress(mut reader: Box<dyn BufRead>) -> io::Result<Box<dyn BufRead>> {
+    let header = reader.fill_buf()?;
+
+    Ok(if header.starts_with(GZIP_MAGIC) {
+        Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+    } else if header.starts_with(ZSTD_MAGIC) {
+        Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?))
+    } else {
+        reader
+    })
+}
+
+/// `path` without a trailing compression extension, e.g. `events.json` for
+/// `events.json.gz`.
+pub(super) fn strip_extension(path: &Path) -> &Path {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if EXTENSIONS.contains(&ext) => Path::new(path.file_stem().unwrap_or_default()),
+        _ => path,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::{Cursor, Read, Write};
+
+    fn read(bytes: Vec<u8>) -> String {
+        let mut contents = String::new();
+        decompress(Box::new(Cursor::new(bytes)))
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        contents
+    }
+
+    #[test]
+    fn detects_compression() {
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(b"{\"a\": 1}\n").unwrap();
+        let zstd = zstd::encode_all(&b"{\"a\": 2}\n"[..], 0).unwrap();
+
+        assert_eq!(read(gzip.finish().unwrap()), "{\"a\": 1}\n");
+        assert_eq!(read(zstd), "{\"a\": 2}\n");
+        assert_eq!(read(b"{\"a\": 3}\n".to_vec()), "{\"a\": 3}\n");
+    }
+}
@@ -0,0 +1,101 @@
+use anyhow::Context as _;
+use std::io::BufRead;
+use vrl::prelude::NotNan;
+use vrl::value::{ObjectMap, Value};
+
+use super::Events;
+
+/// Decodes CSV records into events keyed by the header row.
+///
+/// Fields holding booleans, integers or floats become the matching value type,
+/// empty fields become `null` and everything else stays a string.
+pub(super) fn decode(reader: impl BufRead + 'static, name: String) -> Events {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(err) => {
+            let err = anyhow::Error::new(err).context(format!("invalid CSV header in {name}"));
+            return Box::new(std::iter::once(Err(err)));
+        }
+    };
+
+    Box::new(reader.into_records().map(move |record| {
+        let record = record.with_context(|| format!("invalid CSV record in {name}"))?;
+        let line = record.position().map_or(0, |position| position.line());
+
+        let mut event = ObjectMap::new();
+        for (index, field) in record.iter().enumerate() {
+            let header = headers
+                .get(index)
+                .with_context(|| format!("{name}:{line}: more fields than headers"))?;
+            event.insert(header.into(), infer(field));
+        }
+        Ok(Value::Object(event))
+    }))
+}
+
+/// Infers the value type of a single CSV field.
+fn infer(field: &str) -> Value {
+    if field.is_empty() {
+        return Value::Null;
+    }
+    if let Ok(boolean) = field.parse::<bool>() {
+        return Value::Boolean(boolean);
+    }
+    if let Ok(integer) = field.parse::<i64>() {
+        return Value::Integer(integer);
+    }
+    if let Some(float) = field
+        .parse::<f64>()
+        .ok()
+        .filter(|float| float.is_finite())
+        .and_then(|float| NotNan::new(float).ok())
+    {
+        return Value::Float(float);
+    }
+    Value::from(field)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use std::io::Cursor;
+    use vrl::value;
+
+    fn decode_str(input: &'static str) -> Vec<Result<Value>> {
+        decode(Cursor::new(input), "test.csv".to_owned()).collect()
+    }
+
+    #[test]
+    fn header_driven_fields() {
+        let events = decode_str("host,status,ok,ratio,note\nweb-1,200,true,0.5,\n");
+
+        assert_eq!(
+            events[0].as_ref().unwrap(),
+            &value!({
+                "host": "web-1",
+                "status": 200,
+                "ok": true,
+                "ratio": 0.5,
+                "note": null,
+            })
+        );
+    }
+
+    #[test]
+    fn non_numeric_text_stays_string() {
+        assert_eq!(infer("inf"), value!("inf"));
+        assert_eq!(infer("1e3"), value!(1000.0));
+        assert_eq!(infer("01x"), value!("01x"));
+        assert_eq!(infer("TRUE"), value!("TRUE"));
+    }
+
+    #[test]
+    fn ragged_record_is_an_error() {
+        let events = decode_str("a,b\n1,2\n1,2,3\n");
+
+        assert!(events[0].is_ok());
+        assert!(events[1].is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use anyhow::{Context as _, Result};
+use log::{info, warn};
+use std::fs::{self, File, Metadata};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use super::{Events, LineDecoder};
+
+/// How often the followed file is checked for new data once the end is reached.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Yields an event for every line appended to `path` after it is opened, like
+/// `tail -f`. The file is reopened from the start when it's replaced (log
+/// rotation) or truncated, once what's left of the old one has been read.
+/// The stream never ends on its own.
+pub(super) fn follow(path: &Path, decode_line: LineDecoder) -> Result<Events> {
+    let mut follower = Follower::open(path)?;
+    follower.reader.seek(SeekFrom::End(0))?;
+    follower.position = follower.reader.stream_position()?;
+    info!("Following {}", path.display());
+
+    let name = path.display().to_string();
+    Ok(Box::new(std::iter::from_fn(move || {
+        let line = follower.next_line();
+        Some(line.and_then(|line| {
+            decode_line(&line).with_context(|| format!("invalid event in {name}"))
+        }))
+    })))
+}
+
+struct Follower {
+    path: PathBuf,
+    reader: BufReader<File>,
+    /// Identity of the open file, used to notice it being replaced.
+    id: Option<u64>,
+    /// Bytes consumed from the open file, used to notice truncation.
+    position: u64,
+    /// The start of a line whose newline hasn't been written yet.
+    partial: String,
+    /// Whether the file was replaced or truncated, and is read to its end
+    /// before being reopened.
+    rotated: bool,
+}
+
+impl Follower {
+    fn open(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        let id = file_id(&file.metadata()?);
+
+        Ok(Self {
+            path: path.to_owned(),
+            reader: BufReader::new(file),
+            id,
+            position: 0,
+            partial: String::new(),
+            rotated: false,
+        })
+    }
+
+    /// Blocks until a complete, non-blank line is available.
+    fn next_line(&mut self) -> Result<String> {
+        loop {
+            let mut buf = String::new();
+            let read = self.reader.read_line(&mut buf)?;
+            self.position += read as u64;
+            self.partial.push_str(&buf);
+
+            if self.partial.ends_with('\n') {
+                let line = std::mem::take(&mut self.partial);
+                let line = line.trim_end_matches(['\r', '\n']);
+                if !line.trim().is_empty() {
+                    return Ok(line.to_owned());
+                }
+            } else if read == 0 && self.rotated {
+                // the old file won't get its newline, so its last line ends here
+                let line = std::mem::take(&mut self.partial);
+                *self = Self::open(&self.path)?;
+                let line = line.trim_end_matches('\r');
+                if !line.trim().is_empty() {
+                    return Ok(line.to_owned());
+                }
+            } else if read == 0 {
+                self.wait();
+            }
+        }
+    }
+
+    /// Sleeps until the next poll, noting whether the file was rotated or
+    /// truncated in the meantime.
+    fn wait(&mut self) {
+        thread::sleep(POLL_INTERVAL);
+
+        let metadata = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            // Mid-rotation: the old file is gone and the new one isn't there yet.
+            Err(err) => {
+                warn!("failed to stat {}: {err}", self.path.display());
+                return;
+            }
+        };
+
+        if file_id(&metadata) != self.id || metadata.len() < self.position {
+            info!("{} was rotated, reopening", self.path.display());
+            self.rotated = true;
+        }
+    }
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(_: &Metadata) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::input::text;
+    use std::io::Write;
+    use vrl::value;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("vrl-test-{}-{name}", std::process::id()))
+    }
+
+    fn append(path: &Path, contents: &str) {
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+    }
+
+    #[test]
+    fn follows_appended_lines() {
+        let path = temp_path("follow.log");
+        let _ = fs::remove_file(&path);
+        append(&path, "before\n");
+
+        let mut events = follow(&path, text::decode_line).unwrap();
+        append(&path, "first\n\nsec");
+        append(&path, "ond\n");
+
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            value!({"message": "first"})
+        );
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            value!({"message": "second"})
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reopens_rotated_file() {
+        let path = temp_path("rotate.log");
+        let rotated = temp_path("rotate.log.1");
+        let _ = fs::remove_file(&path);
+        append(&path, "before\n");
+
+        let mut events = follow(&path, text::decode_line).unwrap();
+        fs::rename(&path, &rotated).unwrap();
+        append(&path, "after\n");
+
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            value!({"message": "after"})
+        );
+
+        fs::write(&path, "new\n").unwrap();
+
+        assert_eq!(events.next().unwrap().unwrap(), value!({"message": "new"}));
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&rotated).unwrap();
+    }
+
+    #[test]
+    fn reads_rotated_file_to_the_end() {
+        let path = temp_path("drain.log");
+        let rotated = temp_path("drain.log.1");
+        let _ = fs::remove_file(&path);
+        append(&path, "before\n");
+
+        let mut events = follow(&path, text::decode_line).unwrap();
+        append(&path, "first\n");
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            value!({"message": "first"})
+        );
+
+        // written while the follower waits at the end of the old file
+        let writer = {
+            let (path, rotated) = (path.clone(), rotated.clone());
+            thread::spawn(move || {
+                thread::sleep(POLL_INTERVAL / 5);
+                append(&path, "late\nunterminated");
+                fs::rename(&path, &rotated).unwrap();
+                append(&path, "after\n");
+            })
+        };
+
+        for message in ["late", "unterminated", "after"] {
+            assert_eq!(
+                events.next().unwrap().unwrap(),
+                value!({"message": message})
+            );
+        }
+        writer.join().unwrap();
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&rotated).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+use anyhow::{anyhow, Context as _};
+use std::io::BufRead;
+use vrl::value::Value;
+
+use super::Events;
+
+/// Decodes a JSON document holding either a single event or an array of events.
+pub(super) fn decode(mut reader: impl BufRead, name: String) -> Events {
+    let mut document = String::new();
+    if let Err(err) = reader
+        .read_to_string(&mut document)
+        .with_context(|| format!("failed to read {name}"))
+    {
+        return Box::new(std::iter::once(Err(err)));
+    }
+
+    match serde_json::from_str::<serde_json::Value>(&document) {
+        Ok(serde_json::Value::Array(events)) => {
+            Box::new(events.into_iter().map(|event| Ok(Value::from(event))))
+        }
+        Ok(event) => Box::new(std::iter::once(Ok(Value::from(event)))),
+        Err(err) => {
+            let message = anyhow!(
+                "invalid JSON in {name} at line {}, column {}",
+                err.line(),
+                err.column()
+            );
+            Box::new(std::iter::once(Err(
+                anyhow::Error::new(err).context(message)
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Result;
+    use std::io::Cursor;
+    use vrl::value;
+
+    fn decode_str(input: &'static str) -> Vec<Result<Value>> {
+        decode(Cursor::new(input), "test.json".to_owned()).collect()
+    }
+
+    #[test]
+    fn single_object() {
+        let events = decode_str(r#"{"a": 1}"#);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].as_ref().unwrap(), &value!({"a": 1}));
+    }
+
+    #[test]
+    fn array_of_objects() {
+        let events = decode_str("[\n  {\"a\": 1},\n  {\"b\": 2}\n]");
+
+        let events = events.into_iter().collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(events, vec![value!({"a": 1}), value!({"b": 2})]);
+    }
+
+    #[test]
+    fn invalid_document_reports_position() {
+        let events = decode_str("[\n  {\"a\": 1},\n  {\"b\" 2}\n]");
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].as_ref().unwrap_err().to_string(),
+            "invalid JSON in test.json at line 3, column 8"
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::{anyhow, Context as _, Result};
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, Consumer};
+use rdkafka::Message;
+
+use super::{Events, LineDecoder};
+use crate::cli::KafkaArgs;
+
+/// Subscribes to `topic` and yields one event per message, decoding payloads
+/// with `decode_payload`.
+pub(super) fn consume(
+    args: &KafkaArgs,
+    topic: &str,
+    decode_payload: LineDecoder,
+) -> Result<Events> {
+    let consumer: BaseConsumer = ClientConfig::new()
+        .set("bootstrap.servers", &args.kafka_brokers)
+        .set("group.id", &args.kafka_group)
+        .set("auto.offset.reset", args.kafka_offset_reset.as_str())
+        .set("enable.auto.commit", "true")
+        .create()
+        .context("failed to create Kafka consumer")?;
+    consumer
+        .subscribe(&[topic])
+        .with_context(|| format!("failed to subscribe to Kafka topic {topic}"))?;
+
+    let events = std::iter::from_fn(move || {
+        let message = match consumer.poll(None)? {
+            Ok(message) => message,
+            Err(err) => return Some(Err(anyhow::Error::new(err).context("failed to consume"))),
+        };
+        let position = format!(
+            "{}/{}@{}",
+            message.topic(),
+            message.partition(),
+            message.offset()
+        );
+
+        let event = match message.payload_view::<str>() {
+            Some(Ok(payload)) => decode_payload(payload),
+            Some(Err(err)) => Err(err.into()),
+            None => Err(anyhow!("empty payload")),
+        };
+        Some(event.with_context(|| format!("invalid event at {position}")))
+    });
+
+    Ok(match args.kafka_max_messages {
+        Some(max) => Box::new(events.take(max)),
+        None => Box::new(events),
+    })
+}
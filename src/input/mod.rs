@@ -0,0 +1,462 @@
+//! Readers turning input files into events.
+
+#[cfg(feature = "avro")]
+pub(crate) mod avro;
+mod compression;
+mod csv;
+mod follow;
+mod json;
+#[cfg(feature = "kafka")]
+mod kafka;
+mod mmap;
+mod ndjson;
+#[cfg(feature = "parquet")]
+mod parquet;
+pub(crate) mod protobuf;
+mod socket;
+mod syslog;
+mod text;
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use clap::ValueEnum;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use vrl::value::Value;
+
+pub(crate) use socket::ListenAddr;
+
+/// A stream of decoded events. Decoding failures are yielded in place of the
+/// event they belong to, so one bad record doesn't end the stream.
+pub(crate) type Events = Box<dyn Iterator<Item = Result<Value>>>;
+
+/// The events read from one input, with the input's name for reporting.
+pub(crate) struct Source {
+    pub(crate) name: String,
+    pub(crate) events: Events,
+    /// Whether events keep arriving over time, e.g. on a socket or from a
+    /// command piped to stdin, so that each is resolved as soon as it does.
+    pub(crate) stream: bool,
+}
+
+/// Decodes a single line, message or datagram into an event.
+type LineDecoder = fn(&str) -> Result<Value>;
+
+/// How events are encoded in an input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum InputFormat {
+    /// One JSON event per line
+    Ndjson,
+    /// A single JSON event, or an array of events
+    Json,
+    /// Comma-separated values with a header row naming the fields
+    Csv,
+    /// One RFC 3164 or RFC 5424 syslog message per line
+    Syslog,
+    /// Raw lines, each stored in the `message` field
+    Text,
+    /// Avro object container file, or raw datums with `--avro-schema`
+    #[cfg(feature = "avro")]
+    Avro,
+    /// Length-delimited protobuf messages, typed by `--proto-message`
+    Protobuf,
+    /// Parquet file, one event per row
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+/// The format to decode inputs with, along with any format-specific settings.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Decoding {
+    /// Overrides detection from the file extension.
+    pub(crate) format: Option<InputFormat>,
+    /// Writer schema for raw datums, or reader schema for container files.
+    #[cfg(feature = "avro")]
+    pub(crate) avro_schema: Option<&'static apache_avro::Schema>,
+    /// Message type of protobuf inputs.
+    pub(crate) protobuf: Option<prost_reflect::MessageDescriptor>,
+    /// Top-level columns to load from Parquet inputs; all when empty.
+    #[cfg(feature = "parquet")]
+    pub(crate) parquet_columns: Vec<String>,
+}
+
+impl InputFormat {
+    /// Guesses the format from the file extension, ignoring any compression
+    /// extension and defaulting to NDJSON.
+    pub(crate) fn detect(input: &Input) -> Self {
+        let Input::File(path) = input else {
+            return InputFormat::Ndjson;
+        };
+
+        match compression::strip_extension(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+        {
+            Some("json") => InputFormat::Json,
+            Some("csv") => InputFormat::Csv,
+            #[cfg(feature = "avro")]
+            Some("avro") => InputFormat::Avro,
+            Some("pb" | "binpb") => InputFormat::Protobuf,
+            #[cfg(feature = "parquet")]
+            Some("parquet") => InputFormat::Parquet,
+            _ => InputFormat::Ndjson,
+        }
+    }
+
+    /// The decoder for formats holding one event per line or message.
+    fn line_decoder(self) -> Result<LineDecoder> {
+        match self {
+            InputFormat::Ndjson | InputFormat::Json => Ok(ndjson::decode_line),
+            InputFormat::Syslog => Ok(syslog::decode_line),
+            InputFormat::Text => Ok(text::decode_line),
+            InputFormat::Csv => Err(anyhow!("CSV input requires a file or stdin")),
+            #[cfg(feature = "avro")]
+            InputFormat::Avro => Err(anyhow!("Avro input requires a file or stdin")),
+            InputFormat::Protobuf => Err(anyhow!("protobuf input requires a file or stdin")),
+            #[cfg(feature = "parquet")]
+            InputFormat::Parquet => Err(anyhow!("Parquet input requires a file or stdin")),
+        }
+    }
+}
+
+/// A file, or stdin, that events are read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input {
+    /// Maps `-` to stdin and anything else to a file path.
+    pub(crate) fn from_path(path: &Path) -> Self {
+        match path.as_os_str() == "-" {
+            true => Input::Stdin,
+            false => Input::File(path.to_owned()),
+        }
+    }
+
+    /// Like [`Input::from_path`], but expands a directory into the files
+    /// below it and a glob pattern such as `logs/**/*.json` into the files it
+    /// matches, both in sorted order.
+    pub(crate) fn expand(path: &Path) -> Result<Vec<Self>> {
+        if path.is_dir() {
+            let mut files = Vec::new();
+            walk(path, &mut files).with_context(|| format!("failed to list {}", path.display()))?;
+            files.sort();
+            return Ok(files.into_iter().map(Input::File).collect());
+        }
+
+        let pattern = path.to_string_lossy();
+        if path.exists() || !pattern.contains(['*', '?', '[']) {
+            return Ok(vec![Input::from_path(path)]);
+        }
+
+        let mut files = glob::glob(&pattern)
+            .with_context(|| format!("invalid glob pattern {pattern}"))?
+            .filter(|entry| entry.as_ref().map_or(true, |path| path.is_file()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if files.is_empty() {
+            bail!("no files match {pattern}");
+        }
+        files.sort();
+        Ok(files.into_iter().map(Input::File).collect())
+    }
+
+    /// Opens the input and returns the events decoded from it, detecting the
+    /// format from the file name unless one is given.
+    pub(crate) fn open(&self, decoding: &Decoding) -> Result<Events> {
+        let name = self.to_string();
+        Ok(
+            match decoding.format.unwrap_or_else(|| InputFormat::detect(self)) {
+                InputFormat::Ndjson => self.lines(name, ndjson::decode_line)?,
+                InputFormat::Json => json::decode(self.reader()?, name),
+                InputFormat::Csv => csv::decode(self.reader()?, name),
+                InputFormat::Syslog => self.lines(name, syslog::decode_line)?,
+                InputFormat::Text => self.lines(name, text::decode_line)?,
+                #[cfg(feature = "avro")]
+                InputFormat::Avro => avro::decode(self.reader()?, name, decoding.avro_schema)?,
+                InputFormat::Protobuf => {
+                    let descriptor = decoding.protobuf.clone().ok_or_else(|| {
+                        anyhow!("protobuf input {self} requires --proto-desc and --proto-message")
+                    })?;
+                    protobuf::decode(self.reader()?, name, descriptor)
+                }
+                #[cfg(feature = "parquet")]
+                InputFormat::Parquet => parquet::decode(self, &decoding.parquet_columns)?,
+            },
+        )
+    }
+
+    /// Whether events may arrive over time: stdin, unless it is redirected
+    /// from a file.
+    fn is_stream(&self) -> bool {
+        matches!(self, Input::Stdin) && !stdin_is_file()
+    }
+
+    /// Decodes each line of the input, from a memory map of the file when it
+    /// can be mapped.
+    fn lines(&self, name: String, decode_line: LineDecoder) -> Result<Events> {
+        if let Input::File(path) = self {
+            if let Some(map) = mmap::map(path) {
+                return Ok(mmap::decode_lines(map, name, decode_line));
+            }
+        }
+        Ok(decode_lines(self.reader()?, name, decode_line))
+    }
+
+    /// Opens the input for reading, decompressing gzip and zstd on the fly.
+    fn reader(&self) -> Result<Box<dyn BufRead>> {
+        let reader: Box<dyn BufRead> = match self {
+            Input::Stdin => Box::new(io::stdin().lock()),
+            Input::File(path) => Box::new(BufReader::new(
+                File::open(path).with_context(|| format!("failed to open input {self}"))?,
+            )),
+        };
+
+        compression::decompress(reader).with_context(|| format!("failed to read input {self}"))
+    }
+}
+
+/// Collects every file below `dir`, recursively.
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Input::Stdin => f.write_str("<stdin>"),
+            Input::File(path) => path.display().fmt(f),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn stdin_is_file() -> bool {
+    use std::os::fd::AsFd;
+    io::stdin()
+        .as_fd()
+        .try_clone_to_owned()
+        .and_then(|fd| File::from(fd).metadata())
+        .is_ok_and(|metadata| metadata.is_file())
+}
+
+#[cfg(not(unix))]
+fn stdin_is_file() -> bool {
+    false
+}
+
+/// Decodes each non-blank line of `reader` into an event, tagging failures
+/// with the line they came from.
+fn decode_lines(reader: impl BufRead + 'static, name: String, decode_line: LineDecoder) -> Events {
+    Box::new(
+        reader
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+            .map(move |(index, line)| {
+                line.map_err(Into::into)
+                    .and_then(|line| decode_line(&line))
+                    .with_context(|| format!("invalid event at {name}:{}", index + 1))
+            }),
+    )
+}
+
+/// Opens every input in order, after the events given on the command line.
+pub(crate) fn open_all(
+    literal: Vec<Value>,
+    inputs: &[Input],
+    decoding: &Decoding,
+) -> Result<Vec<Source>> {
+    let mut sources = Vec::new();
+    if !literal.is_empty() {
+        sources.push(Source {
+            name: "<arguments>".to_owned(),
+            events: Box::new(literal.into_iter().map(Ok)),
+            stream: false,
+        });
+    }
+
+    for input in inputs {
+        sources.push(Source {
+            name: input.to_string(),
+            events: input.open(decoding)?,
+            stream: input.is_stream(),
+        });
+    }
+    Ok(sources)
+}
+
+/// Follows the lines written to a file, decoded according to its extension
+/// unless a line-based format is given.
+pub(crate) fn follow(path: &Path, format: Option<InputFormat>) -> Result<Source> {
+    let input = Input::File(path.to_owned());
+    let decode_line = format
+        .unwrap_or_else(|| InputFormat::detect(&input))
+        .line_decoder()?;
+
+    Ok(Source {
+        events: follow::follow(path, decode_line)?,
+        name: input.to_string(),
+        stream: true,
+    })
+}
+
+/// Receives events on a TCP or UDP listener, decoded as NDJSON unless another
+/// line-based format is given.
+pub(crate) fn listen(addr: &ListenAddr, format: Option<InputFormat>) -> Result<Source> {
+    let decode_line = format.unwrap_or(InputFormat::Ndjson).line_decoder()?;
+
+    Ok(Source {
+        name: addr.to_string(),
+        events: socket::listen(addr, decode_line)?,
+        stream: true,
+    })
+}
+
+/// Consumes events from a Kafka topic, decoded as JSON unless another
+/// line-based format is given.
+#[cfg(feature = "kafka")]
+pub(crate) fn kafka(
+    args: &crate::cli::KafkaArgs,
+    topic: &str,
+    format: Option<InputFormat>,
+) -> Result<Source> {
+    let decode_line = format.unwrap_or(InputFormat::Json).line_decoder()?;
+
+    Ok(Source {
+        name: format!("kafka:{topic}"),
+        events: kafka::consume(args, topic, decode_line)?,
+        stream: true,
+    })
+}
+
+/// How the events of one input fared, reported when a run reads several.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct InputStats {
+    pub(crate) name: String,
+    /// Events run through the program, including those that failed.
+    pub(crate) events: usize,
+    /// Records that could not be decoded.
+    pub(crate) invalid: usize,
+    /// Events the program failed on.
+    pub(crate) failed: usize,
+}
+
+impl InputStats {
+    pub(crate) fn new(name: String) -> Self {
+        Self {
+            name,
+            events: 0,
+            invalid: 0,
+            failed: 0,
+        }
+    }
+
+    /// Renders one aligned row per input.
+    pub(crate) fn render(stats: &[InputStats]) -> String {
+        let width = stats
+            .iter()
+            .map(|stats| stats.name.len())
+            .chain([5])
+            .max()
+            .unwrap_or_default();
+
+        let mut out = format!(
+            "{:width$}  {:>8}  {:>8}  {:>8}",
+            "input", "events", "invalid", "failed"
+        );
+        for stats in stats {
+            out.push_str(&format!(
+                "\n{:width$}  {:>8}  {:>8}  {:>8}",
+                stats.name, stats.events, stats.invalid, stats.failed
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_stats() {
+        let stats = [
+            InputStats {
+                events: 3,
+                failed: 1,
+                ..InputStats::new("logs/a.json".to_owned())
+            },
+            InputStats {
+                invalid: 2,
+                ..InputStats::new("b.json".to_owned())
+            },
+        ];
+
+        assert_eq!(
+            InputStats::render(&stats),
+            "\
+input          events   invalid    failed
+logs/a.json         3         0         1
+b.json              0         2         0"
+        );
+    }
+
+    #[test]
+    fn detect_format() {
+        let detect = |path: &str| InputFormat::detect(&Input::from_path(Path::new(path)));
+
+        assert_eq!(detect("-"), InputFormat::Ndjson);
+        assert_eq!(detect("events.json"), InputFormat::Json);
+        assert_eq!(detect("events.csv"), InputFormat::Csv);
+        assert_eq!(detect("events.ndjson"), InputFormat::Ndjson);
+        assert_eq!(detect("events.log"), InputFormat::Ndjson);
+        assert_eq!(detect("events.csv.gz"), InputFormat::Csv);
+        assert_eq!(detect("logs/events.json.zst"), InputFormat::Json);
+    }
+
+    #[test]
+    fn expand_inputs() {
+        let dir = std::env::temp_dir().join(format!("vrl-test-{}-expand", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("b")).unwrap();
+        for file in ["c.json", "a.json", "b/d.json", "b/e.csv"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        let files = |inputs: Vec<Input>| {
+            inputs
+                .into_iter()
+                .map(|input| match input {
+                    Input::File(path) => path.strip_prefix(&dir).unwrap().to_owned(),
+                    Input::Stdin => PathBuf::from("-"),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            files(Input::expand(&dir).unwrap()),
+            ["a.json", "b/d.json", "b/e.csv", "c.json"].map(PathBuf::from)
+        );
+        assert_eq!(
+            files(Input::expand(&dir.join("**/*.json")).unwrap()),
+            ["a.json", "b/d.json", "c.json"].map(PathBuf::from)
+        );
+        assert_eq!(
+            files(Input::expand(Path::new("-")).unwrap()),
+            [PathBuf::from("-")]
+        );
+        assert!(Input::expand(&dir.join("*.ndjson")).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,37 @@
+use anyhow::Result;
+use vrl::value::Value;
+
+/// Decodes a single line holding one JSON event.
+pub(super) fn decode_line(line: &str) -> Result<Value> {
+    Ok(serde_json::from_str::<serde_json::Value>(line)?.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::decode_lines;
+    use super::*;
+    use std::io::Cursor;
+    use vrl::value;
+
+    fn decode_str(input: &'static str) -> Vec<Result<Value>> {
+        decode_lines(Cursor::new(input), "test".to_owned(), decode_line).collect()
+    }
+
+    #[test]
+    fn one_event_per_line() {
+        let events = decode_str("{\"a\": 1}\n\n{\"b\": [true]}\n");
+
+        let events = events.into_iter().collect::<Result<Vec<_>>>().unwrap();
+        assert_eq!(events, vec![value!({"a": 1}), value!({"b": [true]})]);
+    }
+
+    #[test]
+    fn invalid_line_reports_position() {
+        let events = decode_str("{\"a\": 1}\nnope\n{\"c\": 3}");
+
+        assert_eq!(events.len(), 3);
+        let error = events[1].as_ref().unwrap_err();
+        assert_eq!(error.to_string(), "invalid event at test:2");
+        assert!(events[2].is_ok());
+    }
+}
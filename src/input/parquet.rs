@@ -0,0 +1,202 @@
+use anyhow::{anyhow, bail, Context as _, Result};
+use bytes::Bytes;
+use chrono::DateTime;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::{reader::RowIter, Field, Row};
+use parquet::schema::types::Type;
+use std::fs::File;
+use std::io::{self, Read};
+use vrl::prelude::NotNan;
+use vrl::value::{ObjectMap, Value};
+
+use super::{Events, Input};
+
+/// Reads `input` row by row, loading only the top-level `columns` when any
+/// are given. Stdin is buffered in memory, since Parquet's footer is read
+/// before any rows.
+pub(super) fn decode(input: &Input, columns: &[String]) -> Result<Events> {
+    let context = || format!("invalid Parquet file {input}");
+    let reader: Box<dyn FileReader> = match input {
+        Input::Stdin => {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            Box::new(SerializedFileReader::new(Bytes::from(bytes)).with_context(context)?)
+        }
+        Input::File(path) => {
+            let file = File::open(path).with_context(|| format!("failed to open input {input}"))?;
+            Box::new(SerializedFileReader::new(file).with_context(context)?)
+        }
+    };
+
+    rows(reader, input.to_string(), columns)
+}
+
+fn rows(reader: Box<dyn FileReader>, name: String, columns: &[String]) -> Result<Events> {
+    let projection = project(reader.metadata().file_metadata().schema(), columns)
+        .with_context(|| format!("cannot project {name}"))?;
+    let rows = RowIter::from_file_into(reader).project(projection)?;
+
+    Ok(Box::new(rows.enumerate().map(move |(index, row)| {
+        row.map_err(Into::into)
+            .and_then(row_to_value)
+            .with_context(|| format!("invalid Parquet row {} in {name}", index + 1))
+    })))
+}
+
+/// Narrows `schema` to the named top-level columns, or `None` to read them all.
+fn project(schema: &Type, columns: &[String]) -> Result<Option<Type>> {
+    if columns.is_empty() {
+        return Ok(None);
+    }
+
+    let fields = schema.get_fields();
+    if let Some(missing) = columns
+        .iter()
+        .find(|column| !fields.iter().any(|field| field.name() == *column))
+    {
+        let available = fields.iter().map(|field| field.name()).collect::<Vec<_>>();
+        bail!("no column {missing} (available: {})", available.join(", "));
+    }
+
+    let fields = fields
+        .iter()
+        .filter(|field| columns.iter().any(|column| field.name() == column))
+        .cloned()
+        .collect();
+    Ok(Some(
+        Type::group_type_builder(schema.name())
+            .with_fields(fields)
+            .build()?,
+    ))
+}
+
+fn row_to_value(row: Row) -> Result<Value> {
+    row.into_columns()
+        .into_iter()
+        .map(|(name, field)| Ok((name.into(), field_to_value(field)?)))
+        .collect::<Result<ObjectMap>>()
+        .map(Value::Object)
+}
+
+/// Converts a Parquet field, mapping timestamps onto VRL timestamps and
+/// decimals onto their string representation to keep their precision.
+fn field_to_value(field: Field) -> Result<Value> {
+    Ok(match field {
+        Field::Null => Value::Null,
+        Field::Bool(boolean) => boolean.into(),
+        Field::Byte(int) => i64::from(int).into(),
+        Field::Short(int) => i64::from(int).into(),
+        Field::Int(int) | Field::Date(int) | Field::TimeMillis(int) => int.into(),
+        Field::Long(int) | Field::TimeMicros(int) => int.into(),
+        Field::UByte(int) => i64::from(int).into(),
+        Field::UShort(int) => i64::from(int).into(),
+        Field::UInt(int) => i64::from(int).into(),
+        Field::ULong(int) => i64::try_from(int)?.into(),
+        Field::Float16(float) => float64(float.into())?,
+        Field::Float(float) => float64(float.into())?,
+        Field::Double(float) => float64(float)?,
+        Field::Decimal(_) => field.to_string().into(),
+        Field::Str(string) => string.into(),
+        Field::Bytes(bytes) => bytes.data().to_vec().into(),
+        Field::TimestampMillis(millis) => DateTime::from_timestamp_millis(millis)
+            .map(Value::Timestamp)
+            .ok_or_else(|| anyhow!("timestamp out of range"))?,
+        Field::TimestampMicros(micros) => DateTime::from_timestamp_micros(micros)
+            .map(Value::Timestamp)
+            .ok_or_else(|| anyhow!("timestamp out of range"))?,
+        Field::Group(row) => row_to_value(row)?,
+        Field::ListInternal(list) => list
+            .elements()
+            .iter()
+            .cloned()
+            .map(field_to_value)
+            .collect::<Result<Vec<_>>>()?
+            .into(),
+        Field::MapInternal(map) => map
+            .entries()
+            .iter()
+            .map(|(key, value)| {
+                let key = match key {
+                    Field::Str(key) => key.clone(),
+                    key => key.to_string(),
+                };
+                Ok((key.into(), field_to_value(value.clone())?))
+            })
+            .collect::<Result<ObjectMap>>()
+            .map(Value::Object)?,
+    })
+}
+
+fn float64(float: f64) -> Result<Value> {
+    Ok(NotNan::new(float)
+        .map_err(|_| anyhow!("NaN is not a valid value"))?
+        .into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use std::sync::Arc;
+    use vrl::value;
+
+    fn file() -> Box<dyn FileReader> {
+        let schema = "message event { REQUIRED INT64 id; REQUIRED BINARY name (UTF8); }";
+        let schema = Arc::new(parse_message_type(schema).unwrap());
+        let mut bytes = Vec::new();
+        let mut writer = SerializedFileWriter::new(&mut bytes, schema, Default::default()).unwrap();
+
+        let mut row_group = writer.next_row_group().unwrap();
+        let mut ids = row_group.next_column().unwrap().unwrap();
+        ids.typed::<Int64Type>()
+            .write_batch(&[1, 2], None, None)
+            .unwrap();
+        ids.close().unwrap();
+        let mut names = row_group.next_column().unwrap().unwrap();
+        names
+            .typed::<ByteArrayType>()
+            .write_batch(&[ByteArray::from("a"), ByteArray::from("b")], None, None)
+            .unwrap();
+        names.close().unwrap();
+        row_group.close().unwrap();
+        writer.close().unwrap();
+
+        Box::new(SerializedFileReader::new(Bytes::from(bytes)).unwrap())
+    }
+
+    fn collect(events: Events) -> Vec<Value> {
+        events.map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn all_columns() {
+        let events = rows(file(), "test".to_owned(), &[]).unwrap();
+
+        assert_eq!(
+            collect(events),
+            vec![
+                value!({"id": 1, "name": "a"}),
+                value!({"id": 2, "name": "b"})
+            ]
+        );
+    }
+
+    #[test]
+    fn projected_columns() {
+        let events = rows(file(), "test".to_owned(), &["name".to_owned()]).unwrap();
+        assert_eq!(
+            collect(events),
+            vec![value!({"name": "a"}), value!({"name": "b"})]
+        );
+
+        let err = rows(file(), "test".to_owned(), &["missing".to_owned()])
+            .err()
+            .unwrap();
+        assert_eq!(
+            format!("{err:#}"),
+            "cannot project test: no column missing (available: id, name)"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::{anyhow, bail, Context as _, Result};
+use prost_reflect::{DynamicMessage, MessageDescriptor};
+use std::io::{BufRead, Read};
+use std::path::Path;
+use vrl::value::Value;
+
+use super::Events;
+
+/// Looks up `message` in a descriptor set compiled with
+/// `protoc --descriptor_set_out`.
+pub(crate) fn read_descriptor(path: &Path, message: &str) -> Result<MessageDescriptor> {
+    vrl::protobuf::get_message_descriptor(path, message).map_err(|err| anyhow!(err))
+}
+
+/// Decodes a stream of varint length-prefixed messages, as written by
+/// protobuf's `writeDelimitedTo`. A framing error ends the stream, since the
+/// position of the next message is unknown.
+pub(super) fn decode(
+    mut reader: impl BufRead + 'static,
+    name: String,
+    descriptor: MessageDescriptor,
+) -> Events {
+    let mut index = 0;
+    let mut framing_failed = false;
+    Box::new(std::iter::from_fn(move || {
+        if framing_failed {
+            return None;
+        }
+        index += 1;
+
+        let event = match read_delimited(&mut reader) {
+            Ok(Some(message)) => decode_message(&descriptor, &message),
+            Ok(None) => return None,
+            Err(err) => {
+                framing_failed = true;
+                Err(err)
+            }
+        };
+        Some(event.with_context(|| format!("invalid protobuf message {index} in {name}")))
+    }))
+}
+
+fn decode_message(descriptor: &MessageDescriptor, message: &[u8]) -> Result<Value> {
+    let message = DynamicMessage::decode(descriptor.clone(), message)?;
+    vrl::protobuf::proto_to_value(&prost_reflect::Value::Message(message), None)
+        .map_err(|err| anyhow!(err))
+}
+
+/// Reads the next length-prefixed message, or `None` at a clean end of input.
+fn read_delimited(reader: &mut impl BufRead) -> Result<Option<Vec<u8>>> {
+    if reader.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+
+    let mut len = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0];
+        reader
+            .read_exact(&mut byte)
+            .context("truncated message length")?;
+        len |= u64::from(byte[0] & 0x7f) << shift;
+
+        if byte[0] & 0x80 == 0 {
+            let mut message = Vec::new();
+            reader.take(len).read_to_end(&mut message)?;
+            if message.len() as u64 != len {
+                bail!("truncated message of {len} bytes");
+            }
+            return Ok(Some(message));
+        }
+    }
+
+    bail!("message length is not a valid varint")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use prost_reflect::prost_types::field_descriptor_proto::{Label, Type};
+    use prost_reflect::prost_types::{
+        DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+    };
+    use prost_reflect::DescriptorPool;
+    use std::io::Cursor;
+    use vrl::value;
+
+    fn descriptor() -> MessageDescriptor {
+        let field = |name: &str, number, kind: Type| FieldDescriptorProto {
+            name: Some(name.to_owned()),
+            number: Some(number),
+            label: Some(Label::Optional.into()),
+            r#type: Some(kind.into()),
+            ..Default::default()
+        };
+        let file = FileDescriptorProto {
+            name: Some("event.proto".to_owned()),
+            package: Some("test".to_owned()),
+            message_type: vec![DescriptorProto {
+                name: Some("Event".to_owned()),
+                field: vec![
+                    field("message", 1, Type::String),
+                    field("count", 2, Type::Int64),
+                ],
+                ..Default::default()
+            }],
+            syntax: Some("proto3".to_owned()),
+            ..Default::default()
+        };
+
+        DescriptorPool::from_file_descriptor_set(FileDescriptorSet { file: vec![file] })
+            .unwrap()
+            .get_message_by_name("test.Event")
+            .unwrap()
+    }
+
+    #[test]
+    fn length_delimited_messages() {
+        // {message: "hi", count: 3}, then {message: "yo"}
+        let bytes = b"\x06\x0a\x02hi\x10\x03\x04\x0a\x02yo".to_vec();
+
+        let events = decode(Cursor::new(bytes), "test".to_owned(), descriptor())
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            events,
+            vec![
+                value!({"message": "hi", "count": 3}),
+                value!({"message": "yo"})
+            ]
+        );
+    }
+
+    #[test]
+    fn truncated_message() {
+        let mut events = decode(
+            Cursor::new(b"\x06\x0a\x02".to_vec()),
+            "test".to_owned(),
+            descriptor(),
+        );
+
+        let err = events.next().unwrap().unwrap_err();
+        assert!(events.next().is_none());
+        assert_eq!(
+            format!("{err:#}"),
+            "invalid protobuf message 1 in test: truncated message of 6 bytes"
+        );
+    }
+}
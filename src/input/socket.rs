@@ -0,0 +1,182 @@
+use anyhow::{anyhow, Context as _, Result};
+use log::{info, warn};
+use std::fmt;
+use std::io::{BufRead, BufReader};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::str::FromStr;
+use std::sync::mpsc::{self, SyncSender};
+use std::thread;
+
+use super::{Events, LineDecoder};
+
+/// Number of received lines buffered ahead of the program.
+const BUFFER: usize = 1024;
+
+/// Largest datagram accepted by the UDP listener.
+const MAX_DATAGRAM: usize = 65_535;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// A `tcp://host:port` or `udp://host:port` address to listen on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ListenAddr {
+    pub(crate) protocol: Protocol,
+    pub(crate) addr: SocketAddr,
+}
+
+impl FromStr for ListenAddr {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (protocol, addr) = match s.split_once("://") {
+            Some(("tcp", addr)) => (Protocol::Tcp, addr),
+            Some(("udp", addr)) => (Protocol::Udp, addr),
+            _ => return Err(anyhow!("expected tcp://<host:port> or udp://<host:port>")),
+        };
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow!("{addr} did not resolve to an address"))?;
+
+        Ok(Self { protocol, addr })
+    }
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.protocol {
+            Protocol::Tcp => write!(f, "tcp://{}", self.addr),
+            Protocol::Udp => write!(f, "udp://{}", self.addr),
+        }
+    }
+}
+
+/// Binds `addr` and yields an event for every received line (TCP) or
+/// datagram (UDP). The stream never ends on its own.
+pub(super) fn listen(addr: &ListenAddr, decode_line: LineDecoder) -> Result<Events> {
+    let bind = || format!("failed to bind {addr}");
+    let events = match addr.protocol {
+        Protocol::Tcp => tcp(
+            TcpListener::bind(addr.addr).with_context(bind)?,
+            decode_line,
+        ),
+        Protocol::Udp => udp(UdpSocket::bind(addr.addr).with_context(bind)?, decode_line),
+    };
+    info!("Listening on {addr}");
+
+    Ok(events)
+}
+
+fn tcp(listener: TcpListener, decode_line: LineDecoder) -> Events {
+    let (tx, rx) = mpsc::sync_channel(BUFFER);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let tx = tx.clone();
+                    thread::spawn(move || read_lines(stream, tx));
+                }
+                Err(err) => warn!("failed to accept connection: {err}"),
+            }
+        }
+    });
+
+    decode_received(rx, decode_line)
+}
+
+fn read_lines(stream: TcpStream, tx: SyncSender<(String, String)>) {
+    let peer = stream
+        .peer_addr()
+        .map_or_else(|_| "unknown peer".to_owned(), |peer| peer.to_string());
+
+    for line in BufReader::new(stream).lines() {
+        match line {
+            Ok(line) if line.trim().is_empty() => {}
+            Ok(line) => {
+                if tx.send((peer.clone(), line)).is_err() {
+                    return;
+                }
+            }
+            Err(err) => return warn!("connection from {peer} failed: {err}"),
+        }
+    }
+}
+
+fn udp(socket: UdpSocket, decode_line: LineDecoder) -> Events {
+    let (tx, rx) = mpsc::sync_channel(BUFFER);
+
+    thread::spawn(move || {
+        let mut buf = vec![0; MAX_DATAGRAM];
+        loop {
+            match socket.recv_from(&mut buf) {
+                Ok((len, peer)) => {
+                    let datagram = String::from_utf8_lossy(&buf[..len]);
+                    let datagram = datagram.trim_end_matches(['\r', '\n']).to_owned();
+                    if tx.send((peer.to_string(), datagram)).is_err() {
+                        return;
+                    }
+                }
+                Err(err) => warn!("failed to receive datagram: {err}"),
+            }
+        }
+    });
+
+    decode_received(rx, decode_line)
+}
+
+fn decode_received(rx: mpsc::Receiver<(String, String)>, decode_line: LineDecoder) -> Events {
+    Box::new(rx.into_iter().map(move |(peer, line)| {
+        decode_line(&line).with_context(|| format!("invalid event from {peer}"))
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::input::{ndjson, text};
+    use std::io::Write;
+    use vrl::value;
+
+    #[test]
+    fn parse_listen_addr() {
+        let addr = "udp://127.0.0.1:5140".parse::<ListenAddr>().unwrap();
+
+        assert_eq!(addr.protocol, Protocol::Udp);
+        assert_eq!(addr.to_string(), "udp://127.0.0.1:5140");
+        assert!("127.0.0.1:5140".parse::<ListenAddr>().is_err());
+        assert!("http://127.0.0.1:80".parse::<ListenAddr>().is_err());
+    }
+
+    #[test]
+    fn tcp_lines() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut events = tcp(listener, ndjson::decode_line);
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(b"{\"a\": 1}\n\n{\"a\": 2}\n").unwrap();
+
+        assert_eq!(events.next().unwrap().unwrap(), value!({"a": 1}));
+        assert_eq!(events.next().unwrap().unwrap(), value!({"a": 2}));
+    }
+
+    #[test]
+    fn udp_datagrams() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let mut events = udp(socket, text::decode_line);
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client.send_to(b"hello\n", addr).unwrap();
+
+        assert_eq!(
+            events.next().unwrap().unwrap(),
+            value!({"message": "hello"})
+        );
+    }
+}
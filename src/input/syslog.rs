@@ -0,0 +1,116 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Utc};
+use syslog_loose::{IncompleteDate, Message, ProcId, Protocol, Variant};
+use vrl::value::{ObjectMap, Value};
+
+/// Decodes a single RFC 3164 or RFC 5424 syslog line, laid out the same way
+/// as the stdlib `parse_syslog` function.
+pub(super) fn decode_line(line: &str) -> Result<Value> {
+    let message = syslog_loose::parse_message_with_year_exact(line, resolve_year, Variant::Either)
+        .map_err(|err| anyhow!("invalid syslog message: {err}"))?;
+    Ok(message_to_value(message))
+}
+
+/// RFC 3164 timestamps have no year: assume the current one, unless a
+/// December message is read in January.
+fn resolve_year((month, _date, _hour, _min, _sec): IncompleteDate) -> i32 {
+    let now = Utc::now();
+    if now.month() == 1 && month == 12 {
+        now.year() - 1
+    } else {
+        now.year()
+    }
+}
+
+fn message_to_value(message: Message<&str>) -> Value {
+    let mut event = ObjectMap::new();
+
+    event.insert("message".into(), message.msg.into());
+    if let Some(hostname) = message.hostname {
+        event.insert("hostname".into(), hostname.into());
+    }
+    if let Some(severity) = message.severity {
+        event.insert("severity".into(), severity.as_str().into());
+    }
+    if let Some(facility) = message.facility {
+        event.insert("facility".into(), facility.as_str().into());
+    }
+    if let Protocol::RFC5424(version) = message.protocol {
+        event.insert("version".into(), version.into());
+    }
+    if let Some(appname) = message.appname {
+        event.insert("appname".into(), appname.into());
+    }
+    if let Some(msgid) = message.msgid {
+        event.insert("msgid".into(), msgid.into());
+    }
+    if let Some(timestamp) = message.timestamp {
+        let timestamp: DateTime<Utc> = timestamp.into();
+        event.insert("timestamp".into(), timestamp.into());
+    }
+    if let Some(procid) = message.procid {
+        let procid = match procid {
+            ProcId::PID(pid) => pid.into(),
+            ProcId::Name(name) => name.into(),
+        };
+        event.insert("procid".into(), procid);
+    }
+    for element in message.structured_data {
+        let params = element
+            .params()
+            .map(|(name, value)| ((*name).into(), (*value).into()))
+            .collect::<ObjectMap>();
+        event.insert(element.id.into(), params.into());
+    }
+
+    Value::Object(event)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+    use vrl::value;
+
+    #[test]
+    fn rfc5424() {
+        let event = decode_line(
+            r#"<13>1 2020-03-13T20:45:38.119Z web-1 app 2426 ID931 [origin@1 ip="10.0.0.1"] hello"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            event,
+            value!({
+                "message": "hello",
+                "hostname": "web-1",
+                "severity": "notice",
+                "facility": "user",
+                "version": 1,
+                "appname": "app",
+                "msgid": "ID931",
+                "procid": 2426,
+                "timestamp": (Utc.with_ymd_and_hms(2020, 3, 13, 20, 45, 38).unwrap()
+                    + chrono::Duration::milliseconds(119)),
+                "origin@1": {"ip": "10.0.0.1"},
+            })
+        );
+    }
+
+    #[test]
+    fn rfc3164() {
+        let event = decode_line("<34>Oct 11 22:14:15 mymachine su[77]: 'su root' failed").unwrap();
+
+        assert_eq!(event.get("message"), Some(&value!("'su root' failed")));
+        assert_eq!(event.get("hostname"), Some(&value!("mymachine")));
+        assert_eq!(event.get("severity"), Some(&value!("crit")));
+        assert_eq!(event.get("facility"), Some(&value!("auth")));
+        assert_eq!(event.get("appname"), Some(&value!("su")));
+        assert_eq!(event.get("procid"), Some(&value!(77)));
+    }
+
+    #[test]
+    fn invalid_message() {
+        assert!(decode_line("not syslog at all").is_err());
+    }
+}
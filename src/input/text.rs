@@ -0,0 +1,10 @@
+use anyhow::Result;
+use vrl::value::{ObjectMap, Value};
+
+/// Wraps a raw line in an event under the `message` field.
+pub(super) fn decode_line(line: &str) -> Result<Value> {
+    Ok(Value::Object(ObjectMap::from([(
+        "message".into(),
+        line.into(),
+    )])))
+}
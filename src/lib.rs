@@ -0,0 +1,47 @@
+//! Test harness for VRL programs and custom functions.
+//!
+//! [`Harness`] compiles programs against the VRL stdlib with this crate's
+//! custom functions swapped in, and runs events through them or benchmarks
+//! them; the `vrl-test` binary is a command line over the same code, see
+//! [`commands`]. The custom functions themselves are exported from
+//! [`functions`], to register with any VRL program.
+//!
+//! ```
+//! use vrl::value;
+//!
+//! let mut harness = vrl_test::Harness::compile(r#".b = split(string!(.a), ",")"#).unwrap();
+//! let outcome = harness.run(value!({"a": "x,y"})).unwrap();
+//! assert_eq!(outcome.target.value, value!({"a": "x,y", "b": ["x", "y"]}));
+//! ```
+
+#[macro_use]
+mod macros;
+
+mod alloc;
+mod arena;
+mod bench;
+mod cli;
+pub mod closure_fn;
+pub mod commands;
+mod compile_memo;
+mod describe;
+mod error;
+pub mod functions;
+mod harness;
+mod input;
+mod output;
+mod pipeline;
+mod plugin;
+mod profile;
+mod program;
+mod registry;
+mod repl;
+mod state;
+mod timing;
+mod watch;
+
+pub use alloc::CountingAllocator;
+pub use bench::{BenchFormat, BenchOptions, BenchReport};
+pub use error::HarnessError;
+pub use harness::{Harness, HarnessBuilder};
+pub use pipeline::{Outcome, StageDiagnostics, StageError};
@@ -0,0 +1,108 @@
+/// Defines a VRL function from its identifier, parameters, a closure and a
+/// `TypeDef`, generating both the `Function` and the `FunctionExpression`
+/// implementations.
+///
+/// Parameters are listed as `keyword: kind`; giving a default (`=> value`)
+/// makes one optional. The closure receives every argument resolved to a
+/// `Value`, in the order the parameters are listed, and returns a `Resolved`.
+/// Literal arguments are resolved once when compiling, see
+/// `functions::argument`.
+///
+/// Functions that need more than that, such as `split`, which prepares its
+/// pattern when compiling, has an optional argument without a default and a
+/// type depending on its arguments, implement both traits by hand.
+///
+/// ```ignore
+/// vrl_fn! {
+///     /// Repeats a string.
+///     pub struct Repeat => RepeatFn {
+///         identifier: "repeat",
+///         parameters: {
+///             value: kind::BYTES,
+///             times: kind::INTEGER => 2,
+///         },
+///         examples: [],
+///         type_def: TypeDef::bytes().fallible(),
+///         resolve: |value: Value, times: Value| {
+///             Ok(value.try_bytes_utf8_lossy()?.repeat(times.try_integer()? as usize).into())
+///         },
+///     }
+/// }
+/// ```
+macro_rules! vrl_fn {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $function:ident => $expression:ident {
+            identifier: $identifier:literal,
+            parameters: { $($keyword:ident: $kind:expr $(=> $default:expr)?),* $(,)? },
+            examples: [$($example:expr),* $(,)?],
+            type_def: $type_def:expr,
+            resolve: $resolve:expr $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug)]
+        $vis struct $function;
+
+        impl Function for $function {
+            fn identifier(&self) -> &'static str {
+                $identifier
+            }
+
+            fn parameters(&self) -> &'static [Parameter] {
+                &[$(Parameter {
+                    keyword: stringify!($keyword),
+                    kind: $kind,
+                    required: vrl_fn!(@required $($default)?),
+                }),*]
+            }
+
+            fn examples(&self) -> &'static [Example] {
+                &[$($example),*]
+            }
+
+            fn compile(
+                &self,
+                state: &state::TypeState,
+                _ctx: &mut FunctionCompileContext,
+                arguments: ArgumentList,
+            ) -> Compiled {
+                Ok($expression {
+                    $($keyword: $crate::functions::argument::Argument::new(
+                        vrl_fn!(@argument arguments, $keyword $(, $default)?),
+                        state,
+                    )),*
+                }
+                .as_expr())
+            }
+        }
+
+        #[derive(Debug, Clone)]
+        $vis struct $expression {
+            $($keyword: $crate::functions::argument::Argument),*
+        }
+
+        impl FunctionExpression for $expression {
+            fn resolve(&self, ctx: &mut Context) -> Resolved {
+                $(let $keyword = self.$keyword.resolve(ctx)?;)*
+                ($resolve)($($keyword),*)
+            }
+
+            fn type_def(&self, _: &state::TypeState) -> TypeDef {
+                $type_def
+            }
+        }
+    };
+
+    (@required) => { true };
+    (@required $default:expr) => { false };
+
+    (@argument $arguments:ident, $keyword:ident) => {
+        $arguments.required(stringify!($keyword))
+    };
+    (@argument $arguments:ident, $keyword:ident, $default:expr) => {
+        $arguments
+            .optional(stringify!($keyword))
+            .unwrap_or_else(|| expr!($default))
+    };
+}
@@ -0,0 +1,153 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value as Json;
+use std::io::Write;
+use vrl::value::Value;
+
+use super::Sink;
+
+/// Writes events as CSV rows under a header row.
+///
+/// Columns are dot-separated field paths such as `http.status`; an array
+/// element is selected by its index. Without explicit columns, the flattened
+/// fields of the first event are used, and fields that later events add are
+/// left out.
+pub(super) struct CsvWriter<W: Write> {
+    writer: csv::Writer<W>,
+    columns: Vec<String>,
+    header: bool,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub(super) fn new(writer: W, columns: &[String]) -> Self {
+        Self {
+            writer: csv::Writer::from_writer(writer),
+            columns: columns.to_vec(),
+            header: false,
+        }
+    }
+}
+
+impl<W: Write> Sink for CsvWriter<W> {
+    fn send(&mut self, event: &Value) -> Result<()> {
+        let event = serde_json::to_value(event)?;
+        if !self.header {
+            if self.columns.is_empty() {
+                self.columns = flatten(&event).into_iter().map(|(name, _)| name).collect();
+            }
+            self.writer.write_record(&self.columns)?;
+            self.header = true;
+        }
+
+        let record = self
+            .columns
+            .iter()
+            .map(|column| cell(lookup(&event, column)));
+        Ok(self.writer.write_record(record)?)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Writes the flattened fields of `value` as a single headerless record, for
+/// sinks that encode events one at a time.
+pub(super) fn write_record(writer: impl Write, value: &impl Serialize) -> Result<()> {
+    let value = serde_json::to_value(value)?;
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(
+        flatten(&value)
+            .into_iter()
+            .map(|(_, field)| cell(Some(field))),
+    )?;
+    Ok(writer.flush()?)
+}
+
+/// Lists the leaves of `value` with their dot-separated paths; arrays and
+/// empty objects are kept whole.
+fn flatten(value: &Json) -> Vec<(String, &Json)> {
+    fn walk<'a>(prefix: String, value: &'a Json, fields: &mut Vec<(String, &'a Json)>) {
+        match value {
+            Json::Object(object) if !object.is_empty() => {
+                for (key, value) in object {
+                    let path = match prefix.is_empty() {
+                        true => key.clone(),
+                        false => format!("{prefix}.{key}"),
+                    };
+                    walk(path, value, fields);
+                }
+            }
+            _ => fields.push((prefix, value)),
+        }
+    }
+
+    let mut fields = Vec::new();
+    walk(String::new(), value, &mut fields);
+    fields
+}
+
+fn lookup<'a>(value: &'a Json, column: &str) -> Option<&'a Json> {
+    if column.is_empty() {
+        return Some(value);
+    }
+    column
+        .split('.')
+        .try_fold(value, |value, segment| match value {
+            Json::Object(object) => object.get(segment),
+            Json::Array(array) => array.get(segment.parse::<usize>().ok()?),
+            _ => None,
+        })
+}
+
+/// Renders a field: strings as-is, missing fields and nulls as empty cells,
+/// and arrays and objects as compact JSON.
+fn cell(value: Option<&Json>) -> String {
+    match value {
+        None | Some(Json::Null) => String::new(),
+        Some(Json::String(string)) => string.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vrl::value;
+
+    fn write(columns: &[&str], events: &[Value]) -> String {
+        let columns = columns
+            .iter()
+            .map(|column| column.to_string())
+            .collect::<Vec<_>>();
+        let mut writer = CsvWriter::new(Vec::new(), &columns);
+        for event in events {
+            writer.send(event).unwrap();
+        }
+        writer.flush().unwrap();
+        String::from_utf8(writer.writer.into_inner().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn selected_columns() {
+        let events = [
+            value!({"host": "web-1", "http": {"status": 200}, "tags": ["a", "b"]}),
+            value!({"host": "web, 2", "tags": []}),
+        ];
+
+        assert_eq!(
+            write(&["host", "http.status", "tags.1", "tags"], &events),
+            "host,http.status,tags.1,tags\nweb-1,200,b,\"[\"\"a\"\",\"\"b\"\"]\"\n\"web, 2\",,,[]\n"
+        );
+    }
+
+    #[test]
+    fn columns_from_first_event() {
+        let events = [
+            value!({"a": 1, "b": {"c": true, "d": null}}),
+            value!({"a": 2, "e": "ignored"}),
+        ];
+
+        assert_eq!(write(&[], &events), "a,b.c,b.d\n1,true,\n2,,\n");
+    }
+}
@@ -0,0 +1,206 @@
+use anyhow::{anyhow, Context as _, Result};
+use log::warn;
+use std::thread;
+use std::time::Duration;
+use ureq::Agent;
+use vrl::value::Value;
+
+use super::Sink;
+use crate::cli::HttpOutputArgs;
+
+/// Delay before the first retry, doubled for each one after it.
+const BACKOFF: Duration = Duration::from_millis(250);
+
+/// POSTs events to a URL in batches, each sent as a JSON array.
+///
+/// Events are encoded into the body of the next request as they're sent, so
+/// that a pending batch holds its JSON rather than a tree of values, where
+/// every event has its own copy of the keys.
+pub(super) struct HttpSink {
+    agent: Agent,
+    url: String,
+    /// The pending events as a JSON array, without the closing `]`.
+    body: Vec<u8>,
+    events: usize,
+    batch_size: usize,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl HttpSink {
+    pub(super) fn new(args: &HttpOutputArgs, url: &str) -> Self {
+        let agent = Agent::config_builder()
+            .http_status_as_error(true)
+            .build()
+            .into();
+
+        Self {
+            agent,
+            url: url.to_owned(),
+            body: Vec::new(),
+            events: 0,
+            batch_size: args.http_batch_size.max(1),
+            retries: args.http_retries,
+            backoff: BACKOFF,
+        }
+    }
+
+    /// Sends the pending batch, retrying server errors, rate limiting and
+    /// connection failures with exponential backoff.
+    fn post(&mut self) -> Result<()> {
+        if self.events == 0 {
+            return Ok(());
+        }
+        self.body.push(b']');
+        let result = self.send_body();
+        // keeps the batch open if it couldn't be sent
+        self.body.pop();
+        result?;
+
+        self.body.clear();
+        self.events = 0;
+        Ok(())
+    }
+
+    fn send_body(&self) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            let result = self
+                .agent
+                .post(&self.url)
+                .content_type("application/json")
+                .send(&self.body[..]);
+
+            match result {
+                Ok(_) => return Ok(()),
+                Err(err) if attempt < self.retries && retryable(&err) => {
+                    let delay = self.backoff * 2u32.pow(attempt);
+                    warn!("POST to {} failed ({err}), retrying in {delay:?}", self.url);
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                Err(err) => {
+                    return Err(anyhow!(err)).with_context(|| {
+                        format!("failed to POST {} events to {}", self.events, self.url)
+                    })
+                }
+            }
+        }
+    }
+}
+
+fn retryable(err: &ureq::Error) -> bool {
+    match err {
+        ureq::Error::StatusCode(status) => *status == 429 || *status >= 500,
+        ureq::Error::Io(_) | ureq::Error::Timeout(_) | ureq::Error::ConnectionFailed => true,
+        _ => false,
+    }
+}
+
+impl Sink for HttpSink {
+    fn send(&mut self, event: &Value) -> Result<()> {
+        // an event that fails to encode is left out of the batch entirely
+        let len = self.body.len();
+        self.body.push(if self.events == 0 { b'[' } else { b',' });
+        if let Err(err) = serde_json::to_writer(&mut self.body, event) {
+            self.body.truncate(len);
+            return Err(err.into());
+        }
+        self.events += 1;
+        if self.events >= self.batch_size {
+            self.post()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.post()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use vrl::value;
+
+    /// Answers one request per status in `statuses`, sending each request
+    /// body it receives down the returned channel.
+    fn serve(statuses: &'static [u16]) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/ingest", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                tx.send(String::from_utf8(body).unwrap()).unwrap();
+
+                let response = format!(
+                    "HTTP/1.1 {status} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                );
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (url, rx)
+    }
+
+    fn sink(url: &str, batch_size: usize, retries: u32) -> HttpSink {
+        let args = HttpOutputArgs {
+            http_url: Some(url.to_owned()),
+            http_batch_size: batch_size,
+            http_retries: retries,
+        };
+        HttpSink {
+            backoff: Duration::from_millis(1),
+            ..HttpSink::new(&args, url)
+        }
+    }
+
+    #[test]
+    fn batches_and_retries() {
+        let (url, bodies) = serve(&[503, 200, 200]);
+        let mut sink = sink(&url, 2, 1);
+
+        sink.send(&value!({"n": 1})).unwrap();
+        sink.send(&value!({"n": 2})).unwrap();
+        sink.send(&value!({"n": 3})).unwrap();
+        sink.flush().unwrap();
+
+        let bodies = bodies.try_iter().collect::<Vec<_>>();
+        assert_eq!(
+            bodies,
+            [
+                r#"[{"n":1},{"n":2}]"#,
+                r#"[{"n":1},{"n":2}]"#,
+                r#"[{"n":3}]"#
+            ]
+        );
+    }
+
+    #[test]
+    fn client_errors_are_not_retried() {
+        let (url, _) = serve(&[400]);
+        let mut sink = sink(&url, 1, 3);
+
+        let err = sink.send(&value!({"n": 1})).unwrap_err();
+        assert!(format!("{err:#}").contains("failed to POST 1 events"));
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{bail, Context as _, Result};
+use log::warn;
+use rdkafka::config::ClientConfig;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use rdkafka::producer::{BaseProducer, BaseRecord, DeliveryResult, Producer, ProducerContext};
+use rdkafka::ClientContext;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use vrl::path::OwnedValuePath;
+use vrl::value::Value;
+
+use super::{OutputFormat, Sink};
+use crate::cli::KafkaOutputArgs;
+
+/// How long `flush` waits for outstanding messages to be acknowledged.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Publishes each event to a Kafka topic, keyed by one of its fields.
+pub(super) struct KafkaSink {
+    producer: BaseProducer<Delivery>,
+    topic: String,
+    key_field: Option<OwnedValuePath>,
+    format: OutputFormat,
+}
+
+impl KafkaSink {
+    pub(super) fn create(
+        args: &KafkaOutputArgs,
+        topic: &str,
+        format: OutputFormat,
+    ) -> Result<Self> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", &args.kafka_output_brokers)
+            .create_with_context(Delivery::default())
+            .context("failed to create Kafka producer")?;
+
+        Ok(Self {
+            producer,
+            topic: topic.to_owned(),
+            key_field: args.kafka_key_field.clone(),
+            format,
+        })
+    }
+}
+
+impl Sink for KafkaSink {
+    fn send(&mut self, event: &Value) -> Result<()> {
+        let payload = self.format.to_string(event)?;
+        let key = self
+            .key_field
+            .as_ref()
+            .and_then(|field| event.get(field))
+            .map(key_bytes);
+
+        let mut record = BaseRecord::to(&self.topic).payload(&payload);
+        if let Some(key) = &key {
+            record = record.key(key);
+        }
+
+        loop {
+            match self.producer.send(record) {
+                Ok(()) => break,
+                // Wait for deliveries to make room in librdkafka's queue.
+                Err((KafkaError::MessageProduction(RDKafkaErrorCode::QueueFull), rejected)) => {
+                    record = rejected;
+                    self.producer.poll(Duration::from_millis(100));
+                }
+                Err((err, _)) => return Err(err).context("failed to produce Kafka message"),
+            }
+        }
+
+        self.producer.poll(Duration::ZERO);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.producer
+            .flush(FLUSH_TIMEOUT)
+            .context("failed to flush Kafka producer")?;
+
+        match self.producer.context().failed.swap(0, Ordering::Relaxed) {
+            0 => Ok(()),
+            failed => bail!("{failed} Kafka messages could not be delivered"),
+        }
+    }
+}
+
+/// Uses strings as they are, and the JSON encoding of any other value.
+fn key_bytes(key: &Value) -> Vec<u8> {
+    match key {
+        Value::Bytes(bytes) => bytes.to_vec(),
+        key => serde_json::to_vec(key).unwrap_or_default(),
+    }
+}
+
+/// Counts messages the brokers rejected, so `flush` can report them.
+#[derive(Default)]
+struct Delivery {
+    failed: AtomicUsize,
+}
+
+impl ClientContext for Delivery {}
+
+impl ProducerContext for Delivery {
+    type DeliveryOpaque = ();
+
+    fn delivery(&self, result: &DeliveryResult<'_>, _: ()) {
+        if let Err((err, _)) = result {
+            warn!("failed to deliver Kafka message: {err}");
+            self.failed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vrl::value;
+
+    #[test]
+    fn keys() {
+        assert_eq!(key_bytes(&value!("user-1")), b"user-1");
+        assert_eq!(key_bytes(&value!(42)), b"42");
+        assert_eq!(key_bytes(&value!({"id": 1})), b"{\"id\":1}");
+    }
+}
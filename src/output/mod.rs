@@ -0,0 +1,375 @@
+//! Sinks writing transformed events out.
+
+mod csv;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "kafka")]
+mod kafka;
+mod rotate;
+mod template;
+
+use anyhow::{bail, Context as _, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::json;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use vrl::compiler::runtime::Terminate;
+use vrl::value::{ObjectMap, Value};
+
+use crate::cli::OutputArgs;
+use crate::pipeline::{Outcome, StageError};
+
+pub(crate) use rotate::Rotation;
+pub(crate) use template::Template;
+
+/// How events, and errors and JSON reports when selected explicitly, are
+/// encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum OutputFormat {
+    /// One JSON document per line
+    #[default]
+    JsonCompact,
+    /// Indented JSON, for reading in a terminal
+    JsonPretty,
+    /// A stream of YAML documents, each starting with `---`
+    Yaml,
+    /// One TOML document per event, separated by blank lines; events must be
+    /// objects, and null fields are left out
+    Toml,
+    /// CSV rows under a header row holding the `--columns`
+    Csv,
+}
+
+impl OutputFormat {
+    /// Writes `value` as a single document, followed by a newline.
+    pub(crate) fn write(self, mut writer: impl Write, value: &impl Serialize) -> Result<()> {
+        match self {
+            OutputFormat::JsonCompact => serde_json::to_writer(&mut writer, value)?,
+            OutputFormat::JsonPretty => serde_json::to_writer_pretty(&mut writer, value)?,
+            OutputFormat::Yaml => {
+                writer.write_all(b"---\n")?;
+                return Ok(serde_yaml::to_writer(writer, value)?);
+            }
+            OutputFormat::Toml => {
+                let document = toml::to_string(value).context("event cannot be written as TOML")?;
+                writer.write_all(document.as_bytes())?;
+            }
+            OutputFormat::Csv => return csv::write_record(writer, value),
+        }
+        Ok(writer.write_all(b"\n")?)
+    }
+
+    /// Encodes `value` as a single document, without the trailing newline.
+    pub(crate) fn to_string(self, value: &impl Serialize) -> Result<String> {
+        let mut out = Vec::new();
+        self.write(&mut out, value)?;
+        let mut out = String::from_utf8(out)?;
+        out.truncate(out.trim_end().len());
+        Ok(out)
+    }
+}
+
+/// Which part of a pipeline outcome is written out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Emit {
+    /// The transformed event
+    #[default]
+    Event,
+    /// The value the program returned
+    Result,
+    /// An object holding the transformed `event`, the returned `result` and
+    /// the event's `metadata`
+    All,
+}
+
+impl Emit {
+    pub(crate) fn select(self, outcome: Outcome) -> Value {
+        match self {
+            Emit::Event => outcome.target.value,
+            Emit::Result => outcome.result,
+            Emit::All => Value::Object(ObjectMap::from([
+                ("event".into(), outcome.target.value),
+                ("result".into(), outcome.result),
+                ("metadata".into(), outcome.target.metadata),
+            ])),
+        }
+    }
+}
+
+/// What was being done with an event when it failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Failure {
+    Input,
+    Resolve,
+    Output,
+}
+
+impl Failure {
+    fn as_str(self) -> &'static str {
+        match self {
+            Failure::Input => "input",
+            Failure::Resolve => "resolve",
+            Failure::Output => "output",
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Failure::Input => "reading input",
+            Failure::Resolve => "resolving event",
+            Failure::Output => "writing output",
+        }
+    }
+}
+
+/// Reports a failure on stderr: as `{"error": ..., "message": ...}` in the
+/// output format when one was selected, or as plain text.
+pub(crate) fn report_error(format: Option<OutputFormat>, failure: Failure, message: impl Display) {
+    let report = format.and_then(|format| {
+        let report = json!({
+            "error": failure.as_str(),
+            "message": message.to_string(),
+        });
+        format.to_string(&report).ok()
+    });
+
+    match report {
+        Some(report) => eprintln!("{report}"),
+        None => eprintln!("Error {}: {message}", failure.describe()),
+    }
+}
+
+/// Records events the program failed on, with the error, in `--errors`.
+pub(crate) struct DeadLetters {
+    writer: Writer<BufWriter<File>>,
+}
+
+impl DeadLetters {
+    /// Creates the `--errors` file, if one was given.
+    pub(crate) fn open(args: &OutputArgs) -> Result<Option<Self>> {
+        let Some(path) = &args.errors else {
+            return Ok(None);
+        };
+        let file = File::create(path)
+            .with_context(|| format!("failed to create error output {}", path.display()))?;
+        let format = args.output_format.unwrap_or_default();
+
+        Ok(Some(Self {
+            writer: Writer::new(BufWriter::new(file), format),
+        }))
+    }
+
+    /// Writes `{"event": ..., "stage": ..., "error": ..., "aborted": ...}` for
+    /// the original `event`.
+    pub(crate) fn send(&mut self, event: Value, error: &StageError) -> Result<()> {
+        let (aborted, message) = match &error.error {
+            Terminate::Abort(error) => (true, error.to_string()),
+            Terminate::Error(error) => (false, error.to_string()),
+        };
+
+        self.writer.send(&Value::Object(ObjectMap::from([
+            ("event".into(), event),
+            ("stage".into(), error.stage.as_str().into()),
+            ("error".into(), message.into()),
+            ("aborted".into(), aborted.into()),
+        ])))
+    }
+
+    pub(crate) fn flush(&mut self) -> Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// A destination for transformed events.
+pub(crate) trait Sink {
+    /// Writes one transformed event.
+    fn send(&mut self, event: &Value) -> Result<()>;
+
+    /// Writes out anything still buffered; called once the inputs run dry.
+    fn flush(&mut self) -> Result<()>;
+}
+
+/// Opens the sink selected on the command line: an HTTP endpoint, a Kafka
+/// topic, `--output`, or stdout.
+pub(crate) fn open(args: &OutputArgs) -> Result<Box<dyn Sink>> {
+    let format = args.output_format.unwrap_or_default();
+    let rotation = args.rotation();
+
+    #[cfg(feature = "http")]
+    if let Some(url) = &args.http.http_url {
+        return Ok(Box::new(http::HttpSink::new(&args.http, url)));
+    }
+
+    #[cfg(feature = "kafka")]
+    if let Some(topic) = &args.kafka.kafka_output_topic {
+        return Ok(Box::new(kafka::KafkaSink::create(
+            &args.kafka,
+            topic,
+            format,
+        )?));
+    }
+
+    if format == OutputFormat::Csv && rotation != Rotation::default() {
+        bail!("CSV output cannot be rotated");
+    }
+
+    if let Some(template) = &args.template {
+        if rotation != Rotation::default() {
+            bail!("template output cannot be rotated");
+        }
+        return Ok(match &args.output {
+            Some(path) => {
+                let file = File::create(path)
+                    .with_context(|| format!("failed to create output {}", path.display()))?;
+                Box::new(template::TemplateWriter::new(
+                    BufWriter::new(file),
+                    template.clone(),
+                ))
+            }
+            None => Box::new(template::TemplateWriter::new(
+                io::stdout().lock(),
+                template.clone(),
+            )),
+        });
+    }
+
+    Ok(match &args.output {
+        Some(path) if format == OutputFormat::Csv => {
+            let file = File::create(path)
+                .with_context(|| format!("failed to create output {}", path.display()))?;
+            Box::new(csv::CsvWriter::new(BufWriter::new(file), &args.columns))
+        }
+        Some(path) if rotation != Rotation::default() => {
+            Box::new(rotate::RotatingFile::create(path, format, rotation)?)
+        }
+        Some(path) => {
+            let file = File::create(path)
+                .with_context(|| format!("failed to create output {}", path.display()))?;
+            Box::new(Writer::new(BufWriter::new(file), format))
+        }
+        None if format == OutputFormat::Csv => {
+            Box::new(csv::CsvWriter::new(io::stdout().lock(), &args.columns))
+        }
+        None => Box::new(Writer::new(io::stdout().lock(), format)),
+    })
+}
+
+/// Writes each event as a document in the output format.
+pub(crate) struct Writer<W> {
+    writer: W,
+    format: OutputFormat,
+}
+
+impl<W: Write> Writer<W> {
+    pub(crate) fn new(writer: W, format: OutputFormat) -> Self {
+        Self { writer, format }
+    }
+}
+
+impl<W: Write> Sink for Writer<W> {
+    fn send(&mut self, event: &Value) -> Result<()> {
+        self.format.write(&mut self.writer, event)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vrl::value;
+
+    #[test]
+    fn emit() {
+        let outcome = || Outcome {
+            target: crate::program::new_target(value!({"a": 1})),
+            result: value!(true),
+        };
+
+        assert_eq!(Emit::Event.select(outcome()), value!({"a": 1}));
+        assert_eq!(Emit::Result.select(outcome()), value!(true));
+        assert_eq!(
+            Emit::All.select(outcome()),
+            value!({"event": {"a": 1}, "result": true, "metadata": {}})
+        );
+    }
+
+    #[test]
+    fn dead_letters() {
+        let path = std::env::temp_dir().join(format!("vrl-test-{}-errors", std::process::id()));
+        let mut dead_letters = DeadLetters {
+            writer: Writer::new(
+                BufWriter::new(File::create(&path).unwrap()),
+                OutputFormat::JsonCompact,
+            ),
+        };
+
+        let sources = [("stage0".to_owned(), "abort".to_owned())];
+        let mut pipeline =
+            crate::pipeline::Pipeline::compile(&sources, &vrl::stdlib::all(), false).unwrap();
+        let error = pipeline.resolve(value!({"a": 1})).unwrap_err();
+        dead_letters.send(value!({"a": 1}), &error).unwrap();
+        dead_letters.flush().unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\"aborted\":true,\"error\":\"aborted\",\"event\":{\"a\":1},\"stage\":\"stage0\"}\n"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn writes_ndjson() {
+        let mut writer = Writer::new(Vec::new(), OutputFormat::JsonCompact);
+        writer.send(&value!({"a": 1, "b": [true, null]})).unwrap();
+        writer.send(&value!({"message": "hi"})).unwrap();
+
+        assert_eq!(
+            String::from_utf8(writer.writer).unwrap(),
+            "{\"a\":1,\"b\":[true,null]}\n{\"message\":\"hi\"}\n"
+        );
+    }
+
+    #[test]
+    fn writes_yaml_and_toml() {
+        let event = value!({"a": 1, "b": {"c": "x"}});
+        let mut writer = Writer::new(Vec::new(), OutputFormat::Yaml);
+        writer.send(&event).unwrap();
+        writer.send(&value!({"a": 2})).unwrap();
+
+        assert_eq!(
+            String::from_utf8(writer.writer).unwrap(),
+            "---\na: 1\nb:\n  c: x\n---\na: 2\n"
+        );
+
+        let mut writer = Writer::new(Vec::new(), OutputFormat::Toml);
+        writer.send(&event).unwrap();
+        writer.send(&value!({"a": 2})).unwrap();
+
+        assert_eq!(
+            String::from_utf8(writer.writer).unwrap(),
+            "a = 1\n\n[b]\nc = \"x\"\n\na = 2\n\n"
+        );
+        assert!(writer_error(OutputFormat::Toml, value!([1])).contains("TOML"));
+    }
+
+    fn writer_error(format: OutputFormat, event: Value) -> String {
+        let err = Writer::new(Vec::new(), format).send(&event).unwrap_err();
+        format!("{err:#}")
+    }
+
+    #[test]
+    fn writes_pretty_json() {
+        let mut writer = Writer::new(Vec::new(), OutputFormat::JsonPretty);
+        writer.send(&value!({"a": 1})).unwrap();
+
+        assert_eq!(
+            String::from_utf8(writer.writer).unwrap(),
+            "{\n  \"a\": 1\n}\n"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use anyhow::{Context as _, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::info;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use vrl::value::Value;
+
+use super::{OutputFormat, Sink, Writer};
+
+/// When the output file is rolled over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Rotation {
+    /// Rotate once the file holds at least this many bytes.
+    pub(crate) max_bytes: Option<u64>,
+    /// Rotate once the file has been open this long.
+    pub(crate) interval: Option<Duration>,
+    /// Gzip files once they're rotated out.
+    pub(crate) gzip: bool,
+}
+
+/// Writes events to `path`, moving it aside to `path.1`, `path.2`, ... (or
+/// `path.N.gz`) whenever the rotation policy says so.
+///
+/// Rotation is checked before each event, so an event is never split across
+/// files and an idle output isn't rotated until the next event arrives.
+pub(super) struct RotatingFile {
+    path: PathBuf,
+    format: OutputFormat,
+    rotation: Rotation,
+    writer: Writer<Counted<BufWriter<File>>>,
+    opened: Instant,
+    next_index: usize,
+}
+
+impl RotatingFile {
+    pub(super) fn create(path: &Path, format: OutputFormat, rotation: Rotation) -> Result<Self> {
+        let mut next_index = 1;
+        while rotated_path(path, next_index, false).exists()
+            || rotated_path(path, next_index, true).exists()
+        {
+            next_index += 1;
+        }
+
+        Ok(Self {
+            path: path.to_owned(),
+            format,
+            rotation,
+            writer: open(path, format)?,
+            opened: Instant::now(),
+            next_index,
+        })
+    }
+
+    fn due(&self) -> bool {
+        let written = self.writer.writer.bytes;
+        let full = self.rotation.max_bytes.is_some_and(|max| written >= max);
+        let expired = self
+            .rotation
+            .interval
+            .is_some_and(|interval| self.opened.elapsed() >= interval);
+
+        written > 0 && (full || expired)
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        self.writer.flush()?;
+
+        let rotated = rotated_path(&self.path, self.next_index, false);
+        fs::rename(&self.path, &rotated)
+            .with_context(|| format!("failed to rotate {}", self.path.display()))?;
+        self.next_index += 1;
+        if self.rotation.gzip {
+            compress(&rotated)
+                .with_context(|| format!("failed to compress {}", rotated.display()))?;
+        }
+        info!("Rotated {} to {}", self.path.display(), rotated.display());
+
+        self.writer = open(&self.path, self.format)?;
+        self.opened = Instant::now();
+        Ok(())
+    }
+}
+
+impl Sink for RotatingFile {
+    fn send(&mut self, event: &Value) -> Result<()> {
+        if self.due() {
+            self.rotate()?;
+        }
+        self.writer.send(event)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn open(path: &Path, format: OutputFormat) -> Result<Writer<Counted<BufWriter<File>>>> {
+    let file = File::create(path)
+        .with_context(|| format!("failed to create output {}", path.display()))?;
+    Ok(Writer::new(Counted::new(BufWriter::new(file)), format))
+}
+
+fn rotated_path(path: &Path, index: usize, gzip: bool) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{index}"));
+    if gzip {
+        rotated.push(".gz");
+    }
+    rotated.into()
+}
+
+/// Replaces `path` with a gzipped `path.gz`.
+fn compress(path: &Path) -> io::Result<()> {
+    let mut gzipped = path.as_os_str().to_owned();
+    gzipped.push(".gz");
+
+    let mut encoder = GzEncoder::new(
+        BufWriter::new(File::create(gzipped)?),
+        Compression::default(),
+    );
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?.flush()?;
+    fs::remove_file(path)
+}
+
+/// Counts the bytes written through it.
+struct Counted<W> {
+    inner: W,
+    bytes: u64,
+}
+
+impl<W> Counted<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, bytes: 0 }
+    }
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+    use vrl::value;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("vrl-test-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rotates_by_size() {
+        let dir = temp_dir("rotate-size");
+        let path = dir.join("out.ndjson");
+        let rotation = Rotation {
+            max_bytes: Some(16),
+            ..Rotation::default()
+        };
+
+        let mut sink = RotatingFile::create(&path, OutputFormat::JsonCompact, rotation).unwrap();
+        for n in 0..3 {
+            sink.send(&value!({"n": n, "pad": "xx"})).unwrap();
+        }
+        sink.flush().unwrap();
+
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("out.ndjson.1"), "{\"n\":0,\"pad\":\"xx\"}\n");
+        assert_eq!(read("out.ndjson.2"), "{\"n\":1,\"pad\":\"xx\"}\n");
+        assert_eq!(read("out.ndjson"), "{\"n\":2,\"pad\":\"xx\"}\n");
+
+        // A later run continues the numbering instead of overwriting.
+        let mut sink = RotatingFile::create(&path, OutputFormat::JsonCompact, rotation).unwrap();
+        sink.send(&value!({"n": 3, "pad": "xx"})).unwrap();
+        sink.send(&value!({"n": 4, "pad": "xx"})).unwrap();
+        assert!(dir.join("out.ndjson.3").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn gzips_rotated_files() {
+        let dir = temp_dir("rotate-gzip");
+        let path = dir.join("out.ndjson");
+        let rotation = Rotation {
+            interval: Some(Duration::ZERO),
+            gzip: true,
+            ..Rotation::default()
+        };
+
+        let mut sink = RotatingFile::create(&path, OutputFormat::JsonCompact, rotation).unwrap();
+        sink.send(&value!({"n": 0})).unwrap();
+        sink.send(&value!({"n": 1})).unwrap();
+        sink.flush().unwrap();
+
+        let mut rotated = String::new();
+        GzDecoder::new(File::open(dir.join("out.ndjson.1.gz")).unwrap())
+            .read_to_string(&mut rotated)
+            .unwrap();
+        assert_eq!(rotated, "{\"n\":0}\n");
+        assert!(!dir.join("out.ndjson.1").exists());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"n\":1}\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::Result;
+use chrono::SecondsFormat;
+use std::io::Write;
+use std::str::FromStr;
+use vrl::path::{parse_value_path, OwnedValuePath};
+use vrl::value::Value;
+
+use super::Sink;
+
+/// A line of text with `{{ .field }}` placeholders, e.g.
+/// `'{{.host}} -> {{.message}}'`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Field(OwnedValuePath),
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find("{{") {
+            let end = rest[start..]
+                .find("}}")
+                .ok_or_else(|| format!("unclosed `{{{{` at `{}`", &rest[start..]))?;
+            let field = rest[start + 2..start + end].trim();
+            let path = parse_value_path(field)
+                .map_err(|_| format!("invalid field path `{field}` in template"))?;
+
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_owned()));
+            }
+            parts.push(Part::Field(path));
+            rest = &rest[start + end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_owned()));
+        }
+
+        Ok(Self { parts })
+    }
+}
+
+impl Template {
+    /// Interpolates the fields of `event`. Strings and timestamps are written
+    /// bare, missing fields and nulls as nothing and anything else as JSON.
+    fn render(&self, event: &Value) -> Result<String> {
+        let mut line = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => line.push_str(text),
+                Part::Field(path) => match event.get(path) {
+                    None | Some(Value::Null) => {}
+                    Some(Value::Bytes(bytes)) => line.push_str(&String::from_utf8_lossy(bytes)),
+                    Some(Value::Timestamp(timestamp)) => {
+                        line.push_str(&timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+                    }
+                    Some(value) => line.push_str(&serde_json::to_string(value)?),
+                },
+            }
+        }
+        Ok(line)
+    }
+}
+
+/// Writes one rendered template line per event.
+pub(super) struct TemplateWriter<W> {
+    writer: W,
+    template: Template,
+}
+
+impl<W: Write> TemplateWriter<W> {
+    pub(super) fn new(writer: W, template: Template) -> Self {
+        Self { writer, template }
+    }
+}
+
+impl<W: Write> Sink for TemplateWriter<W> {
+    fn send(&mut self, event: &Value) -> Result<()> {
+        let line = self.template.render(event)?;
+        Ok(writeln!(self.writer, "{line}")?)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vrl::value;
+
+    fn render(template: &str, event: Value) -> String {
+        template
+            .parse::<Template>()
+            .unwrap()
+            .render(&event)
+            .unwrap()
+    }
+
+    #[test]
+    fn interpolates_fields() {
+        let event = value!({"host": "web-1", "message": "up", "http": {"status": 200}});
+
+        assert_eq!(
+            render("{{.host}} -> {{ .message }}", event.clone()),
+            "web-1 -> up"
+        );
+        assert_eq!(
+            render("[{{.http.status}}] {{.http}}{{.missing}}", event),
+            "[200] {\"status\":200}"
+        );
+        assert_eq!(render("plain", value!({})), "plain");
+    }
+
+    #[test]
+    fn invalid_templates() {
+        assert!("{{.host".parse::<Template>().is_err());
+        assert!("{{ .host. }}".parse::<Template>().is_err());
+    }
+}
@@ -0,0 +1,458 @@
+use log::{debug, warn};
+use rayon::prelude::*;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use vrl::compiler::runtime::Terminate;
+use vrl::compiler::state::RuntimeState;
+use vrl::compiler::{Context, ExpressionError, Function, Program, TargetValue, TimeZone};
+use vrl::diagnostic::{DiagnosticList, Formatter};
+use vrl::value::{Secrets, Value};
+
+use crate::arena;
+use crate::program::{format_diagnostics, try_compile};
+use crate::state::RunState;
+
+/// A single compiled program in a pipeline.
+pub(crate) struct Stage {
+    pub(crate) name: String,
+    program: Program,
+    pub(crate) compile_time: Duration,
+    pub(crate) resolve_time: Duration,
+}
+
+/// A chain of programs where the output event of each stage is the input of the next.
+pub(crate) struct Pipeline {
+    stages: Vec<Stage>,
+    runtime: RuntimeState,
+    environment: Environment,
+    state: RunState,
+}
+
+/// What events are resolved with besides the programs: the timezone, and the
+/// metadata and secrets each event starts out with.
+#[derive(Debug, Clone)]
+pub(crate) struct Environment {
+    pub(crate) timezone: TimeZone,
+    pub(crate) metadata: Value,
+    pub(crate) secrets: Secrets,
+}
+
+impl Default for Environment {
+    /// The local timezone, with empty metadata and no secrets.
+    fn default() -> Self {
+        Self {
+            timezone: TimeZone::default(),
+            metadata: Value::Object(Default::default()),
+            secrets: Secrets::new(),
+        }
+    }
+}
+
+impl Environment {
+    /// Wraps an event in a target with this environment's metadata and secrets.
+    fn target(&self, event: Value) -> TargetValue {
+        TargetValue {
+            value: event,
+            metadata: self.metadata.clone(),
+            secrets: self.secrets.clone(),
+        }
+    }
+}
+
+impl Pipeline {
+    /// Compiles each `(name, source)` pair as a separate stage, sharing one
+    /// [`RunState`] between all of them.
+    ///
+    /// Diagnostics for every failing stage are printed to stderr. With
+    /// `deny_warnings`, a stage that compiles with warnings counts as failed.
+    pub(crate) fn compile(
+        sources: &[(String, String)],
+        functions: &[Box<dyn Function>],
+        deny_warnings: bool,
+    ) -> Result<Self, CompileFailure> {
+        Self::compile_with(sources, functions, deny_warnings, |failed| {
+            eprintln!("{}", format_diagnostics(&failed.source, failed.diagnostics));
+        })
+    }
+
+    /// Like [`Pipeline::compile`], but passes the diagnostics of every failing
+    /// stage to `report` instead of printing them.
+    pub(crate) fn compile_with(
+        sources: &[(String, String)],
+        functions: &[Box<dyn Function>],
+        deny_warnings: bool,
+        mut report: impl FnMut(StageDiagnostics),
+    ) -> Result<Self, CompileFailure> {
+        let mut stages = Vec::with_capacity(sources.len());
+        let mut failure = None;
+        let state = RunState::default();
+        let failed = |name: &String, source: &String, diagnostics| StageDiagnostics {
+            stage: name.clone(),
+            source: source.clone(),
+            diagnostics,
+        };
+
+        for (name, source) in sources {
+            let start = Instant::now();
+            let result = match try_compile(source, functions, &state) {
+                Ok(result) => result,
+                Err(diagnostics) => {
+                    report(failed(name, source, diagnostics));
+                    failure = Some(CompileFailure::Errors);
+                    continue;
+                }
+            };
+            let compile_time = start.elapsed();
+            debug!("Compiled {name}, took {compile_time:?}");
+
+            if !result.warnings.is_empty() {
+                if deny_warnings {
+                    report(failed(name, source, result.warnings));
+                    failure.get_or_insert(CompileFailure::DeniedWarnings);
+                } else {
+                    warn!("{}", format_diagnostics(source, result.warnings));
+                }
+            }
+
+            stages.push(Stage {
+                name: name.clone(),
+                program: result.program,
+                compile_time,
+                resolve_time: Duration::ZERO,
+            });
+        }
+
+        match failure {
+            Some(failure) => Err(failure),
+            None => Ok(Self {
+                stages,
+                runtime: RuntimeState::default(),
+                environment: Environment::default(),
+                state,
+            }),
+        }
+    }
+
+    /// Resolves the events from now on with `environment` instead of the
+    /// default one.
+    pub(crate) fn set_environment(&mut self, environment: Environment) {
+        self.environment = environment;
+    }
+
+    pub(crate) fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    /// The state shared by the stateful functions of every stage.
+    pub(crate) fn state(&self) -> &RunState {
+        &self.state
+    }
+
+    /// Flushes the state of the stateful functions, once no more events
+    /// will be resolved.
+    pub(crate) fn flush(&self) -> anyhow::Result<()> {
+        self.state.flush()
+    }
+
+    /// Runs `event` through every stage in order, returning the final target
+    /// and the value the last stage returned.
+    pub(crate) fn resolve(&mut self, event: Value) -> Result<Outcome, StageError> {
+        let mut resolve_times = vec![Duration::ZERO; self.stages.len()];
+        let outcome = resolve_event(
+            &mut self.runtime,
+            &self.stages,
+            self.environment.target(event),
+            &self.environment.timezone,
+            |stage, elapsed| resolve_times[stage] += elapsed,
+        );
+        self.add_resolve_times(resolve_times);
+        outcome
+    }
+
+    /// Runs each of `events` through every stage in order, returning the
+    /// results in order along with how long each event took.
+    ///
+    /// The runtime state and the timezone are set up once for the whole
+    /// batch, and each event gets a single [`Context`] that every stage
+    /// resolves in. Only the target is made for each event, since every
+    /// outcome owns its own.
+    pub(crate) fn run_batch(&mut self, events: &[Value]) -> Vec<Resolved> {
+        let mut resolve_times = vec![Duration::ZERO; self.stages.len()];
+        let (stages, environment) = (&self.stages, &self.environment);
+        let timezone = &environment.timezone;
+
+        let mut results = Vec::with_capacity(events.len());
+        for event in events {
+            let start = Instant::now();
+            let outcome = resolve_event(
+                &mut self.runtime,
+                stages,
+                environment.target(event.clone()),
+                timezone,
+                |stage, elapsed| resolve_times[stage] += elapsed,
+            );
+            results.push((outcome, start.elapsed()));
+        }
+
+        self.add_resolve_times(resolve_times);
+        results
+    }
+
+    /// Like [`Pipeline::run_batch`], but on the threads of the current rayon
+    /// pool. The stages are shared between the threads, each resolving with
+    /// runtime state of its own, set up once per thread.
+    pub(crate) fn resolve_all(&mut self, events: &[Value]) -> Vec<Resolved> {
+        let resolve_nanos = self
+            .stages
+            .iter()
+            .map(|_| AtomicU64::new(0))
+            .collect::<Vec<_>>();
+        let (stages, environment) = (&self.stages, &self.environment);
+        let timezone = &environment.timezone;
+
+        let results = events
+            .par_iter()
+            .map_init(RuntimeState::default, |runtime, event| {
+                let start = Instant::now();
+                let target = environment.target(event.clone());
+                let outcome = resolve_event(runtime, stages, target, timezone, |stage, elapsed| {
+                    resolve_nanos[stage].fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+                });
+                (outcome, start.elapsed())
+            })
+            .collect();
+
+        self.add_resolve_times(
+            resolve_nanos
+                .into_iter()
+                .map(|nanos| Duration::from_nanos(nanos.into_inner())),
+        );
+        results
+    }
+
+    /// Adds how long each stage took, by index, to its resolve time.
+    fn add_resolve_times(&mut self, resolve_times: impl IntoIterator<Item = Duration>) {
+        for (stage, elapsed) in self.stages.iter_mut().zip(resolve_times) {
+            stage.resolve_time += elapsed;
+        }
+    }
+}
+
+/// An event that went through the pipeline, along with how long it took.
+pub(crate) type Resolved = (Result<Outcome, StageError>, Duration);
+
+/// Runs `target` through `stages` in order, all in one [`Context`], passing
+/// how long each took to `record` by index.
+fn resolve_event(
+    runtime: &mut RuntimeState,
+    stages: &[Stage],
+    mut target: TargetValue,
+    timezone: &TimeZone,
+    mut record: impl FnMut(usize, Duration),
+) -> Result<Outcome, StageError> {
+    arena::reset();
+    let mut ctx = Context::new(&mut target, runtime, timezone);
+    let mut result = Value::Null;
+    for (index, stage) in stages.iter().enumerate() {
+        let (resolved, elapsed) = resolve_stage(&mut ctx, &stage.program);
+        record(index, elapsed);
+
+        result = resolved.map_err(|error| StageError {
+            stage: stage.name.clone(),
+            error,
+        })?;
+    }
+    Ok(Outcome { target, result })
+}
+
+/// Resolves `program` in `ctx`, leaving its variables cleared for the next
+/// one, and returns how long it took. Errors terminate the program the way
+/// [`vrl::compiler::runtime::Runtime::resolve`] does.
+fn resolve_stage(ctx: &mut Context<'_>, program: &Program) -> (Result<Value, Terminate>, Duration) {
+    let start = Instant::now();
+    let resolved = match program.resolve(ctx) {
+        Ok(value) | Err(ExpressionError::Return { value, .. }) => Ok(value),
+        Err(
+            error @ (ExpressionError::Abort { .. }
+            | ExpressionError::Fallible { .. }
+            | ExpressionError::Missing { .. }),
+        ) => Err(Terminate::Abort(error)),
+        Err(error @ ExpressionError::Error { .. }) => Err(Terminate::Error(error)),
+    };
+    ctx.state_mut().clear();
+    (resolved, start.elapsed())
+}
+
+/// An event after it went through the pipeline.
+#[derive(Debug)]
+pub struct Outcome {
+    /// The event and metadata as the stages left them.
+    pub target: TargetValue,
+    /// The value of the last expression of the last stage.
+    pub result: Value,
+}
+
+/// Why a pipeline failed to compile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CompileFailure {
+    /// At least one stage has compile errors.
+    Errors,
+    /// Every stage compiled, but warnings were denied.
+    DeniedWarnings,
+}
+
+/// The diagnostics of a stage that failed to compile, or compiled with
+/// warnings that were denied.
+#[derive(Debug, Clone)]
+pub struct StageDiagnostics {
+    /// The name of the failing stage.
+    pub stage: String,
+    /// The source the diagnostics point into.
+    pub source: String,
+    pub diagnostics: DiagnosticList,
+}
+
+impl fmt::Display for StageDiagnostics {
+    /// Renders the diagnostics against the source, without colors.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Formatter::new(&self.source, self.diagnostics.clone()).fmt(f)
+    }
+}
+
+/// A runtime failure, tagged with the stage that raised it.
+#[derive(Debug)]
+pub struct StageError {
+    /// The name of the failing stage.
+    pub stage: String,
+    pub error: Terminate,
+}
+
+impl fmt::Display for StageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.stage, self.error)
+    }
+}
+
+impl std::error::Error for StageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use vrl::value;
+
+    fn pipeline(sources: &[&str]) -> Pipeline {
+        let sources = sources
+            .iter()
+            .enumerate()
+            .map(|(i, source)| (format!("stage{i}"), source.to_string()))
+            .collect::<Vec<_>>();
+        Pipeline::compile(&sources, &vrl::stdlib::all(), false).unwrap()
+    }
+
+    #[test]
+    fn stages_feed_into_each_other() {
+        let mut pipeline = pipeline(&[".a = 1", ".b = int!(.a) + 1", "del(.a)"]);
+
+        let outcome = pipeline.resolve(value!({})).unwrap();
+
+        assert_eq!(outcome.target.value, value!({"b": 2}));
+    }
+
+    #[test]
+    fn result_of_last_stage() {
+        let mut pipeline = pipeline(&["1", ".a = 2; \"done\""]);
+
+        let outcome = pipeline.resolve(value!({})).unwrap();
+
+        assert_eq!(outcome.result, value!("done"));
+        assert_eq!(outcome.target.value, value!({"a": 2}));
+    }
+
+    #[test]
+    fn error_names_failing_stage() {
+        let mut pipeline = pipeline(&[".a = 1", "abort"]);
+
+        let error = pipeline.resolve(value!({})).unwrap_err();
+
+        assert_eq!(error.stage, "stage1");
+    }
+
+    #[test]
+    fn runs_batches_in_order() {
+        let mut pipeline = pipeline(&[".b = 1", "assert!(.a != 1)"]);
+        let events = vec![value!({"a": 0}), value!({"a": 1}), value!({"a": 2})];
+
+        let results = pipeline.run_batch(&events);
+
+        let values = results
+            .into_iter()
+            .map(|(result, _)| result.ok().map(|outcome| outcome.target.value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            vec![
+                Some(value!({"a": 0, "b": 1})),
+                None,
+                Some(value!({"a": 2, "b": 1}))
+            ]
+        );
+        assert!(pipeline.stages()[1].resolve_time > Duration::ZERO);
+    }
+
+    #[test]
+    fn resolves_in_parallel_in_order() {
+        let mut pipeline = pipeline(&[".b = int!(.a) * 2", "assert!(.a != 3); .c = 1"]);
+        let events = (0..100).map(|a| value!({"a": a})).collect::<Vec<_>>();
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        let results = pool.install(|| pipeline.resolve_all(&events));
+
+        assert_eq!(results.len(), 100);
+        for (a, (result, _)) in results.into_iter().enumerate() {
+            let (a, b) = (a as i64, a as i64 * 2);
+            match result {
+                Ok(outcome) => assert_eq!(outcome.target.value, value!({"a": a, "b": b, "c": 1})),
+                Err(error) => {
+                    assert_eq!(a, 3);
+                    assert_eq!(error.stage, "stage1");
+                }
+            }
+        }
+        assert!(pipeline
+            .stages()
+            .iter()
+            .all(|stage| stage.resolve_time > Duration::ZERO));
+    }
+
+    #[test]
+    fn any_failing_stage_fails_compile() {
+        let sources = vec![
+            ("ok".to_owned(), ".a = 1".to_owned()),
+            ("bad".to_owned(), ".a = (".to_owned()),
+        ];
+
+        assert!(matches!(
+            Pipeline::compile(&sources, &vrl::stdlib::all(), false),
+            Err(CompileFailure::Errors)
+        ));
+    }
+
+    #[test]
+    fn deny_warnings() {
+        let sources = vec![("unused".to_owned(), "x = 1".to_owned())];
+
+        assert!(Pipeline::compile(&sources, &vrl::stdlib::all(), false).is_ok());
+        assert!(matches!(
+            Pipeline::compile(&sources, &vrl::stdlib::all(), true),
+            Err(CompileFailure::DeniedWarnings)
+        ));
+    }
+}
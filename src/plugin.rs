@@ -0,0 +1,144 @@
+//! Custom functions loaded from shared libraries at runtime.
+//!
+//! A plugin is a `cdylib` exporting a static named `VRL_TEST_PLUGIN` of type
+//! [`PluginDeclaration`]; copy the struct into the plugin crate verbatim:
+//!
+//! ```ignore
+//! #[no_mangle]
+//! pub static VRL_TEST_PLUGIN: PluginDeclaration = PluginDeclaration {
+//!     abi_version: 1,
+//!     rustc_version: env!("RUSTC_VERSION"), // e.g. set from `rustc --version` in build.rs
+//!     vrl_version: "0.20",
+//!     functions,
+//! };
+//!
+//! fn functions() -> Vec<Box<dyn Function>> {
+//!     vec![Box::new(MyFn)]
+//! }
+//! ```
+//!
+//! Rust has no stable ABI, so the plugin must be built with the same compiler
+//! and `vrl` release as this binary; both are checked before `functions` is
+//! called.
+
+use anyhow::{bail, Context as _, Result};
+use libloading::Library;
+use log::info;
+use std::path::Path;
+use vrl::compiler::Function;
+
+/// Bumped whenever the layout of [`PluginDeclaration`] changes.
+pub(crate) const ABI_VERSION: u32 = 1;
+
+const RUSTC_VERSION: &str = env!("VRL_TEST_RUSTC_VERSION");
+
+/// The `vrl` release from Cargo.toml, without the patch version.
+const VRL_VERSION: &str = "0.20";
+
+const SYMBOL: &[u8] = b"VRL_TEST_PLUGIN\0";
+
+/// What a plugin exports. `abi_version` comes first so it can be checked
+/// before the rest of the layout is relied on.
+#[repr(C)]
+pub(crate) struct PluginDeclaration {
+    pub(crate) abi_version: u32,
+    pub(crate) rustc_version: &'static str,
+    pub(crate) vrl_version: &'static str,
+    pub(crate) functions: fn() -> Vec<Box<dyn Function>>,
+}
+
+impl PluginDeclaration {
+    fn check(&self) -> Result<()> {
+        if self.abi_version != ABI_VERSION {
+            bail!(
+                "plugin ABI version {} is not supported (expected {ABI_VERSION})",
+                self.abi_version
+            );
+        }
+        if self.rustc_version != RUSTC_VERSION {
+            bail!(
+                "plugin was built with {}, but this binary with {RUSTC_VERSION}",
+                self.rustc_version
+            );
+        }
+        if self.vrl_version != VRL_VERSION {
+            bail!(
+                "plugin was built against vrl {}, but this binary against vrl {VRL_VERSION}",
+                self.vrl_version
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Loads the functions exported by the plugin at `path`.
+///
+/// The library is never unloaded, since the functions point into it.
+pub(crate) fn load(path: &Path) -> Result<Vec<Box<dyn Function>>> {
+    let context = || format!("failed to load plugin {}", path.display());
+
+    // SAFETY: loading runs the library's initialisers; plugins are trusted
+    // code named explicitly on the command line.
+    let library = unsafe { Library::new(path) }.with_context(context)?;
+    // SAFETY: the versions are checked, ABI version first, before anything
+    // else in the declaration is relied on.
+    let declaration = unsafe {
+        let symbol = library
+            .get::<*const PluginDeclaration>(SYMBOL)
+            .with_context(context)?;
+        &**symbol
+    };
+    declaration.check().with_context(context)?;
+
+    let functions = (declaration.functions)();
+    info!(
+        "Loaded {} function(s) from plugin {}",
+        functions.len(),
+        path.display()
+    );
+    std::mem::forget(library);
+    Ok(functions)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn declaration() -> PluginDeclaration {
+        PluginDeclaration {
+            abi_version: ABI_VERSION,
+            rustc_version: RUSTC_VERSION,
+            vrl_version: VRL_VERSION,
+            functions: || vec![Box::new(crate::functions::Split)],
+        }
+    }
+
+    #[test]
+    fn checks_versions() {
+        assert!(declaration().check().is_ok());
+
+        let err = PluginDeclaration {
+            abi_version: ABI_VERSION + 1,
+            ..declaration()
+        };
+        assert!(err.check().unwrap_err().to_string().contains("ABI version"));
+
+        let err = PluginDeclaration {
+            rustc_version: "rustc 1.0.0",
+            ..declaration()
+        };
+        assert!(err.check().unwrap_err().to_string().contains("rustc 1.0.0"));
+
+        let err = PluginDeclaration {
+            vrl_version: "0.1",
+            ..declaration()
+        };
+        assert!(err.check().unwrap_err().to_string().contains("vrl 0.1"));
+    }
+
+    #[test]
+    fn missing_library() {
+        let err = load(Path::new("/nonexistent/libplugin.so")).unwrap_err();
+        assert!(err.to_string().contains("failed to load plugin"));
+    }
+}
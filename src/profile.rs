@@ -0,0 +1,290 @@
+//! Profiling of the function calls in programs: `run --profile` wraps every
+//! call in an expression timing it, and reports how much of the resolve time
+//! each call took once the run is over.
+//!
+//! A call's total time includes the calls among its arguments or in its
+//! closure, while its self time is only what the function itself spent.
+
+use std::cell::Cell;
+use std::fmt::{self, Write as _};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use vrl::compiler::function::closure;
+use vrl::compiler::state::{TypeInfo, TypeState};
+use vrl::prelude::*;
+
+use crate::state::{FunctionState, RunState};
+
+/// The key of the [`Profile`] in the run state.
+pub(crate) const KEY: &str = "profile";
+
+thread_local! {
+    /// The time spent in the nested calls of the call resolving on this
+    /// thread.
+    static NESTED: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+/// Times spent in a function call of a program.
+#[derive(Debug)]
+struct Site {
+    identifier: &'static str,
+    span: Span,
+    calls: AtomicU64,
+    total_nanos: AtomicU64,
+    self_nanos: AtomicU64,
+}
+
+impl Site {
+    fn record(&self, total: Duration, own: Duration) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.total_nanos
+            .fetch_add(total.as_nanos() as u64, Ordering::Relaxed);
+        self.self_nanos
+            .fetch_add(own.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+/// The function calls of every program of a run, along with the time spent
+/// in them.
+#[derive(Default)]
+pub(crate) struct Profile {
+    sites: Mutex<Vec<Arc<Site>>>,
+}
+
+impl FunctionState for Profile {}
+
+impl Profile {
+    fn site(&self, identifier: &'static str, span: Span) -> Arc<Site> {
+        let site = Arc::new(Site {
+            identifier,
+            span,
+            calls: AtomicU64::new(0),
+            total_nanos: AtomicU64::new(0),
+            self_nanos: AtomicU64::new(0),
+        });
+        self.sites
+            .lock()
+            .expect("profile lock poisoned")
+            .push(site.clone());
+        site
+    }
+
+    /// Renders one row per function call, the most expensive first, with its
+    /// location in `sources` and its share of `resolve`, the time spent
+    /// resolving events.
+    pub(crate) fn render(&self, sources: &[(String, String)], resolve: Duration) -> String {
+        let nanos = |nanos: &AtomicU64| Duration::from_nanos(nanos.load(Ordering::Relaxed));
+        let mut sites = self
+            .sites
+            .lock()
+            .expect("profile lock poisoned")
+            .iter()
+            .map(|site| {
+                (
+                    site.identifier,
+                    locate(sources, site.identifier, site.span),
+                    site.calls.load(Ordering::Relaxed),
+                    nanos(&site.total_nanos),
+                    nanos(&site.self_nanos),
+                )
+            })
+            .collect::<Vec<_>>();
+        sites.sort_by(|a, b| b.4.cmp(&a.4).then(a.1.cmp(&b.1)));
+
+        let width = |column: fn(&(&str, String, u64, Duration, Duration)) -> usize, header| {
+            sites
+                .iter()
+                .map(column)
+                .chain([header])
+                .max()
+                .unwrap_or_default()
+        };
+        let function = width(|site| site.0.len(), 8);
+        let location = width(|site| site.1.len(), 8);
+
+        let mut out = format!(
+            "{:function$}  {:location$}  {:>8}  {:>12}  {:>12}  {:>6}",
+            "function", "location", "calls", "total", "self", "share"
+        );
+        for (identifier, place, calls, total, own) in sites {
+            let share = match resolve.as_secs_f64() {
+                0.0 => 0.0,
+                resolve => own.as_secs_f64() / resolve * 100.0,
+            };
+            let _ = write!(
+                out,
+                "\n{identifier:function$}  {place:location$}  {calls:>8}  {:>12}  {:>12}  {share:>5.1}%",
+                format!("{total:?}"),
+                format!("{own:?}"),
+            );
+        }
+        out
+    }
+}
+
+/// The `name:line:column` of the call to `identifier` at `span`, in the first
+/// of `sources` holding one there.
+fn locate(sources: &[(String, String)], identifier: &str, span: Span) -> String {
+    let source = sources.iter().find(|(_, source)| {
+        source
+            .get(span.start()..)
+            .is_some_and(|call| call.starts_with(identifier))
+    });
+    let Some((name, source)) = source else {
+        return format!("{}..{}", span.start(), span.end());
+    };
+
+    let before = &source[..span.start()];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |newline| newline + 1) + 1;
+    format!("{name}:{line}:{column}")
+}
+
+/// Wraps each of `functions` so that their calls are profiled.
+pub(crate) fn instrument(functions: Vec<Box<dyn Function>>) -> Vec<Box<dyn Function>> {
+    functions
+        .into_iter()
+        .map(|function| Box::new(Profiled(function)) as Box<dyn Function>)
+        .collect()
+}
+
+/// A function whose calls are timed, in the [`Profile`] of the run.
+#[derive(Debug)]
+struct Profiled(Box<dyn Function>);
+
+impl Function for Profiled {
+    fn identifier(&self) -> &'static str {
+        self.0.identifier()
+    }
+
+    fn summary(&self) -> &'static str {
+        self.0.summary()
+    }
+
+    fn usage(&self) -> &'static str {
+        self.0.usage()
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        self.0.examples()
+    }
+
+    fn compile(
+        &self,
+        state: &TypeState,
+        ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        let profile = match ctx.get_external_context::<RunState>() {
+            Some(run) => run.get_or_init(KEY, Profile::default),
+            None => Arc::default(),
+        };
+        let site = profile.site(self.identifier(), ctx.span());
+        let expression = self.0.compile(state, ctx, arguments)?;
+        Ok(Box::new(ProfiledCall { expression, site }))
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        self.0.parameters()
+    }
+
+    fn closure(&self) -> Option<closure::Definition> {
+        self.0.closure()
+    }
+}
+
+#[derive(Clone)]
+struct ProfiledCall {
+    expression: Box<dyn Expression>,
+    site: Arc<Site>,
+}
+
+impl fmt::Debug for ProfiledCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.expression.fmt(f)
+    }
+}
+
+impl Expression for ProfiledCall {
+    fn resolve(&self, ctx: &mut Context) -> Resolved {
+        let outer = NESTED.replace(Duration::ZERO);
+        let start = Instant::now();
+        let resolved = self.expression.resolve(ctx);
+        let total = start.elapsed();
+        let nested = NESTED.replace(outer + total);
+        self.site.record(total, total.saturating_sub(nested));
+        resolved
+    }
+
+    fn resolve_constant(&self, state: &TypeState) -> Option<Value> {
+        self.expression.resolve_constant(state)
+    }
+
+    fn type_info(&self, state: &TypeState) -> TypeInfo {
+        self.expression.type_info(state)
+    }
+
+    fn format(&self) -> Option<String> {
+        self.expression.format()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pipeline::Pipeline;
+    use vrl::value;
+
+    #[test]
+    fn times_every_call() {
+        let sources = vec![
+            (
+                "a.vrl".to_owned(),
+                "x = upcase(\"a\")\n.b = downcase(upcase(string!(.b)))".to_owned(),
+            ),
+            ("b.vrl".to_owned(), ".c = length(string!(.b))".to_owned()),
+        ];
+        let functions = instrument(vrl::stdlib::all());
+        let mut pipeline = Pipeline::compile(&sources, &functions, false).unwrap();
+        for _ in 0..3 {
+            pipeline.resolve(value!({"b": "B"})).unwrap();
+        }
+
+        let profile = pipeline.state().get_or_init(KEY, Profile::default);
+        let rendered = profile.render(&sources, Duration::ZERO);
+        let mut rows = rendered
+            .lines()
+            .skip(1)
+            .map(|row| row.split_whitespace().take(3).collect::<Vec<_>>().join(" "))
+            .collect::<Vec<_>>();
+        rows.sort();
+        assert_eq!(
+            rows,
+            [
+                "downcase a.vrl:2:6 3",
+                "length b.vrl:1:6 3",
+                "string a.vrl:2:22 3",
+                "string b.vrl:1:13 3",
+                "upcase a.vrl:1:5 3",
+                "upcase a.vrl:2:15 3",
+            ]
+        );
+
+        // the time of a call includes the nested ones, but its self time doesn't
+        let sites = profile.sites.lock().unwrap();
+        let nanos = |identifier, start| {
+            let site = sites
+                .iter()
+                .find(|site| site.identifier == identifier && site.span.start() == start)
+                .unwrap();
+            (
+                site.total_nanos.load(Ordering::Relaxed),
+                site.self_nanos.load(Ordering::Relaxed),
+            )
+        };
+        let (downcase_total, downcase_self) = nanos("downcase", 21);
+        let (upcase_total, _) = nanos("upcase", 30);
+        assert_eq!(downcase_self, downcase_total - upcase_total);
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::{bail, Context as _, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::PathBuf;
+use vrl::compiler::state::ExternalEnv;
+use vrl::compiler::{compile_with_external, CompilationResult, Function, TargetValue};
+use vrl::diagnostic::{DiagnosticList, Formatter};
+use vrl::value::{Secrets, Value};
+
+use crate::registry::{compile_config, Deprecations};
+use crate::state::RunState;
+
+/// Where the source of a VRL program is read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ProgramSource {
+    File(PathBuf),
+    Stdin,
+    Inline(String),
+}
+
+impl ProgramSource {
+    pub(crate) fn read(&self) -> Result<String> {
+        match self {
+            ProgramSource::File(path) => fs::read_to_string(path)
+                .with_context(|| format!("failed to read program from {self}")),
+            ProgramSource::Stdin => {
+                let mut source = String::new();
+                io::stdin()
+                    .read_to_string(&mut source)
+                    .with_context(|| format!("failed to read program from {self}"))?;
+                Ok(source)
+            }
+            ProgramSource::Inline(source) => Ok(source.clone()),
+        }
+    }
+}
+
+impl fmt::Display for ProgramSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProgramSource::File(path) => path.display().fmt(f),
+            ProgramSource::Stdin => f.write_str("<stdin>"),
+            ProgramSource::Inline(_) => f.write_str("<inline>"),
+        }
+    }
+}
+
+/// Reads every source, pairing its contents with a display name.
+pub(crate) fn read_sources(sources: &[ProgramSource]) -> Result<Vec<(String, String)>> {
+    let stdin_count = sources
+        .iter()
+        .filter(|source| matches!(source, ProgramSource::Stdin))
+        .count();
+    if stdin_count > 1 {
+        bail!("stdin can only be read as a program once");
+    }
+
+    sources
+        .iter()
+        .map(|source| Ok((source.to_string(), source.read()?)))
+        .collect()
+}
+
+/// Compiles `source`, printing any error diagnostics to stderr. Calls to
+/// deprecated functions are added to the warnings; stateful functions keep
+/// their state in `state`.
+pub(crate) fn compile_source(
+    source: &str,
+    functions: &[Box<dyn Function>],
+    state: &RunState,
+) -> Option<CompilationResult> {
+    try_compile(source, functions, state)
+        .map_err(|diagnostics| eprintln!("{}", format_diagnostics(source, diagnostics)))
+        .ok()
+}
+
+/// Like [`compile_source`], but returns the error diagnostics instead of
+/// printing them.
+pub(crate) fn try_compile(
+    source: &str,
+    functions: &[Box<dyn Function>],
+    state: &RunState,
+) -> Result<CompilationResult, DiagnosticList> {
+    let external = ExternalEnv::default();
+    let mut result = compile_with_external(source, functions, &external, compile_config(state))?;
+
+    if let Some(Deprecations(warnings)) = result.config.get_custom_mut() {
+        result.warnings.append(warnings);
+    }
+    Ok(result)
+}
+
+/// Renders diagnostics against their source, colored when stderr is a terminal.
+pub(crate) fn format_diagnostics(source: &str, diagnostics: DiagnosticList) -> String {
+    let mut formatter = Formatter::new(source, diagnostics);
+    formatter.enable_colors(io::stderr().is_terminal());
+    formatter.to_string()
+}
+
+/// Wraps an event in a target with empty metadata and no secrets.
+pub(crate) fn new_target(value: Value) -> TargetValue {
+    TargetValue {
+        value,
+        metadata: Value::Object(BTreeMap::new()),
+        secrets: Secrets::new(),
+    }
+}
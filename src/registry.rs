@@ -0,0 +1,617 @@
+use anyhow::{bail, Result};
+use log::debug;
+use std::fmt;
+use std::sync::Arc;
+use vrl::compiler::function::{closure, ArgumentList, Compiled, Example, FunctionCompileContext};
+use vrl::compiler::prelude::TypeState;
+use vrl::compiler::{CompileConfig, Function, Parameter};
+use vrl::diagnostic::{Diagnostic, DiagnosticMessage, Label, Note, Severity, Span};
+
+use crate::cli::RegistryArgs;
+use crate::closure_fn::{Callable, ClosureFn};
+use crate::describe::Origin;
+use crate::state::RunState;
+use crate::{functions, plugin};
+
+/// Builds the function set programs are compiled against.
+///
+/// Mistakes such as overriding a function that doesn't exist or adding one
+/// that does are collected and reported together by [`Registry::build`]:
+///
+/// ```ignore
+/// let functions = Registry::stdlib().override_fn(Split).add_fn(MyFn).build()?;
+/// ```
+pub(crate) struct Registry {
+    entries: Vec<(Origin, Box<dyn Function>)>,
+    errors: Vec<String>,
+}
+
+impl Registry {
+    /// Starts from every `vrl::stdlib` function.
+    pub(crate) fn stdlib() -> Self {
+        Self {
+            entries: vrl::stdlib::all()
+                .into_iter()
+                .map(|function| (Origin::Stdlib, function))
+                .collect(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Starts from exactly `functions`.
+    pub(crate) fn new(functions: Vec<Box<dyn Function>>) -> Self {
+        Self {
+            entries: functions
+                .into_iter()
+                .map(|function| (Origin::Custom, function))
+                .collect(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Replaces the registered function with the same identifier.
+    pub(crate) fn override_fn(mut self, function: impl Function + 'static) -> Self {
+        match self.position(function.identifier()) {
+            Some(index) => self.entries[index] = (Origin::Override, Box::new(function)),
+            None => self.errors.push(format!(
+                "cannot override `{}`: no such function is registered",
+                function.identifier()
+            )),
+        }
+        self
+    }
+
+    /// Registers a function under an identifier that isn't taken yet.
+    pub(crate) fn add_fn(self, function: impl Function + 'static) -> Self {
+        self.add(Origin::Custom, Box::new(function))
+    }
+
+    /// Registers `function` as `<namespace>_<identifier>`, keeping any
+    /// function registered under its own identifier, so programs can call
+    /// either one. VRL identifiers can't contain `::`, hence the underscore.
+    pub(crate) fn add_namespaced(self, namespace: &str, function: impl Function + 'static) -> Self {
+        let identifier = format!("{namespace}_{}", function.identifier()).leak();
+        let function = Wrapped::new(identifier, Arc::new(function));
+        self.add(Origin::Custom, Box::new(function))
+    }
+
+    /// Registers a plain closure as a function; its parameter kinds and
+    /// return type follow from the closure's argument and return types:
+    ///
+    /// ```ignore
+    /// registry.register_closure("double", |value: i64| value * 2)
+    /// ```
+    pub(crate) fn register_closure<Args>(
+        self,
+        identifier: &'static str,
+        closure: impl Callable<Args>,
+    ) -> Self {
+        self.add(
+            Origin::Custom,
+            Box::new(ClosureFn::new(identifier, closure)),
+        )
+    }
+
+    /// Registers the functions exported by a plugin.
+    pub(crate) fn add_plugin_fns(self, functions: Vec<Box<dyn Function>>) -> Self {
+        functions.into_iter().fold(self, |registry, function| {
+            registry.add(Origin::Plugin, function)
+        })
+    }
+
+    /// Disables every function not matching one of `patterns`; a no-op when
+    /// there are none.
+    pub(crate) fn allow(self, patterns: &[String]) -> Self {
+        self.restrict(patterns, true)
+    }
+
+    /// Disables every function matching one of `patterns`.
+    pub(crate) fn deny(self, patterns: &[String]) -> Self {
+        self.restrict(patterns, false)
+    }
+
+    /// Replaces the functions an allow or deny list removes with stubs, so a
+    /// program calling one gets a diagnostic naming the function as disabled
+    /// rather than unknown.
+    fn restrict(mut self, patterns: &[String], allow: bool) -> Self {
+        let mut matchers = Vec::new();
+        for pattern in patterns {
+            match Matcher::parse(pattern) {
+                Ok(matcher)
+                    if self
+                        .entries
+                        .iter()
+                        .any(|(_, f)| matcher.matches(f.identifier())) =>
+                {
+                    matchers.push(matcher)
+                }
+                Ok(_) => self
+                    .errors
+                    .push(format!("`{pattern}` matches no registered function")),
+                Err(err) => self.errors.push(err),
+            }
+        }
+        if matchers.is_empty() {
+            return self;
+        }
+
+        self.entries = self
+            .entries
+            .into_iter()
+            .map(|(origin, function)| {
+                let matched = matchers.iter().any(|m| m.matches(function.identifier()));
+                if origin == Origin::Disabled || matched == allow {
+                    return (origin, function);
+                }
+                let function = Wrapped {
+                    disabled: true,
+                    ..Wrapped::new(function.identifier(), Arc::from(function))
+                };
+                (Origin::Disabled, Box::new(function) as Box<dyn Function>)
+            })
+            .collect();
+        self
+    }
+
+    /// Registers `alias` as another name for `target`. Programs calling the
+    /// alias get a deprecation warning pointing them at `target`.
+    ///
+    /// Function identifiers are `&'static str`, so the name of an alias that
+    /// is registered is leaked.
+    pub(crate) fn alias(mut self, alias: &str, target: &str) -> Self {
+        let Some(index) = self.position(target) else {
+            self.errors.push(format!(
+                "cannot alias `{alias}`: no function `{target}` is registered"
+            ));
+            return self;
+        };
+
+        let (origin, function) = self.entries.remove(index);
+        let function: Arc<dyn Function> = Arc::from(function);
+        self.entries.insert(
+            index,
+            (
+                origin,
+                Box::new(Wrapped::new(function.identifier(), function.clone())),
+            ),
+        );
+
+        let deprecation = format!("`{alias}` is deprecated, use `{target}` instead");
+        let alias = Wrapped {
+            deprecation: Some(deprecation),
+            ..Wrapped::new(alias.to_owned().leak(), function)
+        };
+        self.add(Origin::Alias, Box::new(alias))
+    }
+
+    /// Makes calls to `identifier` emit a deprecation warning with `notice`.
+    pub(crate) fn deprecate(mut self, identifier: &str, notice: &str) -> Self {
+        let Some(index) = self.position(identifier) else {
+            self.errors.push(format!(
+                "cannot deprecate `{identifier}`: no such function is registered"
+            ));
+            return self;
+        };
+
+        let (origin, function) = self.entries.remove(index);
+        let function = Wrapped {
+            deprecation: Some(format!("`{identifier}` is deprecated: {notice}")),
+            ..Wrapped::new(function.identifier(), Arc::from(function))
+        };
+        self.entries.insert(index, (origin, Box::new(function)));
+        self
+    }
+
+    fn add(mut self, origin: Origin, function: Box<dyn Function>) -> Self {
+        match self.position(function.identifier()) {
+            Some(_) => self.errors.push(format!(
+                "cannot add `{}`: a function with that name is already registered",
+                function.identifier()
+            )),
+            None => self.entries.push((origin, function)),
+        }
+        self
+    }
+
+    fn position(&self, identifier: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|(_, function)| function.identifier() == identifier)
+    }
+
+    /// Returns the registered functions along with where each comes from.
+    pub(crate) fn build_with_origins(self) -> Result<Vec<(Origin, Box<dyn Function>)>> {
+        if !self.errors.is_empty() {
+            bail!("invalid function registry: {}", self.errors.join("; "));
+        }
+        Ok(self.entries)
+    }
+
+    /// Returns the function list to hand to `compile`.
+    pub(crate) fn build(self) -> Result<Vec<Box<dyn Function>>> {
+        Ok(self
+            .build_with_origins()?
+            .into_iter()
+            .map(|(_, function)| function)
+            .collect())
+    }
+}
+
+/// Named groups usable as `@group` in allow and deny lists.
+const GROUPS: &[(&str, &[&str])] = &[(
+    "io",
+    &[
+        "dns_lookup",
+        "exec",
+        "geoip",
+        "get_env_var",
+        "get_hostname",
+        "http_get",
+        "log",
+        "redis_get",
+        "resolve_addrs",
+        "resolve_ptr",
+        "reverse_dns",
+        "sqlite_lookup",
+    ],
+)];
+
+/// An allow or deny list entry: a function identifier, a glob such as
+/// `parse_*`, or a `@group`.
+enum Matcher {
+    Glob(glob::Pattern),
+    Group(&'static [&'static str]),
+}
+
+impl Matcher {
+    fn parse(pattern: &str) -> Result<Self, String> {
+        match pattern.strip_prefix('@') {
+            Some(group) => GROUPS
+                .iter()
+                .find(|(name, _)| *name == group)
+                .map(|(_, members)| Matcher::Group(members))
+                .ok_or_else(|| format!("unknown function group `{pattern}`")),
+            None => glob::Pattern::new(pattern)
+                .map(Matcher::Glob)
+                .map_err(|err| format!("invalid function pattern `{pattern}`: {err}")),
+        }
+    }
+
+    fn matches(&self, identifier: &str) -> bool {
+        match self {
+            Matcher::Glob(pattern) => pattern.matches(identifier),
+            Matcher::Group(members) => members.contains(&identifier),
+        }
+    }
+}
+
+/// A registered function under a different identifier, one that fails to
+/// compile because it was removed by an allow or deny list, or one whose
+/// calls are reported as deprecated.
+#[derive(Debug)]
+struct Wrapped {
+    identifier: &'static str,
+    function: Arc<dyn Function>,
+    disabled: bool,
+    deprecation: Option<String>,
+}
+
+impl Wrapped {
+    fn new(identifier: &'static str, function: Arc<dyn Function>) -> Self {
+        Self {
+            identifier,
+            function,
+            disabled: false,
+            deprecation: None,
+        }
+    }
+}
+
+/// The stdlib function set with our custom implementations swapped in, plus
+/// any plugin functions and `--alias` names, with the `--deprecate` notices,
+/// restricted by `--allow` and `--deny`.
+///
+/// With `--namespace`, the custom implementations are registered under a
+/// prefix instead, next to the stdlib originals.
+pub(crate) fn configured(args: &RegistryArgs) -> Result<Registry> {
+    debug!("Function groups: {:?}", functions::GROUPS);
+    let registry = functions::register(Registry::stdlib(), args);
+    let registry = args.plugin.iter().try_fold(registry, |registry, path| {
+        Ok::<_, anyhow::Error>(registry.add_plugin_fns(plugin::load(path)?))
+    })?;
+    let registry = args
+        .alias
+        .iter()
+        .fold(registry, |registry, (alias, target)| {
+            registry.alias(alias, target)
+        });
+    let registry = args
+        .deprecate
+        .iter()
+        .fold(registry, |registry, (identifier, notice)| {
+            registry.deprecate(identifier, notice)
+        });
+    Ok(registry.allow(&args.allow).deny(&args.deny))
+}
+
+/// The functions of the [`configured`] registry.
+pub(crate) fn functions(args: &RegistryArgs) -> Result<Vec<Box<dyn Function>>> {
+    configured(args)?.build()
+}
+
+/// Deprecation warnings for the calls in a program, collected through the
+/// compile config while it compiles; see [`compile_config`].
+#[derive(Debug, Default)]
+pub(crate) struct Deprecations(pub(crate) Vec<Diagnostic>);
+
+/// A compile config collecting [`Deprecations`] and giving functions access to
+/// the run's `state`.
+pub(crate) fn compile_config(state: &RunState) -> CompileConfig {
+    let mut config = CompileConfig::default();
+    config.set_custom(Deprecations::default());
+    config.set_custom(state.clone());
+    config
+}
+
+/// The code reported with deprecation warnings.
+const DEPRECATED: usize = 950;
+
+/// Reported when a program calls a function removed from the registry.
+#[derive(Debug)]
+struct DisabledFunction {
+    identifier: &'static str,
+    span: Span,
+}
+
+impl fmt::Display for DisabledFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "call to disabled function `{}`", self.identifier)
+    }
+}
+
+impl std::error::Error for DisabledFunction {}
+
+impl DiagnosticMessage for DisabledFunction {
+    /// Same code as calling an undefined function.
+    fn code(&self) -> usize {
+        105
+    }
+
+    fn labels(&self) -> Vec<Label> {
+        vec![Label::primary("disabled function", self.span)]
+    }
+
+    fn notes(&self) -> Vec<Note> {
+        vec![Note::Basic(
+            "the function was removed by --allow or --deny".to_owned(),
+        )]
+    }
+}
+
+impl Function for Wrapped {
+    fn identifier(&self) -> &'static str {
+        self.identifier
+    }
+
+    fn summary(&self) -> &'static str {
+        self.function.summary()
+    }
+
+    fn usage(&self) -> &'static str {
+        self.function.usage()
+    }
+
+    fn examples(&self) -> &'static [Example] {
+        self.function.examples()
+    }
+
+    fn compile(
+        &self,
+        state: &TypeState,
+        ctx: &mut FunctionCompileContext,
+        arguments: ArgumentList,
+    ) -> Compiled {
+        if self.disabled {
+            return Err(Box::new(DisabledFunction {
+                identifier: self.identifier,
+                span: ctx.span(),
+            }));
+        }
+        if let Some(deprecation) = &self.deprecation {
+            let span = ctx.span();
+            if let Some(Deprecations(warnings)) = ctx.get_external_context_mut() {
+                warnings.push(Diagnostic {
+                    severity: Severity::Warning,
+                    code: DEPRECATED,
+                    message: deprecation.clone(),
+                    labels: vec![Label::primary("deprecated function", span)],
+                    notes: Vec::new(),
+                });
+            }
+        }
+        self.function.compile(state, ctx, arguments)
+    }
+
+    fn parameters(&self) -> &'static [Parameter] {
+        self.function.parameters()
+    }
+
+    fn closure(&self) -> Option<closure::Definition> {
+        self.function.closure()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::functions::Split;
+
+    #[test]
+    fn override_replaces_stdlib() {
+        let functions = Registry::stdlib()
+            .override_fn(Split)
+            .build_with_origins()
+            .unwrap();
+        let splits = functions
+            .iter()
+            .filter(|(_, function)| function.identifier() == "split")
+            .collect::<Vec<_>>();
+
+        assert_eq!(splits.len(), 1);
+        assert_eq!(splits[0].0, Origin::Override);
+        assert_eq!(functions.len(), vrl::stdlib::all().len());
+    }
+
+    #[test]
+    fn namespaced_keeps_stdlib() {
+        let functions = Registry::stdlib()
+            .add_namespaced("custom", Split)
+            .build_with_origins()
+            .unwrap();
+        let origin = |identifier| {
+            functions
+                .iter()
+                .find(|(_, function)| function.identifier() == identifier)
+                .map(|(origin, _)| *origin)
+        };
+
+        assert_eq!(origin("split"), Some(Origin::Stdlib));
+        assert_eq!(origin("custom_split"), Some(Origin::Custom));
+    }
+
+    #[test]
+    fn deny_disables_functions() {
+        let functions = configured(&RegistryArgs::default())
+            .unwrap()
+            .deny(&["@io".to_owned(), "parse_*".to_owned()])
+            .build()
+            .unwrap();
+        let error = |source| match vrl::compiler::compile(source, &functions) {
+            Ok(_) => String::new(),
+            Err(diagnostics) => diagnostics.errors()[0].message.clone(),
+        };
+
+        assert!(error(r#"get_env_var!("HOME")"#).ends_with("disabled function `get_env_var`"));
+        assert!(error(r#"parse_json!("{}")"#).ends_with("disabled function `parse_json`"));
+        assert_eq!(error(r#"upcase("a")"#), "");
+        #[cfg(feature = "networking")]
+        assert!(
+            error(r#"http_get!("http://localhost/")"#).ends_with("disabled function `http_get`")
+        );
+        #[cfg(feature = "networking")]
+        assert!(error(r#"redis_get!("user:1")"#).ends_with("disabled function `redis_get`"));
+        #[cfg(feature = "enrichment")]
+        assert!(error(r#"geoip!("89.160.20.128", "GeoLite2-City.mmdb")"#)
+            .ends_with("disabled function `geoip`"));
+        #[cfg(feature = "enrichment")]
+        assert!(error(r#"sqlite_lookup!("services.db", "SELECT 1")"#)
+            .ends_with("disabled function `sqlite_lookup`"));
+
+        let err = Registry::stdlib().deny(&["no_such_*".to_owned()]).build();
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("matches no registered function"));
+    }
+
+    #[test]
+    fn allow_keeps_only_matches() {
+        let functions = Registry::stdlib()
+            .allow(&["upcase".to_owned()])
+            .build_with_origins()
+            .unwrap();
+
+        for (origin, function) in &functions {
+            let expected = match function.identifier() {
+                "upcase" => Origin::Stdlib,
+                _ => Origin::Disabled,
+            };
+            assert_eq!(*origin, expected, "{}", function.identifier());
+        }
+    }
+
+    #[test]
+    fn aliases_warn() {
+        let functions = Registry::stdlib()
+            .override_fn(Split)
+            .alias("split_str", "split")
+            .deprecate("upcase", "use a custom function")
+            .build()
+            .unwrap();
+        let compile = |source| {
+            let result =
+                crate::program::compile_source(source, &functions, &RunState::default()).unwrap();
+            result
+                .warnings
+                .iter()
+                .map(|warning| (warning.code, warning.message.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            compile(r#"split_str("a b", " ")"#),
+            [(
+                950,
+                "`split_str` is deprecated, use `split` instead".to_owned()
+            )]
+        );
+        assert_eq!(compile(r#"split("a b", " ")"#), []);
+        assert_eq!(
+            compile(r#"upcase("a")"#),
+            [(
+                950,
+                "`upcase` is deprecated: use a custom function".to_owned()
+            )]
+        );
+    }
+
+    #[test]
+    fn aliases_from_the_command_line() {
+        use crate::cli::Cli;
+        use clap::Parser;
+
+        let cli = Cli::parse_from([
+            "vrl-test",
+            "--alias=split_str=split",
+            "--deprecate=upcase=use shout instead",
+            "functions",
+        ]);
+        let functions = functions(&cli.registry).unwrap();
+        let result = crate::program::compile_source(
+            r#".a = split_str("a b", " ")
+            upcase("a")"#,
+            &functions,
+            &RunState::default(),
+        )
+        .unwrap();
+        let warnings = result
+            .warnings
+            .iter()
+            .map(|warning| warning.message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            [
+                "`split_str` is deprecated, use `split` instead",
+                "`upcase` is deprecated: use shout instead"
+            ]
+        );
+
+        let cli = Cli::parse_from(["vrl-test", "--alias=old=no_such_fn", "functions"]);
+        assert!(configured(&cli.registry).unwrap().build().is_err());
+        assert!(Cli::try_parse_from(["vrl-test", "--alias=a-b=split", "functions"]).is_err());
+    }
+
+    #[test]
+    fn collisions_are_errors() {
+        let err = Registry::stdlib().add_fn(Split).build().unwrap_err();
+        assert!(err.to_string().contains("cannot add `split`"));
+
+        let registry = Registry {
+            entries: Vec::new(),
+            errors: Vec::new(),
+        };
+        let err = registry.override_fn(Split).build().unwrap_err();
+        assert!(err.to_string().contains("cannot override `split`"));
+    }
+}
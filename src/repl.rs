@@ -0,0 +1,777 @@
+//! The `repl` subcommand: evaluates expressions line by line against the
+//! registry the other subcommands compile programs with.
+
+use anyhow::{Context as _, Result};
+use log::warn;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, IsTerminal};
+use std::path::{Path, PathBuf};
+use vrl::compiler::runtime::{Runtime, Terminate};
+use vrl::compiler::state::ExternalEnv;
+use vrl::compiler::{compile_with_external, Function, TargetValue, TimeZone, TypeDef};
+use vrl::prelude::{Collection, Kind};
+use vrl::value::kind::{Field, Index};
+use vrl::value::Value;
+
+use crate::arena;
+use crate::describe::{describe, Origin};
+use crate::input::{Decoding, Input};
+use crate::program::{compile_source, new_target};
+use crate::registry::compile_config;
+use crate::state::RunState;
+
+const PROMPT: &str = "> ";
+/// The prompt for the following lines of an expression spanning several.
+const CONTINUATION_PROMPT: &str = "... ";
+
+const COMMANDS: &str = "\
+:reset               go back to the event the session started from
+:load <PATH> [<N>]   start over from the first or Nth event of a file
+:type <EXPR>         show the type of an expression without running it
+:functions           list the functions expressions can call
+:save <PATH>         write the session so far to a script for `repl --replay`
+:help [<FUNCTION>]   show these commands, or the parameters and examples of a function";
+
+/// The commands `:save` records, along with the expressions: the ones
+/// changing the event or printing something worth checking on replay.
+const RECORDED_COMMANDS: &[&str] = &[":reset", ":load", ":type"];
+
+/// Where entered lines are kept between sessions, in the home directory.
+const HISTORY_FILE: &str = ".vrl_test_history";
+
+/// What reading a line got.
+enum Entered {
+    Line(String),
+    /// Ctrl-C was pressed, dropping what was being entered.
+    Cancelled,
+    /// Stdin was closed, or Ctrl-D pressed.
+    Closed,
+}
+
+/// What an expression or command printed. `:save` records it in a comment
+/// below the entry, and `--replay` checks it's printed again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Outcome {
+    /// A result, printed to stdout.
+    Output(String),
+    /// An error, printed to stderr.
+    Error(String),
+}
+
+impl Outcome {
+    const OUTPUT: &'static str = "# => ";
+    const ERROR: &'static str = "# error: ";
+
+    fn print(&self) {
+        match self {
+            Outcome::Output(output) => println!("{output}"),
+            Outcome::Error(error) => eprintln!("Error: {error}"),
+        }
+    }
+
+    /// The comment recording the outcome in a script.
+    fn record(&self) -> String {
+        match self {
+            Outcome::Output(output) => format!("{}{output}", Self::OUTPUT),
+            Outcome::Error(error) => format!("{}{error}", Self::ERROR),
+        }
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        match line.strip_prefix(Self::OUTPUT) {
+            Some(output) => Some(Outcome::Output(output.to_owned())),
+            None => line
+                .strip_prefix(Self::ERROR)
+                .map(|error| Outcome::Error(error.to_owned())),
+        }
+    }
+}
+
+/// Where the lines of a session come from: a line editor with history on a
+/// terminal, or plain lines from a piped stdin or a script being replayed.
+enum Lines {
+    Editor {
+        editor: Box<DefaultEditor>,
+        history: Option<PathBuf>,
+    },
+    Reader(io::Lines<Box<dyn BufRead>>),
+}
+
+impl Lines {
+    fn open() -> Result<Self> {
+        if !io::stdin().is_terminal() {
+            let stdin: Box<dyn BufRead> = Box::new(io::stdin().lock());
+            return Ok(Lines::Reader(stdin.lines()));
+        }
+
+        let mut editor = DefaultEditor::new().context("failed to set up line editing")?;
+        let history = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+        if let Some(history) = &history {
+            // there is no history yet on the first run
+            if let Err(err) = editor.load_history(history) {
+                if !matches!(&err, ReadlineError::Io(err) if err.kind() == io::ErrorKind::NotFound)
+                {
+                    warn!("failed to load history from {}: {err}", history.display());
+                }
+            }
+        }
+        Ok(Lines::Editor {
+            editor: Box::new(editor),
+            history,
+        })
+    }
+
+    /// Reads the next line, showing `prompt` on a terminal.
+    fn next(&mut self, prompt: &str) -> Result<Entered> {
+        match self {
+            Lines::Editor { editor, .. } => match editor.readline(prompt) {
+                Ok(line) => Ok(Entered::Line(line)),
+                Err(ReadlineError::Interrupted) => Ok(Entered::Cancelled),
+                Err(ReadlineError::Eof) => Ok(Entered::Closed),
+                Err(err) => Err(err).context("failed to read a line"),
+            },
+            Lines::Reader(lines) => match lines.next() {
+                Some(line) => Ok(Entered::Line(line.context("failed to read a line")?)),
+                None => Ok(Entered::Closed),
+            },
+        }
+    }
+
+    /// Adds a command or expression, with all of its lines, to the history.
+    fn remember(&mut self, entry: &str) -> Result<()> {
+        if let Lines::Editor { editor, .. } = self {
+            editor.add_history_entry(entry)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the history out for the next session.
+    fn save(&mut self) {
+        if let Lines::Editor {
+            editor,
+            history: Some(history),
+        } = self
+        {
+            if let Err(err) = editor.save_history(history) {
+                warn!("failed to save history to {}: {err}", history.display());
+            }
+        }
+    }
+}
+
+/// An interactive session. Every line is compiled as a program of its own,
+/// resolved against the event the previous lines left, and sharing one
+/// [`RunState`] so stateful functions keep their state between lines.
+pub(crate) struct Session<'a> {
+    /// The entries so far and their outcomes, as `:save` writes them.
+    transcript: Vec<String>,
+    functions: &'a [Box<dyn Function>],
+    /// Where each of `functions` comes from.
+    origins: &'a [Origin],
+    state: RunState,
+    runtime: Runtime,
+    /// The event the session started from, restored by `:reset`.
+    event: Value,
+    target: TargetValue,
+}
+
+impl<'a> Session<'a> {
+    /// Starts a session resolving lines against `event`.
+    pub(crate) fn new(
+        functions: &'a [Box<dyn Function>],
+        origins: &'a [Origin],
+        event: Value,
+    ) -> Self {
+        Self {
+            transcript: Vec::new(),
+            functions,
+            origins,
+            state: RunState::default(),
+            runtime: Runtime::default(),
+            target: new_target(event.clone()),
+            event,
+        }
+    }
+
+    /// Goes back to the event the session started from, dropping the state
+    /// of stateful functions too.
+    pub(crate) fn reset(&mut self) -> Result<()> {
+        self.state.flush()?;
+        self.state = RunState::default();
+        self.target = new_target(self.event.clone());
+        Ok(())
+    }
+
+    /// Starts over from event `number`, counting from 1, of the file at
+    /// `path`, decoded like `run` decodes its inputs: a JSON document or
+    /// NDJSON unless the extension says otherwise. `:reset` then goes back
+    /// to it.
+    pub(crate) fn load(&mut self, path: &Path, number: usize) -> Result<&Value> {
+        let input = Input::File(path.to_owned());
+        let event = input
+            .open(&Decoding::default())?
+            .nth(number.saturating_sub(1))
+            .with_context(|| format!("{input} has no event {number}"))??;
+        self.event = event;
+        self.target = new_target(self.event.clone());
+        Ok(&self.event)
+    }
+
+    /// The identifiers of the functions expressions can call, in order, with
+    /// where each comes from.
+    pub(crate) fn list_functions(&self) -> String {
+        let mut functions = self
+            .functions
+            .iter()
+            .zip(self.origins)
+            .map(|(function, origin)| format!("{} ({})", function.identifier(), origin.as_str()))
+            .collect::<Vec<_>>();
+        functions.sort();
+        functions.join("\n")
+    }
+
+    /// The parameters and examples of function `identifier`.
+    pub(crate) fn help(&self, identifier: &str) -> Option<String> {
+        self.functions
+            .iter()
+            .zip(self.origins)
+            .find(|(function, _)| function.identifier() == identifier)
+            .map(|(function, origin)| describe(function.as_ref(), *origin))
+    }
+
+    /// Writes the expressions and commands entered so far, with what they
+    /// printed, to a script that `repl --replay` runs again.
+    pub(crate) fn save(&self, path: &Path) -> Result<()> {
+        let mut script = self.transcript.join("\n");
+        script.push('\n');
+        fs::write(path, script).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Runs an expression or `:command`, returning what it printed for
+    /// commands that print a single result.
+    fn enter(&mut self, entry: &str) -> Result<Option<Outcome>> {
+        let outcome = match entry.starts_with(':') {
+            true => self.command(entry)?,
+            false => Some(match self.eval(entry) {
+                Some(Ok(value)) => Outcome::Output(serde_json::to_string(&value)?),
+                Some(Err(e)) => Outcome::Error(format!("failed to resolve expression: {e}")),
+                None => Outcome::Error("failed to compile expression".to_owned()),
+            }),
+        };
+
+        let name = entry.split(' ').next().unwrap_or_default();
+        if !entry.starts_with(':') || RECORDED_COMMANDS.contains(&name) {
+            self.transcript.push(entry.to_owned());
+            self.transcript
+                .extend(outcome.as_ref().map(Outcome::record));
+        }
+        Ok(outcome)
+    }
+
+    /// Runs a `:command` line.
+    fn command(&mut self, line: &str) -> Result<Option<Outcome>> {
+        let (name, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+        Ok(match name {
+            ":reset" => {
+                self.reset()?;
+                None
+            }
+            ":load" => {
+                let numbered = argument
+                    .rsplit_once(' ')
+                    .map(|(path, number)| (path.trim(), number.parse::<usize>()));
+                let (path, number) = match numbered {
+                    Some((path, Ok(number))) => (path, number),
+                    _ => (argument, 1),
+                };
+                Some(match self.load(Path::new(path), number) {
+                    Ok(event) => Outcome::Output(serde_json::to_string(event)?),
+                    Err(err) => Outcome::Error(format!("{err:#}")),
+                })
+            }
+            ":type" => Some(match self.type_of(argument) {
+                Some(type_def) => Outcome::Output(describe_type(&type_def)),
+                None => Outcome::Error("failed to compile expression".to_owned()),
+            }),
+            ":save" => {
+                match self.save(Path::new(argument)) {
+                    Ok(()) => eprintln!("saved the session to {argument}"),
+                    Err(err) => eprintln!("Error: {err:#}"),
+                }
+                None
+            }
+            ":functions" => {
+                println!("{}", self.list_functions());
+                None
+            }
+            ":help" if argument.is_empty() => {
+                println!("{COMMANDS}");
+                None
+            }
+            ":help" => {
+                match self.help(argument) {
+                    Some(help) => print!("{help}"),
+                    None => eprintln!("unknown function: {argument}"),
+                }
+                None
+            }
+            _ => {
+                eprintln!("unknown command `{name}`; `:help` lists them");
+                None
+            }
+        })
+    }
+
+    /// Compiles and resolves `source`, or returns `None` after printing the
+    /// diagnostics when it doesn't compile. Warnings, such as an unused
+    /// variable, are expected while experimenting and not shown.
+    ///
+    /// Changes to the event are kept for the next lines, even when the
+    /// program fails after making them.
+    pub(crate) fn eval(&mut self, source: &str) -> Option<Result<Value, Terminate>> {
+        let program = compile_source(source, self.functions, &self.state)?.program;
+        arena::reset();
+        let resolved = self
+            .runtime
+            .resolve(&mut self.target, &program, &TimeZone::default());
+        self.runtime.clear();
+        Some(resolved)
+    }
+
+    /// The type `source` resolves to, found by compiling it without resolving
+    /// it, or `None` after printing the diagnostics when it doesn't compile.
+    /// Unlike a program, the expression may be fallible.
+    pub(crate) fn type_of(&self, source: &str) -> Option<TypeDef> {
+        let compile = |source: &str| {
+            compile_with_external(
+                source,
+                self.functions,
+                &ExternalEnv::default(),
+                compile_config(&self.state),
+            )
+            .map(|result| result.program.final_type_info().result)
+        };
+
+        let type_def = match compile(source) {
+            Ok(type_def) => Some(type_def),
+            // E100 is the error of leaving a fallible expression unhandled;
+            // handling it by aborting leaves the type as it is otherwise
+            Err(diagnostics) if diagnostics.errors().iter().all(|error| error.code == 100) => {
+                compile(&format!("{{\n{source}\n}} ?? {{ abort }}"))
+                    .ok()
+                    .map(TypeDef::fallible)
+            }
+            Err(_) => None,
+        };
+        // the diagnostics of the expression as it was entered
+        type_def.or_else(|| compile_source(source, self.functions, &self.state).and(None))
+    }
+
+    /// Reads lines from stdin until it's closed, or from the script at
+    /// `replay`, printing the result of each expression as JSON, then
+    /// flushes the state of the session. Lines starting with `:` are
+    /// commands, listed by `:help`.
+    ///
+    /// An expression with unclosed brackets or strings continues on the next
+    /// lines, until they are closed. On a terminal, lines are edited like in a
+    /// shell, with the history of previous sessions in `~/.vrl_test_history`
+    /// and searchable with Ctrl-R.
+    ///
+    /// Returns whether every outcome a replayed script recorded was printed
+    /// again.
+    pub(crate) fn run(mut self, replay: Option<&Path>) -> Result<bool> {
+        let mut lines = match replay {
+            Some(path) => {
+                let file = File::open(path)
+                    .with_context(|| format!("failed to open {}", path.display()))?;
+                let reader: Box<dyn BufRead> = Box::new(BufReader::new(file));
+                Lines::Reader(reader.lines())
+            }
+            None => Lines::open()?,
+        };
+        let script = replay.map_or_else(|| "<stdin>".into(), Path::to_string_lossy);
+        let matched = self.read(&mut lines, &script)?;
+        lines.save();
+        self.state.flush()?;
+        Ok(matched)
+    }
+
+    /// Runs the entries read from `lines`, checking them against the
+    /// outcomes recorded below them, and returns whether they all matched.
+    fn read(&mut self, lines: &mut Lines, script: &str) -> Result<bool> {
+        let mut entry = String::new();
+        let mut outcome = None;
+        let mut matched = true;
+        let mut number = 0;
+        loop {
+            let prompt = match entry.is_empty() {
+                true => PROMPT,
+                false => CONTINUATION_PROMPT,
+            };
+            let line = match lines.next(prompt)? {
+                Entered::Line(line) => line,
+                Entered::Cancelled => {
+                    entry.clear();
+                    continue;
+                }
+                Entered::Closed => break,
+            };
+            number += 1;
+
+            if entry.is_empty() {
+                let line = line.trim();
+                if let Some(expected) = Outcome::parse(line) {
+                    if outcome.as_ref().is_some_and(|outcome| *outcome != expected) {
+                        let outcome = outcome.as_ref().map(Outcome::record).unwrap_or_default();
+                        eprintln!("{script}:{number}: expected `{line}`, got `{outcome}`");
+                        matched = false;
+                    }
+                    outcome = None;
+                    continue;
+                }
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                if line.starts_with(':') {
+                    lines.remember(line)?;
+                    outcome = self.enter(line)?;
+                    outcome.iter().for_each(Outcome::print);
+                    continue;
+                }
+            }
+
+            entry.push_str(&line);
+            entry.push('\n');
+            if is_complete(&entry) {
+                lines.remember(entry.trim_end())?;
+                outcome = self.enter(entry.trim_end())?;
+                outcome.iter().for_each(Outcome::print);
+                entry.clear();
+            }
+        }
+        // an unfinished expression left at the end fails to compile, which
+        // is better shown than dropped
+        if !entry.trim().is_empty() {
+            self.enter(entry.trim_end())?
+                .iter()
+                .for_each(Outcome::print);
+        }
+        Ok(matched)
+    }
+}
+
+/// Whether every bracket, block, string and quoted literal such as `r'...'`
+/// opened in `source` is closed again, ignoring comments. Unbalanced closing
+/// brackets are left for the compiler to report.
+fn is_complete(source: &str) -> bool {
+    let mut depth = 0i32;
+    let mut previous = None;
+    let mut chars = source.chars();
+    while let Some(c) = chars.next() {
+        let closed = match c {
+            '"' => closes(&mut chars, '"'),
+            '\'' if matches!(previous, Some('s' | 'r' | 't')) => closes(&mut chars, '\''),
+            '#' => {
+                chars.by_ref().find(|&c| c == '\n');
+                true
+            }
+            '(' | '[' | '{' => {
+                depth += 1;
+                true
+            }
+            ')' | ']' | '}' => {
+                depth -= 1;
+                true
+            }
+            _ => true,
+        };
+        if !closed {
+            return false;
+        }
+        previous = Some(c);
+    }
+    depth <= 0
+}
+
+/// Skips to the `quote` closing a literal, after any escaped ones.
+fn closes(chars: &mut std::str::Chars<'_>, quote: char) -> bool {
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            c if c == quote => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Renders a type the way VRL documents them, with the element and field
+/// types of collections, e.g. `array of string (fallible)`.
+fn describe_type(type_def: &TypeDef) -> String {
+    let kind = describe_kind(type_def.kind());
+    match type_def.is_fallible() {
+        true => format!("{kind} (fallible)"),
+        false => kind,
+    }
+}
+
+fn describe_kind(kind: &Kind) -> String {
+    let kind = kind.without_undefined();
+    if kind == Kind::any().without_undefined() {
+        return "any".to_owned();
+    }
+    // what parsing JSON returns
+    if kind == Kind::json() {
+        return "json".to_owned();
+    }
+
+    let mut kinds = Vec::new();
+    let primitives = [
+        (kind.contains_bytes(), "string"),
+        (kind.contains_integer(), "integer"),
+        (kind.contains_float(), "float"),
+        (kind.contains_boolean(), "boolean"),
+        (kind.contains_timestamp(), "timestamp"),
+        (kind.contains_regex(), "regex"),
+    ];
+    kinds.extend(
+        primitives
+            .into_iter()
+            .filter(|(contains, _)| *contains)
+            .map(|(_, name)| name.to_owned()),
+    );
+    if let Some(array) = kind.as_array() {
+        kinds.push(describe_array(array));
+    }
+    if let Some(object) = kind.as_object() {
+        kinds.push(describe_object(object));
+    }
+    if kind.contains_null() {
+        kinds.push("null".to_owned());
+    }
+
+    match kinds.is_empty() {
+        true => "never".to_owned(),
+        false => kinds.join(" or "),
+    }
+}
+
+/// `[integer, string]` when every element is known, `array of string` when
+/// none are, and `[integer, ...string]` for the rest after known ones.
+fn describe_array(array: &Collection<Index>) -> String {
+    let unknown = array.unknown_kind();
+    if array.known().is_empty() {
+        return match unknown.contains_any_defined() {
+            true => format!("array of {}", describe_element(&unknown)),
+            false => "[]".to_owned(),
+        };
+    }
+
+    let mut elements = array
+        .known()
+        .values()
+        .map(describe_element)
+        .collect::<Vec<_>>();
+    if unknown.contains_any_defined() {
+        elements.push(format!("...{}", describe_element(&unknown)));
+    }
+    format!("[{}]", elements.join(", "))
+}
+
+/// `{ a: string, b?: integer }` for known fields, `b` maybe missing, and
+/// `object of string` when none are.
+fn describe_object(object: &Collection<Field>) -> String {
+    let unknown = object.unknown_kind();
+    if object.known().is_empty() {
+        return match unknown.contains_any_defined() {
+            true => format!("object of {}", describe_element(&unknown)),
+            false => "{}".to_owned(),
+        };
+    }
+
+    let mut fields = object
+        .known()
+        .iter()
+        .map(|(field, kind)| {
+            let optional = if kind.contains_undefined() { "?" } else { "" };
+            format!("{field}{optional}: {}", describe_element(kind))
+        })
+        .collect::<Vec<_>>();
+    if unknown.contains_any_defined() {
+        fields.push(format!("...: {}", describe_element(&unknown)));
+    }
+    format!("{{ {} }}", fields.join(", "))
+}
+
+/// The kind of an element or field, in parentheses when it's one of several.
+fn describe_element(kind: &Kind) -> String {
+    let kind = describe_kind(kind);
+    match kind.contains(" or ") {
+        true => format!("({kind})"),
+        false => kind,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::functions::Split;
+    use crate::registry::Registry;
+    use vrl::value;
+
+    /// The stdlib with split overridden, and where each function comes from.
+    fn functions() -> (Vec<Box<dyn Function>>, Vec<Origin>) {
+        let functions = Registry::stdlib().override_fn(Split);
+        let (origins, functions) = functions.build_with_origins().unwrap().into_iter().unzip();
+        (functions, origins)
+    }
+
+    #[test]
+    fn resolves_lines_against_the_event() {
+        let (functions, origins) = functions();
+        let mut session = Session::new(&functions, &origins, value!({"message": "a,b"}));
+
+        let split = session.eval(r#"split(string!(.message), ",", 1)"#).unwrap();
+        assert_eq!(split.unwrap(), value!(["a,b"]));
+        assert!(session.eval("abort").unwrap().is_err());
+        assert!(session.eval("nope(").is_none());
+        assert_eq!(session.eval(".message").unwrap().unwrap(), value!("a,b"));
+    }
+
+    #[test]
+    fn keeps_changes_to_the_event() {
+        let (functions, origins) = functions();
+        let mut session = Session::new(&functions, &origins, value!({"a": 1}));
+
+        assert!(session.eval(".b = 2").unwrap().is_ok());
+        assert!(session.eval(".c = int!(.b) + 1").unwrap().is_ok());
+        assert_eq!(
+            session.eval(".").unwrap().unwrap(),
+            value!({"a": 1, "b": 2, "c": 3})
+        );
+
+        session.enter(":reset").unwrap();
+        assert_eq!(session.eval(".").unwrap().unwrap(), value!({"a": 1}));
+    }
+
+    #[test]
+    fn infers_types_without_resolving() {
+        let (functions, origins) = functions();
+        let mut session = Session::new(&functions, &origins, value!({}));
+        let type_of = |source| describe_type(&session.type_of(source).unwrap());
+
+        assert_eq!(type_of(r#"split("a,b", ",")"#), "array of string");
+        assert_eq!(
+            type_of(r#"split("a,b", ",", max_segments: 1)"#),
+            "array of string (fallible)"
+        );
+        assert_eq!(type_of(r#"parse_json("{}")"#), "json (fallible)");
+        assert_eq!(type_of(".a"), "any");
+        assert_eq!(type_of(r#"x = 1; [x, "a"]"#), "[integer, string]");
+        assert_eq!(
+            type_of(r#"b = if true { null } else { 1.5 }; {"a": 1, "b": b}"#),
+            "{ a: integer, b: (float or null) }"
+        );
+        assert!(session.type_of("nope(").is_none());
+        // the event is left alone
+        assert!(session.type_of(".a = 1").is_some());
+        assert_eq!(session.eval(".").unwrap().unwrap(), value!({}));
+    }
+
+    #[test]
+    fn loads_events_from_files() {
+        let path =
+            std::env::temp_dir().join(format!("vrl-test-{}-load.ndjson", std::process::id()));
+        std::fs::write(&path, "{\"a\": 1}\n{\"a\": 2}\n").unwrap();
+        let (functions, origins) = functions();
+        let mut session = Session::new(&functions, &origins, value!({}));
+
+        assert_eq!(session.load(&path, 2).unwrap(), &value!({"a": 2}));
+        assert!(session.eval(".b = 3").unwrap().is_ok());
+        session.reset().unwrap();
+        assert_eq!(session.eval(".").unwrap().unwrap(), value!({"a": 2}));
+
+        assert_eq!(session.load(&path, 1).unwrap(), &value!({"a": 1}));
+        assert!(session.load(&path, 3).is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(session.load(&path, 1).is_err());
+        assert_eq!(session.eval(".").unwrap().unwrap(), value!({"a": 1}));
+    }
+
+    #[test]
+    fn completes_balanced_input() {
+        assert!(is_complete(".a = 1\n"));
+        assert!(is_complete("if .a == 1 {\n  .b = [1, 2]\n}\n"));
+        assert!(!is_complete("if .a == 1 {\n"));
+        assert!(!is_complete("map_values(.) -> |value| {\n  [value,\n"));
+        assert!(!is_complete("\"a {\n"));
+        assert!(is_complete("\"{ \\\" (\"\n"));
+        assert!(is_complete("split(.a, r'[\\)]')\n"));
+        assert!(!is_complete("s'it\\'s\n"));
+        assert!(is_complete("map_keys(.) -> |key| { key } # {\n"));
+        assert!(is_complete("}\n"));
+    }
+
+    #[test]
+    fn describes_functions() {
+        let (functions, origins) = functions();
+        let session = Session::new(&functions, &origins, value!({}));
+
+        let list = session.list_functions();
+        assert!(list.contains("\nsplit (override)\n"));
+        assert!(list.contains("\nupcase (stdlib)\n"));
+        let help = session.help("split").unwrap();
+        assert!(help.starts_with("split (override)\n"));
+        assert!(help.contains("  max_segments: integer (optional)\n"));
+        assert!(session.help("nope").is_none());
+    }
+
+    #[test]
+    fn replays_saved_sessions() {
+        let path =
+            std::env::temp_dir().join(format!("vrl-test-{}-session.vrl", std::process::id()));
+        let (functions, origins) = functions();
+        let mut session = Session::new(&functions, &origins, value!({}));
+        for entry in [
+            ".a = 1",
+            "if true {\n  .b = 2\n}",
+            ":type 1 + 1",
+            "to_int!(\"x\")",
+            ":help",
+        ] {
+            session.enter(entry).unwrap();
+        }
+        session.save(&path).unwrap();
+
+        let script = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            script,
+            r#".a = 1
+# => 1
+if true {
+  .b = 2
+}
+# => 2
+:type 1 + 1
+# => integer
+to_int!("x")
+# error: failed to resolve expression: function call error for "to_int" at (0:12): Invalid integer "x": invalid digit found in string
+"#
+        );
+        let replay = |script: String| {
+            let reader: Box<dyn BufRead> = Box::new(io::Cursor::new(script));
+            let mut session = Session::new(&functions, &origins, value!({}));
+            session
+                .read(&mut Lines::Reader(reader.lines()), "session.vrl")
+                .unwrap()
+        };
+        assert!(replay(script.clone()));
+        assert!(!replay(script.replace("# => 2", "# => 3")));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,166 @@
+//! State that functions keep across the events of a run, such as counters or
+//! caches.
+//!
+//! Every compile of a run shares one [`RunState`], found in the compile
+//! config. A function creates its state while it is compiled, using
+//! [`RunState::get_or_init`], and moves the returned `Arc` into its
+//! expression; every call, in every stage of the pipeline, then sees the same
+//! state. A recompile in `--watch` mode starts from a new `RunState`.
+//!
+//! State is `Send + Sync` and changed through interior mutability (atomics or
+//! a `Mutex`), since an expression is only ever borrowed while it resolves: a
+//! run resolving events on several threads shares the state between all of
+//! them. Once the last event is processed, [`RunState::flush`] flushes every
+//! state once, in the order they were created.
+//!
+//! Functions that read a file while they're compiled, such as the database
+//! of `geoip`, record it with [`RunState::record_input`], so that `compile`
+//! knows when a program it remembers needs compiling again.
+
+use anyhow::{Context as _, Result};
+use std::any::Any;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// State kept by a function for the duration of a run.
+pub(crate) trait FunctionState: Any + Send + Sync {
+    /// Called once when the run ends, e.g. to write out what was collected.
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct Entry {
+    key: &'static str,
+    state: Arc<dyn Any + Send + Sync>,
+    flush: Arc<dyn FunctionState>,
+}
+
+/// The files read by functions while they were compiled.
+#[derive(Default)]
+struct Inputs(Mutex<Vec<PathBuf>>);
+
+impl FunctionState for Inputs {}
+
+/// The state of every function in a run.
+#[derive(Clone, Default)]
+pub(crate) struct RunState {
+    entries: Arc<Mutex<Vec<Entry>>>,
+}
+
+impl RunState {
+    /// Returns the state of type `T` stored under `key`, usually the
+    /// function's identifier, creating it with `init` on first use.
+    pub(crate) fn get_or_init<T: FunctionState>(
+        &self,
+        key: &'static str,
+        init: impl FnOnce() -> T,
+    ) -> Arc<T> {
+        let mut entries = self.entries.lock().expect("run state lock poisoned");
+        let existing = entries
+            .iter()
+            .filter(|entry| entry.key == key)
+            .find_map(|entry| entry.state.clone().downcast::<T>().ok());
+        if let Some(state) = existing {
+            return state;
+        }
+
+        let state = Arc::new(init());
+        entries.push(Entry {
+            key,
+            state: state.clone(),
+            flush: state.clone(),
+        });
+        state
+    }
+
+    /// Records that compiling a function read the file at `path`.
+    #[cfg(feature = "enrichment")]
+    pub(crate) fn record_input(&self, path: &std::path::Path) {
+        let inputs = self.get_or_init("inputs", Inputs::default);
+        let mut inputs = inputs.0.lock().expect("inputs lock poisoned");
+        if !inputs.iter().any(|input| input == path) {
+            inputs.push(path.to_owned());
+        }
+    }
+
+    /// The files recorded with [`RunState::record_input`], in the order they
+    /// were first read.
+    pub(crate) fn inputs(&self) -> Vec<PathBuf> {
+        let inputs = self.get_or_init("inputs", Inputs::default);
+        let inputs = inputs.0.lock().expect("inputs lock poisoned");
+        inputs.clone()
+    }
+
+    /// Flushes every state, stopping at the first that fails.
+    pub(crate) fn flush(&self) -> Result<()> {
+        let entries = self.entries.lock().expect("run state lock poisoned");
+        for entry in entries.iter() {
+            entry
+                .flush
+                .flush()
+                .with_context(|| format!("failed to flush the state of `{}`", entry.key))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Flag(AtomicBool);
+
+    impl FunctionState for Flag {
+        fn flush(&self) -> Result<()> {
+            self.0.store(true, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct Count(AtomicUsize);
+
+    impl FunctionState for Count {}
+
+    #[test]
+    fn shares_state_by_key_and_type() {
+        let state = RunState::default();
+        let count = state.get_or_init("count", Count::default);
+        count.0.fetch_add(1, Ordering::Relaxed);
+
+        let again = state.clone().get_or_init("count", Count::default);
+        assert_eq!(again.0.load(Ordering::Relaxed), 1);
+        let other = state.get_or_init("other", Count::default);
+        assert_eq!(other.0.load(Ordering::Relaxed), 0);
+        state.get_or_init("count", Flag::default);
+        assert_eq!(state.entries.lock().unwrap().len(), 3);
+    }
+
+    #[cfg(feature = "enrichment")]
+    #[test]
+    fn records_every_input_once() {
+        let state = RunState::default();
+        state.record_input("a.db".as_ref());
+        state.record_input("b.db".as_ref());
+        state.clone().record_input("a.db".as_ref());
+
+        assert_eq!(
+            state.inputs(),
+            [PathBuf::from("a.db"), PathBuf::from("b.db")]
+        );
+    }
+
+    #[test]
+    fn flushes_every_state() {
+        let state = RunState::default();
+        let first = state.get_or_init("first", Flag::default);
+        let second = state.get_or_init("second", Flag::default);
+
+        state.flush().unwrap();
+        assert!(first.0.load(Ordering::Relaxed));
+        assert!(second.0.load(Ordering::Relaxed));
+    }
+}
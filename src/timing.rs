@@ -0,0 +1,128 @@
+use clap::ValueEnum;
+use serde_json::json;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use crate::pipeline::Stage;
+
+/// How the timing report is rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum TimingFormat {
+    Text,
+    Json,
+}
+
+/// Compile and resolve durations collected over a run.
+#[derive(Debug, Default)]
+pub(crate) struct TimingReport {
+    stages: Vec<(String, Duration, Duration)>,
+    events: Vec<Duration>,
+    total: Duration,
+}
+
+impl TimingReport {
+    /// Records the time taken to run a single event through the whole pipeline.
+    pub(crate) fn record_event(&mut self, elapsed: Duration) {
+        self.events.push(elapsed);
+    }
+
+    /// Copies per-stage compile and resolve totals and sets the wall-clock total.
+    pub(crate) fn finish(&mut self, stages: &[Stage], total: Duration) {
+        self.stages = stages
+            .iter()
+            .map(|stage| (stage.name.clone(), stage.compile_time, stage.resolve_time))
+            .collect();
+        self.total = total;
+    }
+
+    fn compile_total(&self) -> Duration {
+        self.stages.iter().map(|(_, compile, _)| *compile).sum()
+    }
+
+    fn resolve_total(&self) -> Duration {
+        self.events.iter().sum()
+    }
+
+    fn resolve_mean(&self) -> Duration {
+        match u32::try_from(self.events.len()) {
+            Ok(0) | Err(_) => Duration::ZERO,
+            Ok(count) => self.resolve_total() / count,
+        }
+    }
+
+    pub(crate) fn render(&self, format: TimingFormat) -> String {
+        match format {
+            TimingFormat::Text => self.render_text(),
+            TimingFormat::Json => self.to_json().to_string(),
+        }
+    }
+
+    fn render_text(&self) -> String {
+        let mut out = String::new();
+
+        let _ = writeln!(out, "compile: {:?}", self.compile_total());
+        for (name, compile, resolve) in &self.stages {
+            let _ = writeln!(out, "  {name}: compile {compile:?}, resolve {resolve:?}");
+        }
+        let _ = writeln!(
+            out,
+            "resolve: {:?} over {} events (mean {:?}, min {:?}, max {:?})",
+            self.resolve_total(),
+            self.events.len(),
+            self.resolve_mean(),
+            self.events.iter().min().copied().unwrap_or_default(),
+            self.events.iter().max().copied().unwrap_or_default(),
+        );
+        let _ = write!(out, "total: {:?}", self.total);
+
+        out
+    }
+
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        let micros = |duration: Duration| duration.as_micros() as u64;
+
+        json!({
+            "compile_us": micros(self.compile_total()),
+            "stages": self.stages.iter().map(|(name, compile, resolve)| json!({
+                "name": name,
+                "compile_us": micros(*compile),
+                "resolve_us": micros(*resolve),
+            })).collect::<Vec<_>>(),
+            "resolve": {
+                "events": self.events.len(),
+                "total_us": micros(self.resolve_total()),
+                "mean_us": micros(self.resolve_mean()),
+                "min_us": micros(self.events.iter().min().copied().unwrap_or_default()),
+                "max_us": micros(self.events.iter().max().copied().unwrap_or_default()),
+            },
+            "total_us": micros(self.total),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolve_stats() {
+        let mut report = TimingReport::default();
+        report.record_event(Duration::from_micros(10));
+        report.record_event(Duration::from_micros(30));
+
+        let json = report.to_json();
+
+        assert_eq!(json["resolve"]["events"], 2);
+        assert_eq!(json["resolve"]["total_us"], 40);
+        assert_eq!(json["resolve"]["mean_us"], 20);
+        assert_eq!(json["resolve"]["min_us"], 10);
+        assert_eq!(json["resolve"]["max_us"], 30);
+    }
+
+    #[test]
+    fn empty_report() {
+        let report = TimingReport::default();
+
+        assert_eq!(report.to_json()["resolve"]["mean_us"], 0);
+    }
+}
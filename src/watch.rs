@@ -0,0 +1,48 @@
+use log::warn;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often the watched files' modification times are polled.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Calls `on_change` with the contents of every file in `paths` once up front
+/// and again each time any of their modification times change. Runs until the
+/// process is killed.
+///
+/// Read failures (e.g. while an editor replaces a file) are logged and the
+/// files are retried on the next poll.
+pub(crate) fn watch(paths: &[PathBuf], mut on_change: impl FnMut(&[String])) -> ! {
+    let mut last_modified: Vec<Option<SystemTime>> = vec![None; paths.len()];
+
+    loop {
+        let modified = paths
+            .iter()
+            .map(|path| {
+                fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .map_err(|err| warn!("failed to stat {}: {err}", path.display()))
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+
+        if modified.iter().all(Option::is_some) && modified != last_modified {
+            let sources = paths
+                .iter()
+                .map(|path| {
+                    fs::read_to_string(path)
+                        .map_err(|err| warn!("failed to read {}: {err}", path.display()))
+                        .ok()
+                })
+                .collect::<Option<Vec<_>>>();
+
+            if let Some(sources) = sources {
+                last_modified = modified;
+                on_change(&sources);
+            }
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}